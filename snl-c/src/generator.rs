@@ -0,0 +1,520 @@
+use crate::runtime::{BOUNDS_CHECK, RUNTIME};
+use snl_ir::scope::{self, Binding, Result, Scope};
+use snl_ir::{ScalarType, Type};
+use snl_utils::ast::*;
+use snl_utils::options::CodegenOptions;
+
+/// Variable in the frame of a procedure at `level`
+struct Variable {
+    level: usize,
+    is_var: bool,
+    ty: Type,
+}
+
+/// Procedure whose own frame is at `level`, generated as `snl_<name>`
+struct Procedure {
+    level: usize,
+    name: String,
+    params: Vec<(bool, Type)>,
+}
+
+struct Generator {
+    scope: Scope<Variable, Procedure>,
+    /// Structured types with the name of their typedef
+    types: Vec<(Type, String)>,
+    typedefs: String,
+    frames: String,
+    prototypes: String,
+    functions: String,
+    /// Body of the function being generated
+    body: String,
+    indent: usize,
+    /// Nesting level of the block being generated, 0 for the main program
+    level: usize,
+    /// Names of the enclosing procedures
+    path: Vec<String>,
+    options: CodegenOptions,
+}
+
+/// Generate a self-contained C file from a semantically checked program.
+///
+/// Each procedure is lifted to a top level function `snl_<path>` whose variables live in a
+/// `struct frame_<path>`. The frame starts with `up`, the frame of the lexically enclosing
+/// procedure, which callers pass as the first argument. `var` params are stored as pointers,
+/// arrays and records become structs so they are copied on assignment. Integers wrap on
+/// overflow when compiled with `-fwrapv`.
+pub fn generate(program: &Positional<Program>, options: CodegenOptions) -> Result<String> {
+    let mut generator = Generator {
+        scope: Default::default(),
+        types: Default::default(),
+        typedefs: Default::default(),
+        frames: Default::default(),
+        prototypes: Default::default(),
+        functions: Default::default(),
+        body: Default::default(),
+        indent: 1,
+        level: 0,
+        path: Default::default(),
+        options,
+    };
+    generator.generate_block(
+        "program",
+        "static void snl_program(void)".to_owned(),
+        vec!["void *up;".to_owned()],
+        vec!["frame.up = NULL;".to_owned()],
+        &program.declare,
+        &program.body,
+    )?;
+
+    let mut out = format!("/* program {} */\n{}", program.name, RUNTIME);
+    if options.bounds_check {
+        out.push_str(BOUNDS_CHECK);
+    }
+    out.push('\n');
+    for section in [&generator.typedefs, &generator.frames, &generator.prototypes].iter() {
+        if !section.is_empty() {
+            out.push_str(section);
+            out.push('\n');
+        }
+    }
+    out.push_str(&generator.functions);
+    out.push_str("int main(void) {\n    snl_program();\n    return 0;\n}\n");
+    Ok(out)
+}
+
+fn scalar_type(ty: ScalarType) -> &'static str {
+    match ty {
+        ScalarType::Integer => "int64_t",
+        ScalarType::Char => "char",
+    }
+}
+
+impl Generator {
+    fn line<S: AsRef<str>>(&mut self, line: S) {
+        for _ in 0..self.indent {
+            self.body.push_str("    ");
+        }
+        self.body.push_str(line.as_ref());
+        self.body.push('\n');
+    }
+
+    /// Name of a C type, declaring a typedef for structured types on first use
+    fn c_type(&mut self, ty: &Type) -> String {
+        match ty {
+            Type::Scalar(ty) => scalar_type(*ty).to_owned(),
+            _ => {
+                if let Some((_, name)) = self.types.iter().find(|(t, _)| t == ty) {
+                    return name.clone();
+                }
+                let members = match ty {
+                    // empty arrays are not valid C
+                    Type::Array { base, .. } => format!(" {} at[{}];", scalar_type(*base), ty.size().max(1)),
+                    Type::Record(fields) => fields.iter()
+                        .map(|(name, ty)| format!(" {} f_{};", self.c_type(ty), name))
+                        .collect(),
+                    Type::Scalar(_) => unreachable!(),
+                };
+                let name = format!("snl_type_{}", self.types.len());
+                self.typedefs.push_str(&format!("/* {} */\ntypedef struct {{{} }} {};\n", ty, members, name));
+                self.types.push((ty.clone(), name.clone()));
+                name
+            }
+        }
+    }
+
+    /// Generate the frame and function of a block.
+    ///
+    /// `fields` and `setup` describe the static link and params, locals are appended by declarations.
+    fn generate_block(&mut self, name: &str, signature: String, mut fields: Vec<String>, setup: Vec<String>,
+                      declare: &ProgramDeclare, body: &StatementList) -> Result<()> {
+        for t in declare.type_declare.iter() {
+            let ty = self.scope.resolve_type(&t.base)?;
+            self.scope.insert(t.name().to_owned(), Binding::Type(ty));
+        }
+
+        for v in declare.variable_declare.iter() {
+            let ty = self.scope.resolve_type(&v.type_name)?;
+            let c_type = self.c_type(&ty);
+            for id in v.identifiers.iter() {
+                fields.push(format!("{} v_{};", c_type, id.inner()));
+                self.scope.insert(id.inner().clone(), Binding::Variable(Variable { level: self.level, is_var: false, ty: ty.clone() }));
+            }
+        }
+
+        for p in declare.procedure_declare.iter() {
+            self.generate_procedure(p)?;
+        }
+
+        self.frames.push_str(&format!("struct frame_{} {{\n", name));
+        for field in fields {
+            self.frames.push_str(&format!("    {}\n", field));
+        }
+        self.frames.push_str("};\n");
+        self.prototypes.push_str(&format!("{};\n", signature));
+
+        // a zeroed frame gives every local its initial value
+        self.line(format!("struct frame_{} frame;", name));
+        self.line("memset(&frame, 0, sizeof frame);");
+        for line in setup {
+            self.line(line);
+        }
+        self.generate_statement_list(body)?;
+
+        let body = std::mem::take(&mut self.body);
+        self.functions.push_str(&format!("{} {{\n{}}}\n\n", signature, body));
+        Ok(())
+    }
+
+    fn generate_procedure(&mut self, p: &Positional<ProcedureDeclare>) -> Result<()> {
+        let parent = if self.path.is_empty() { "program".to_owned() } else { self.path.join("_") };
+        self.path.push(p.name().to_owned());
+        let name = self.path.join("_");
+
+        let mut arguments = vec![format!("struct frame_{} *up", parent)];
+        let mut fields = vec![format!("struct frame_{} *up;", parent)];
+        let mut setup = vec!["frame.up = up;".to_owned()];
+        let mut params = Vec::new();
+        let mut variables = Vec::new();
+        for param in p.params.iter() {
+            let ty = self.scope.resolve_type(&param.definition.type_name)?;
+            let c_type = self.c_type(&ty);
+            let pointer = if param.is_var { "*" } else { "" };
+            for id in param.definition.identifiers.iter() {
+                arguments.push(format!("{} {}a_{}", c_type, pointer, id.inner()));
+                fields.push(format!("{} {}v_{};", c_type, pointer, id.inner()));
+                setup.push(format!("frame.v_{0} = a_{0};", id.inner()));
+                params.push((param.is_var, ty.clone()));
+                variables.push((id.inner().clone(), param.is_var, ty.clone()));
+            }
+        }
+        self.scope.insert(p.name().to_owned(), Binding::Procedure(Procedure { level: self.level + 1, name: name.clone(), params }));
+
+        self.scope.step_in();
+        self.level += 1;
+        let body = std::mem::take(&mut self.body);
+        for (id, is_var, ty) in variables {
+            self.scope.insert(id, Binding::Variable(Variable { level: self.level, is_var, ty }));
+        }
+
+        let signature = format!("static void snl_{}({})", name, arguments.join(", "));
+        self.generate_block(&name, signature, fields, setup, &p.declare, &p.body)?;
+
+        self.body = body;
+        self.level -= 1;
+        self.path.pop();
+        self.scope.step_out();
+        Ok(())
+    }
+
+    fn generate_statement_list(&mut self, list: &StatementList) -> Result<()> {
+        for statement in list.iter() {
+            self.generate_statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn generate_statement(&mut self, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::Conditional(con) => {
+                let condition = self.generate_condition(&con.condition)?;
+                self.line(format!("if ({}) {{", condition));
+                self.indent += 1;
+                self.generate_statement_list(&con.body)?;
+                self.indent -= 1;
+                if !con.else_body.is_empty() {
+                    self.line("} else {");
+                    self.indent += 1;
+                    self.generate_statement_list(&con.else_body)?;
+                    self.indent -= 1;
+                }
+                self.line("}");
+            }
+            Statement::Loop(lo) => {
+                let condition = self.generate_condition(&lo.condition)?;
+                self.line(format!("while ({}) {{", condition));
+                self.indent += 1;
+                self.generate_statement_list(&lo.body)?;
+                self.indent -= 1;
+                self.line("}");
+            }
+            Statement::Input(input) => {
+                let repr = scope::input_variable(input);
+                let (lvalue, ty) = self.generate_access(&repr)?;
+                let (line, column) = input.position();
+                match scope::scalar(&ty, input)? {
+                    ScalarType::Integer => self.line(format!("{} = snl_read_integer({}, {});", lvalue, line, column)),
+                    ScalarType::Char => self.line(format!("{} = snl_read_char({}, {});", lvalue, line, column)),
+                }
+            }
+            Statement::Output(output) => {
+                let (value, ty) = self.generate_expression(output)?;
+                match ty {
+                    ScalarType::Integer => self.line(format!("snl_write_integer({});", value)),
+                    ScalarType::Char => self.line(format!("snl_write_char({});", value)),
+                }
+            }
+            Statement::Return(ret) => {
+                // the value has no receiver, but a division in it may still fail
+                let (value, _) = self.generate_expression(ret)?;
+                self.line(format!("(void) {};", value));
+                self.line("return;");
+            }
+            Statement::Assign(assign) => {
+                let (lvalue, ty) = self.generate_access(&assign.variable)?;
+                let value = if ty.scalar().is_some() {
+                    self.generate_expression(&assign.value)?.0
+                } else {
+                    self.generate_access(scope::structured_source(assign)?)?.0
+                };
+                self.line(format!("{} = {};", lvalue, value));
+            }
+            Statement::Call(call) => self.generate_call(call)?,
+            Statement::Error(error) => return Err(scope::syntax_error(error)),
+        }
+        Ok(())
+    }
+
+    fn generate_call(&mut self, call: &Positional<CallStatement>) -> Result<()> {
+        let Procedure { level, name, params } = self.scope.procedure(call)?;
+        let (level, name, params) = (*level, name.clone(), params.clone());
+
+        // the environment of the callee is the frame of its parent, at `level - 1`
+        let mut arguments = vec![match self.level + 1 - level {
+            0 => "&frame".to_owned(),
+            depth => format!("frame{}", "->up".repeat(depth).replacen("->", ".", 1)),
+        }];
+        for ((is_var, ty), exp) in params.iter().zip(call.params.iter()) {
+            if *is_var || ty.scalar().is_none() {
+                let (lvalue, _) = self.generate_access(scope::referenced_argument(exp, *is_var, call.name())?)?;
+                arguments.push(if *is_var { format!("&{}", lvalue) } else { lvalue });
+            } else {
+                arguments.push(self.generate_expression(exp)?.0);
+            }
+        }
+        self.line(format!("snl_{}({});", name, arguments.join(", ")));
+        Ok(())
+    }
+
+    fn generate_condition(&mut self, rel: &RelationExpression) -> Result<String> {
+        let (left, _) = self.generate_expression(&rel.left)?;
+        let (right, _) = self.generate_expression(&rel.right)?;
+        Ok(match rel.op.as_str() {
+            "<" => format!("{} < {}", left, right),
+            "=" => format!("{} == {}", left, right),
+            _ => unreachable!(),
+        })
+    }
+
+    fn generate_expression(&mut self, exp: &Expression) -> Result<(String, ScalarType)> {
+        match exp {
+            Expression::Binary(binary_exp) => {
+                let left = self.generate_expression(&binary_exp.left)?;
+                let right = self.generate_expression(&binary_exp.right)?;
+                Ok(binary(binary_exp.op, left, right, binary_exp.right.position()))
+            }
+            Expression::Factor(factor) => self.generate_factor(factor),
+        }
+    }
+
+    fn generate_factor(&mut self, factor: &Positional<ExpressionFactor>) -> Result<(String, ScalarType)> {
+        match factor.inner() {
+            ExpressionFactor::Bracket(exp) => self.generate_expression(exp),
+            ExpressionFactor::Constant(c) => Ok((c.to_string(), ScalarType::Integer)),
+            ExpressionFactor::Variable(repr) => {
+                let (lvalue, ty) = self.generate_access(repr)?;
+                Ok((lvalue, scope::scalar(&ty, &repr.base)?))
+            }
+        }
+    }
+
+    /// Lvalue of a variable represent, with field and index offsets applied
+    fn generate_access(&mut self, repr: &VariableRepresent) -> Result<(String, Type)> {
+        let Variable { level, is_var, ty } = self.scope.variable(&repr.base)?;
+        let frame = format!("frame{}", "->up".repeat(self.level - level).replacen("->", ".", 1));
+        let member = if self.level == *level { "." } else { "->" };
+        let mut lvalue = if *is_var {
+            format!("(*{}{}v_{})", frame, member, repr.base.inner())
+        } else {
+            format!("{}{}v_{}", frame, member, repr.base.inner())
+        };
+        let mut ty = ty.clone();
+
+        if let Some(visit) = &repr.visit {
+            if let Some(field) = &visit.dot {
+                let field_type = scope::field(&ty, field)?.1.clone();
+                lvalue = format!("{}.f_{}", lvalue, field.inner());
+                ty = field_type;
+            }
+
+            // array indexes are rebased to start from zero
+            if let Some(index) = &visit.sqbr {
+                let (base, lower_bound, upper_bound) = scope::element(&ty, repr, index)?;
+                let checked = self.options.bounds_check && index.constant().is_none();
+                let (line, column) = index.position();
+                let (mut index, _) = self.generate_expression(index)?;
+                if checked {
+                    index = format!("snl_check_index({}, {}, {}, {}, {})", index, lower_bound, upper_bound, line, column);
+                }
+                lvalue = if lower_bound == 0 {
+                    format!("{}.at[{}]", lvalue, index)
+                } else {
+                    format!("{}.at[{} - {}]", lvalue, index, lower_bound)
+                };
+                ty = Type::Scalar(base);
+            }
+        }
+        Ok((lvalue, ty))
+    }
+}
+
+fn binary(op: BinaryOperator, (left, left_type): (String, ScalarType), (right, right_type): (String, ScalarType), (line, column): (u32, u32)) -> (String, ScalarType) {
+    let value = match op {
+        BinaryOperator::Div => format!("snl_divide({}, {}, {}, {})", left, right, line, column),
+        _ => format!("({} {} {})", left, op, right),
+    };
+    let ty = if left_type == ScalarType::Char && right_type == ScalarType::Char {
+        ScalarType::Char
+    } else {
+        ScalarType::Integer
+    };
+    (value, ty)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generate;
+    use snl_rdp::Parser;
+    use snl_utils::options::CodegenOptions;
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    fn c(source: &str, options: CodegenOptions) -> String {
+        let tokens = snl_lexer::read_tokens(source).unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        generate(&ast, options).unwrap()
+    }
+
+    /// Compile and run with the system C compiler, `None` if it is not installed
+    fn run(source: &str, input: &str, options: CodegenOptions) -> Option<(String, String)> {
+        let dir = std::env::temp_dir().join(format!("snl-c-{}-{}", std::process::id(), source.len()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (file, exe) = (dir.join("out.c"), dir.join("out"));
+        std::fs::write(&file, c(source, options)).unwrap();
+        let status = Command::new("cc")
+            .args(["-std=c99", "-fwrapv", "-Wall", "-Werror"])
+            .arg(&file)
+            .arg("-o")
+            .arg(&exe)
+            .status()
+            .ok()?;
+        assert!(status.success());
+
+        let mut child = Command::new(&exe)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+        let output = child.wait_with_output().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        Some((String::from_utf8(output.stdout).unwrap(), String::from_utf8(output.stderr).unwrap()))
+    }
+
+    #[test]
+    fn test_lifting() {
+        let c = c(r#"program p
+var array [3..5] of integer a;
+procedure outer(var integer k);
+procedure inner();
+begin
+  a[k] := k
+end
+begin
+  inner()
+end
+begin
+  outer(a[4])
+end."#, Default::default());
+        assert!(c.contains("typedef struct { int64_t at[3]; } snl_type_0;"));
+        assert!(c.contains("static void snl_outer_inner(struct frame_outer *up) {"));
+        assert!(c.contains("    frame.up->up->v_a.at[(*frame.up->v_k) - 3] = (*frame.up->v_k);\n"));
+        assert!(c.contains("    snl_outer(&frame, &frame.v_a.at[4 - 3]);\n"));
+    }
+
+    #[test]
+    fn test_run() {
+        let result = run(r#"program nested
+type pair = record
+  integer first;
+  array [1..3] of char tag;
+end;
+var pair p, q;
+    array [2..4] of integer a;
+    integer offset;
+    char c;
+procedure outer(integer base);
+var integer offset;
+procedure inner(integer k);
+begin
+  a[k] := base + offset + k
+end
+procedure swap(var integer x, y);
+var integer t;
+begin
+  t := x;
+  x := y;
+  y := t
+end
+begin
+  offset := 100;
+  inner(2);
+  inner(4);
+  swap(a[2], a[4])
+end
+procedure show(pair r);
+begin
+  write(r.first);
+  write(r.tag[2]);
+  write(0 - r.first / 5);
+  r.first := 0
+end
+begin
+  offset := 7;
+  outer(10);
+  read(c);
+  p.tag[2] := c;
+  p.first := a[4];
+  q := p;
+  show(q);
+  write(q.first);
+  write(a[4]);
+  write(offset);
+  write(offset / (offset - 7))
+end."#, "  x", Default::default());
+        if let Some(result) = result {
+            assert_eq!(result.0, "112\nx\n-22\n112\n112\n7\n");
+            assert_eq!(result.1, "Runtime error at line 47, column 18:\tDivision by zero\n");
+        }
+    }
+
+    #[test]
+    fn test_bounds_check() {
+        let source = r#"program bounds
+var array [1..3] of integer a;
+    integer i;
+begin
+  read(i);
+  a[i] := 1;
+  write(a[i])
+end."#;
+        assert!(!c(source, Default::default()).contains("snl_check_index"));
+        let options = CodegenOptions { bounds_check: true };
+        if let Some(result) = run(source, "2", options) {
+            assert_eq!(result, ("1\n".to_owned(), String::new()));
+        }
+        if let Some(result) = run(source, "4", options) {
+            assert_eq!(result, (String::new(), "Runtime error at line 6, column 5:\tArray index(4) out of bound(1..3)\n".to_owned()));
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub use generator::generate;
+
+mod generator;
+mod runtime;
@@ -0,0 +1,80 @@
+/// Includes and runtime functions placed at the top of every generated file
+pub const RUNTIME: &str = r#"#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+static inline void snl_error(int line, int column, const char *message) {
+    fflush(stdout);
+    fprintf(stderr, "Runtime error at line %d, column %d:\t%s\n", line, column, message);
+    exit(1);
+}
+
+static inline int snl_skip_whitespace(void) {
+    int c;
+    do {
+        c = getchar();
+    } while (c == ' ' || c == '\t' || c == '\n' || c == '\f' || c == '\r');
+    return c;
+}
+
+static inline int64_t snl_read_integer(int line, int column) {
+    uint64_t value = 0;
+    int negative = 0, digits = 0;
+    int c = snl_skip_whitespace();
+    if (c == '-') {
+        negative = 1;
+        c = getchar();
+    }
+    for (; c >= '0' && c <= '9'; c = getchar(), digits++) {
+        value = value * 10 + (uint64_t) (c - '0');
+    }
+    if (c != EOF) {
+        ungetc(c, stdin);
+    }
+    if (digits == 0) {
+        snl_error(line, column, c == EOF ? "Unexpected end of input" : "Invalid input for type 'integer'");
+    }
+    return (int64_t) (negative ? 0 - value : value);
+}
+
+static inline char snl_read_char(int line, int column) {
+    int c = snl_skip_whitespace();
+    if (c == EOF) {
+        snl_error(line, column, "Unexpected end of input");
+    }
+    return (char) c;
+}
+
+static inline void snl_write_integer(int64_t value) {
+    printf("%lld\n", (long long) value);
+}
+
+static inline void snl_write_char(char value) {
+    printf("%c\n", value);
+}
+
+/* wraps on overflow like the interpreter */
+static inline int64_t snl_divide(int64_t a, int64_t b, int line, int column) {
+    if (b == 0) {
+        snl_error(line, column, "Division by zero");
+    }
+    if (b == -1) {
+        return (int64_t) (0 - (uint64_t) a);
+    }
+    return a / b;
+}
+"#;
+
+/// Runtime function added when array indexes are checked at runtime
+pub const BOUNDS_CHECK: &str = r#"
+static inline int64_t snl_check_index(int64_t index, int64_t lower, int64_t upper, int line, int column) {
+    if (index < lower || index > upper) {
+        char message[96];
+        snprintf(message, sizeof message, "Array index(%lld) out of bound(%lld..%lld)",
+                 (long long) index, (long long) lower, (long long) upper);
+        snl_error(line, column, message);
+    }
+    return index;
+}
+"#;
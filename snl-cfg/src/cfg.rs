@@ -0,0 +1,262 @@
+use snl_utils::ast::*;
+use std::fmt::{Display, Formatter};
+
+/// Index of a block in its `Cfg`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub usize);
+
+impl Display for BlockId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "bb{}", self.0)
+    }
+}
+
+/// How control leaves a basic block
+#[derive(Debug)]
+pub enum Terminator<'a> {
+    Goto(BlockId),
+    /// Condition of an `if` or a `while`, jumps to `then` if it holds and to `otherwise` if not
+    Branch { condition: &'a RelationExpression, then: BlockId, otherwise: BlockId },
+    /// Returns the value, continuing at the exit block
+    Return(&'a Expression),
+    /// End of the exit block, which has no successors
+    Exit,
+}
+
+/// Straight-line statements, only `read`, `write`, assignments and calls are kept in blocks
+#[derive(Debug)]
+pub struct BasicBlock<'a> {
+    pub statements: Vec<&'a Statement>,
+    pub terminator: Terminator<'a>,
+}
+
+/// Control-flow graph of the body of a procedure or the main program.
+///
+/// Blocks following a return are kept, they have no predecessors.
+#[derive(Debug)]
+pub struct Cfg<'a> {
+    /// Procedure name, nested procedures are qualified with the procedures enclosing them
+    pub name: String,
+    blocks: Vec<BasicBlock<'a>>,
+    predecessors: Vec<Vec<BlockId>>,
+}
+
+impl<'a> Cfg<'a> {
+    pub const ENTRY: BlockId = BlockId(0);
+    /// Empty block all paths end at
+    pub const EXIT: BlockId = BlockId(1);
+
+    pub fn new(name: String, body: &'a StatementList) -> Self {
+        let mut builder = Builder { blocks: Vec::new() };
+        let entry = builder.new_block();
+        let exit = builder.new_block();
+        builder.blocks[exit.0].terminator = Terminator::Exit;
+        if let Some(end) = builder.statement_list(body, Some(entry)) {
+            builder.blocks[end.0].terminator = Terminator::Goto(exit);
+        }
+
+        let mut cfg = Cfg { name, predecessors: vec![Vec::new(); builder.blocks.len()], blocks: builder.blocks };
+        for block in cfg.ids() {
+            for successor in cfg.successors(block).collect::<Vec<_>>() {
+                cfg.predecessors[successor.0].push(block);
+            }
+        }
+        cfg
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = BlockId> {
+        (0..self.blocks.len()).map(BlockId)
+    }
+
+    pub fn block(&self, id: BlockId) -> &BasicBlock<'a> {
+        &self.blocks[id.0]
+    }
+
+    pub fn successors(&self, id: BlockId) -> impl Iterator<Item = BlockId> {
+        let successors = match self.blocks[id.0].terminator {
+            Terminator::Goto(target) => vec![target],
+            Terminator::Branch { then, otherwise, .. } => vec![then, otherwise],
+            Terminator::Return(_) => vec![Cfg::EXIT],
+            Terminator::Exit => vec![],
+        };
+        successors.into_iter()
+    }
+
+    pub fn predecessors(&self, id: BlockId) -> impl Iterator<Item = BlockId> + '_ {
+        self.predecessors[id.0].iter().copied()
+    }
+
+    /// Blocks reachable from the entry, each one after all of its predecessors except back edges
+    pub fn reverse_postorder(&self) -> Vec<BlockId> {
+        let mut visited = vec![false; self.len()];
+        let mut order = Vec::new();
+        // blocks with the successors left to visit
+        let mut stack = vec![(Cfg::ENTRY, self.successors(Cfg::ENTRY))];
+        visited[Cfg::ENTRY.0] = true;
+        while let Some((block, successors)) = stack.last_mut() {
+            match successors.next() {
+                Some(next) if !visited[next.0] => {
+                    visited[next.0] = true;
+                    stack.push((next, self.successors(next)));
+                }
+                Some(_) => {}
+                None => {
+                    order.push(*block);
+                    stack.pop();
+                }
+            }
+        }
+        order.reverse();
+        order
+    }
+}
+
+struct Builder<'a> {
+    blocks: Vec<BasicBlock<'a>>,
+}
+
+impl<'a> Builder<'a> {
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push(BasicBlock { statements: Vec::new(), terminator: Terminator::Goto(Cfg::EXIT) });
+        BlockId(self.blocks.len() - 1)
+    }
+
+    /// Add statements to `current`, returns the block control falls out of,
+    /// `None` if every path returns. Statements after a return start an unreachable block.
+    fn statement_list(&mut self, list: &'a StatementList, mut current: Option<BlockId>) -> Option<BlockId> {
+        for statement in list.iter() {
+            let block = match current {
+                Some(block) => block,
+                None => self.new_block(),
+            };
+            current = self.statement(statement, block);
+        }
+        current
+    }
+
+    fn statement(&mut self, statement: &'a Statement, block: BlockId) -> Option<BlockId> {
+        match statement {
+            Statement::Conditional(con) => {
+                let then = self.new_block();
+                let otherwise = self.new_block();
+                self.blocks[block.0].terminator = Terminator::Branch { condition: &con.condition, then, otherwise };
+                let ends: Vec<_> = vec![
+                    self.statement_list(&con.body, Some(then)),
+                    self.statement_list(&con.else_body, Some(otherwise)),
+                ].into_iter().flatten().collect();
+                if ends.is_empty() {
+                    return None;
+                }
+                let join = self.new_block();
+                for end in ends {
+                    self.blocks[end.0].terminator = Terminator::Goto(join);
+                }
+                Some(join)
+            }
+            Statement::Loop(lo) => {
+                // the condition is evaluated in a block of its own, which is the target of the back edge
+                let header = if self.blocks[block.0].statements.is_empty() && block != Cfg::ENTRY {
+                    block
+                } else {
+                    let header = self.new_block();
+                    self.blocks[block.0].terminator = Terminator::Goto(header);
+                    header
+                };
+                let body = self.new_block();
+                let after = self.new_block();
+                self.blocks[header.0].terminator = Terminator::Branch { condition: &lo.condition, then: body, otherwise: after };
+                if let Some(end) = self.statement_list(&lo.body, Some(body)) {
+                    self.blocks[end.0].terminator = Terminator::Goto(header);
+                }
+                Some(after)
+            }
+            Statement::Return(exp) => {
+                self.blocks[block.0].terminator = Terminator::Return(exp);
+                None
+            }
+            _ => {
+                self.blocks[block.0].statements.push(statement);
+                Some(block)
+            }
+        }
+    }
+}
+
+/// Graphs of the main program and every procedure, in the order they are declared
+pub fn build(program: &Program) -> Vec<Cfg<'_>> {
+    let mut graphs = vec![Cfg::new(program.name.clone(), &program.body)];
+    build_procedures(&program.declare, "", &mut graphs);
+    graphs
+}
+
+fn build_procedures<'a>(declare: &'a ProgramDeclare, prefix: &str, graphs: &mut Vec<Cfg<'a>>) {
+    for p in declare.procedure_declare.iter() {
+        let name = format!("{}{}", prefix, p.name());
+        graphs.push(Cfg::new(name.clone(), &p.body));
+        build_procedures(&p.declare, &format!("{}::", name), graphs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{build, BlockId, Cfg, Terminator};
+    use snl_rdp::Parser;
+
+    fn edges(cfg: &Cfg) -> Vec<(usize, usize)> {
+        cfg.ids()
+            .flat_map(|block| cfg.successors(block).map(move |next| (block.0, next.0)))
+            .collect()
+    }
+
+    #[test]
+    fn test_build() {
+        let tokens = snl_lexer::read_tokens("program p
+var integer a;
+procedure q();
+procedure r();
+begin
+  return(1);
+  write(2)
+end
+begin
+  r()
+end
+begin
+  read(a);
+  while a < 10 do
+    if a = 5 then
+      return(a)
+    else
+      a := a + 1
+    fi
+  endwh;
+  write(a)
+end.").unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let graphs = build(&ast);
+        let names: Vec<_> = graphs.iter().map(|cfg| cfg.name.as_str()).collect();
+        assert_eq!(names, vec!["p", "q", "q::r"]);
+
+        let main = &graphs[0];
+        // entry -> header -> body -> if -> join -> header, header -> after -> exit
+        assert_eq!(edges(main), vec![(0, 2), (2, 3), (2, 4), (3, 5), (3, 6), (4, 1), (5, 1), (6, 7), (7, 2)]);
+        assert_eq!(main.block(Cfg::ENTRY).statements.len(), 1);
+        assert!(matches!(main.block(BlockId(5)).terminator, Terminator::Return(_)));
+        assert_eq!(main.predecessors(BlockId(2)).collect::<Vec<_>>(), vec![BlockId(0), BlockId(7)]);
+        assert_eq!(main.reverse_postorder()[..3], [BlockId(0), BlockId(2), BlockId(4)]);
+
+        // statements after a return are kept in an unreachable block
+        let r = &graphs[2];
+        assert_eq!(edges(r), vec![(0, 1), (2, 1)]);
+        assert_eq!(r.predecessors(BlockId(2)).count(), 0);
+        assert!(!r.reverse_postorder().contains(&BlockId(2)));
+    }
+}
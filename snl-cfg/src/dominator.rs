@@ -0,0 +1,146 @@
+use crate::cfg::{BlockId, Cfg};
+
+/// Immediate dominators of the blocks of a `Cfg`, computed with the iterative algorithm of
+/// Cooper, Harvey and Kennedy. Blocks unreachable from the entry are not in the tree.
+#[derive(Debug)]
+pub struct DominatorTree {
+    /// Immediate dominator of each block, the entry dominates itself
+    idom: Vec<Option<BlockId>>,
+}
+
+impl DominatorTree {
+    pub fn new(cfg: &Cfg) -> Self {
+        let order = cfg.reverse_postorder();
+        // position of each block in reverse postorder, used to find common dominators
+        let mut rank = vec![usize::MAX; cfg.len()];
+        for (i, block) in order.iter().enumerate() {
+            rank[block.0] = i;
+        }
+
+        let mut idom = vec![None; cfg.len()];
+        idom[Cfg::ENTRY.0] = Some(Cfg::ENTRY);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in order.iter().skip(1) {
+                let mut new_idom: Option<BlockId> = None;
+                for pred in cfg.predecessors(*block).filter(|pred| idom[pred.0].is_some()) {
+                    new_idom = Some(match new_idom {
+                        Some(current) => intersect(&idom, &rank, pred, current),
+                        None => pred,
+                    });
+                }
+                if new_idom.is_some() && idom[block.0] != new_idom {
+                    idom[block.0] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+        DominatorTree { idom }
+    }
+
+    pub fn is_reachable(&self, block: BlockId) -> bool {
+        self.idom[block.0].is_some()
+    }
+
+    /// `None` for the entry and unreachable blocks
+    pub fn immediate_dominator(&self, block: BlockId) -> Option<BlockId> {
+        match self.idom[block.0] {
+            Some(idom) if idom != block => Some(idom),
+            _ => None,
+        }
+    }
+
+    /// Whether every path from the entry to `b` goes through `a`, a block dominates itself
+    pub fn dominates(&self, a: BlockId, b: BlockId) -> bool {
+        if !self.is_reachable(b) {
+            return false;
+        }
+        let mut block = b;
+        loop {
+            if block == a {
+                return true;
+            }
+            match self.immediate_dominator(block) {
+                Some(idom) => block = idom,
+                None => return false,
+            }
+        }
+    }
+
+    /// Blocks immediately dominated by `block`
+    pub fn children(&self, block: BlockId) -> impl Iterator<Item = BlockId> + '_ {
+        (0..self.idom.len())
+            .map(BlockId)
+            .filter(move |child| self.immediate_dominator(*child) == Some(block))
+    }
+}
+
+/// Closest common dominator of two blocks
+fn intersect(idom: &[Option<BlockId>], rank: &[usize], mut a: BlockId, mut b: BlockId) -> BlockId {
+    while a != b {
+        while rank[a.0] > rank[b.0] {
+            a = idom[a.0].unwrap();
+        }
+        while rank[b.0] > rank[a.0] {
+            b = idom[b.0].unwrap();
+        }
+    }
+    a
+}
+
+impl<'a> Cfg<'a> {
+    pub fn dominators(&self) -> DominatorTree {
+        DominatorTree::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BlockId, Cfg};
+    use snl_rdp::Parser;
+
+    #[test]
+    fn test_dominators() {
+        let tokens = snl_lexer::read_tokens("program p
+var integer a;
+begin
+  read(a);
+  while a < 10 do
+    if a = 5 then
+      return(a)
+    else
+      a := a + 1
+    fi
+  endwh;
+  write(a)
+end.").unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let cfg = Cfg::new("p".to_owned(), &ast.body);
+        let tree = cfg.dominators();
+
+        let idoms: Vec<_> = cfg.ids().map(|block| tree.immediate_dominator(block).map(|b| b.0)).collect();
+        // the exit is reached from the return and after the loop, the header dominates both
+        assert_eq!(idoms, vec![None, Some(2), Some(0), Some(2), Some(2), Some(3), Some(3), Some(6)]);
+        assert!(tree.dominates(BlockId(2), BlockId(7)));
+        assert!(tree.dominates(BlockId(7), BlockId(7)));
+        assert!(!tree.dominates(BlockId(5), BlockId(1)));
+        assert_eq!(tree.children(BlockId(3)).collect::<Vec<_>>(), vec![BlockId(5), BlockId(6)]);
+    }
+
+    #[test]
+    fn test_unreachable() {
+        let tokens = snl_lexer::read_tokens("program p
+begin
+  return(1);
+  write(2)
+end.").unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let cfg = Cfg::new("p".to_owned(), &ast.body);
+        let tree = cfg.dominators();
+        assert!(tree.is_reachable(Cfg::EXIT));
+        assert!(!tree.is_reachable(BlockId(2)));
+        assert!(!tree.dominates(Cfg::ENTRY, BlockId(2)));
+        assert_eq!(tree.immediate_dominator(Cfg::EXIT), Some(Cfg::ENTRY));
+    }
+}
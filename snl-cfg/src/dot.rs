@@ -0,0 +1,112 @@
+use crate::cfg::{BlockId, Cfg, Terminator};
+use snl_utils::ast::Statement;
+use std::fmt::Write;
+
+/// Graphviz DOT of the graphs, each graph is drawn in a cluster named after its procedure
+pub fn to_dot(graphs: &[Cfg]) -> String {
+    let mut out = String::new();
+    writeln!(out, "digraph cfg {{").unwrap();
+    writeln!(out, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+    for (i, cfg) in graphs.iter().enumerate() {
+        writeln!(out, "    subgraph cluster_{} {{", i).unwrap();
+        writeln!(out, "        label=\"{}\";", escape(&cfg.name)).unwrap();
+        for block in cfg.ids() {
+            writeln!(out, "        {} [label=\"{}\"];", node(i, block), label(cfg, block)).unwrap();
+        }
+        for block in cfg.ids() {
+            match cfg.block(block).terminator {
+                Terminator::Branch { then, otherwise, .. } => {
+                    writeln!(out, "        {} -> {} [label=\"true\"];", node(i, block), node(i, then)).unwrap();
+                    writeln!(out, "        {} -> {} [label=\"false\"];", node(i, block), node(i, otherwise)).unwrap();
+                }
+                _ => {
+                    for next in cfg.successors(block) {
+                        writeln!(out, "        {} -> {};", node(i, block), node(i, next)).unwrap();
+                    }
+                }
+            }
+        }
+        writeln!(out, "    }}").unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+fn node(graph: usize, block: BlockId) -> String {
+    format!("g{}_{}", graph, block)
+}
+
+/// Block name, statements and terminator, each on a left-aligned line
+fn label(cfg: &Cfg, id: BlockId) -> String {
+    let block = cfg.block(id);
+    let name = match id {
+        Cfg::ENTRY => format!("{} (entry)", id),
+        Cfg::EXIT => format!("{} (exit)", id),
+        _ => id.to_string(),
+    };
+    let mut lines = vec![name];
+    for statement in block.statements.iter() {
+        lines.push(match statement {
+            Statement::Input(input) => format!("read({})", input.inner()),
+            Statement::Output(exp) => format!("write({})", exp),
+            Statement::Assign(assign) => format!("{} := {}", assign.variable, assign.value),
+            Statement::Call(call) => {
+                let params: Vec<_> = call.params.iter().map(|exp| exp.to_string()).collect();
+                format!("{}({})", call.name(), params.join(", "))
+            }
+            Statement::Error(_) => "<error>".to_owned(),
+            // control flow is in terminators
+            Statement::Conditional(_) | Statement::Loop(_) | Statement::Return(_) => unreachable!(),
+        });
+    }
+    match block.terminator {
+        Terminator::Branch { condition, .. } => lines.push(format!("if {}", condition)),
+        Terminator::Return(exp) => lines.push(format!("return({})", exp)),
+        Terminator::Goto(_) | Terminator::Exit => {}
+    }
+    lines.iter().map(|line| escape(line) + "\\l").collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{build, to_dot};
+    use snl_rdp::Parser;
+
+    #[test]
+    fn test_to_dot() {
+        let tokens = snl_lexer::read_tokens("program p
+var integer a;
+    array [1..2] of integer b;
+begin
+  read(a);
+  if a < b[1] then
+    return(a)
+  else
+    b[a] := 0
+  fi;
+  write((a + 1) * 2)
+end.").unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        assert_eq!(to_dot(&build(&ast)), r#"digraph cfg {
+    node [shape=box, fontname="monospace"];
+    subgraph cluster_0 {
+        label="p";
+        g0_bb0 [label="bb0 (entry)\lread(a)\lif a < b[1]\l"];
+        g0_bb1 [label="bb1 (exit)\l"];
+        g0_bb2 [label="bb2\lreturn(a)\l"];
+        g0_bb3 [label="bb3\lb[a] := 0\l"];
+        g0_bb4 [label="bb4\lwrite((a + 1) * 2)\l"];
+        g0_bb0 -> g0_bb2 [label="true"];
+        g0_bb0 -> g0_bb3 [label="false"];
+        g0_bb2 -> g0_bb1;
+        g0_bb3 -> g0_bb4;
+        g0_bb4 -> g0_bb1;
+    }
+}
+"#);
+    }
+}
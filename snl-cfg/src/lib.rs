@@ -0,0 +1,7 @@
+pub use cfg::{build, BasicBlock, BlockId, Cfg, Terminator};
+pub use dominator::DominatorTree;
+pub use dot::to_dot;
+
+mod cfg;
+mod dominator;
+mod dot;
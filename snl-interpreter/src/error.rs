@@ -0,0 +1,26 @@
+use snl_utils::diagnostic::Diagnose;
+use snl_utils::error::RuntimeError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Name, type or argument which can not be resolved
+    #[error(transparent)]
+    Resolve(#[from] snl_ir::Error),
+    /// Procedure call parameter count mismatch
+    #[error("Procedure call needs {expected} parameter(s) but got {got}")]
+    CallParameterCountMismatch { expected: usize, got: usize },
+    /// Failure of the program itself, e.g. division by zero
+    #[error(transparent)]
+    Runtime(#[from] RuntimeError),
+}
+
+impl Diagnose for Error {
+    fn code(&self) -> &'static str {
+        match self {
+            Error::Resolve(error) => error.code(),
+            // the same mismatch semantic analysis reports, for programs run without it
+            Error::CallParameterCountMismatch { .. } => "E0314",
+            Error::Runtime(error) => error.code(),
+        }
+    }
+}
@@ -0,0 +1,486 @@
+use crate::error::Error;
+use crate::value::{initialize, Value};
+use snl_ir::scope::{self, Binding};
+use snl_ir::{ScalarType, Type};
+use snl_utils::ast::*;
+use snl_utils::error::RuntimeError;
+use snl_utils::input::{read_char, read_integer};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+/// Default maximum depth of nested procedure calls before reporting stack overflow
+const MAX_CALL_DEPTH: usize = 1024;
+
+pub type Result<T> = std::result::Result<T, Positional<Error>>;
+
+fn at(position: (u32, u32)) -> impl Fn(Error) -> Positional<Error> {
+    move |error| Positional::from_position(position, error)
+}
+
+/// Errors of resolving names are reported as they are
+fn resolved<T>(result: scope::Result<T>) -> Result<T> {
+    result.map_err(|e| e.map(Error::from))
+}
+
+/// Variable stored at `address` in memory
+struct Variable {
+    address: usize,
+    ty: Type,
+}
+
+/// Procedure declared in frame `frame`
+struct Procedure<'p> {
+    declare: &'p Positional<ProcedureDeclare>,
+    frame: usize,
+}
+
+struct Frame<'p> {
+    bindings: HashMap<&'p str, Binding<Variable, Procedure<'p>>>,
+    /// Frame of the procedure (or program) lexically enclosing this one
+    static_link: Option<usize>,
+    /// Memory size when the frame was entered, restored on exit
+    memory_base: usize,
+}
+
+/// Whether execution continues after a statement
+enum Flow {
+    Continue,
+    Return,
+}
+
+/// Evaluated argument of a procedure call
+enum Argument {
+    /// Address of the variable passed to a `var` parameter
+    Reference(usize),
+    /// Copied cells passed to a value parameter
+    Value(Vec<Value>),
+}
+
+pub struct Interpreter<'p, R, W> {
+    program: &'p Program,
+    input: R,
+    output: W,
+    frames: Vec<Frame<'p>>,
+    memory: Vec<Value>,
+    max_call_depth: usize,
+}
+
+impl<'p, R: BufRead, W: Write> Interpreter<'p, R, W> {
+    pub fn new(program: &'p Program, input: R, output: W) -> Self {
+        Self {
+            program,
+            input,
+            output,
+            frames: Default::default(),
+            memory: Default::default(),
+            max_call_depth: MAX_CALL_DEPTH,
+        }
+    }
+
+    /// Limit nested procedure calls, each call uses host stack of the thread running the interpreter
+    pub fn with_max_call_depth(mut self, depth: usize) -> Self {
+        self.max_call_depth = depth;
+        self
+    }
+
+    pub fn run(&mut self) -> Result<()> {
+        let program = self.program;
+        self.frames.clear();
+        self.memory.clear();
+        self.frames.push(Frame {
+            bindings: Default::default(),
+            static_link: None,
+            memory_base: 0,
+        });
+        self.declare(&program.declare)?;
+        self.execute_list(&program.body)?;
+        self.output.flush().map_err(|e| Positional::dump(RuntimeError::from(e).into()))
+    }
+
+    fn bind(&mut self, name: &'p str, binding: Binding<Variable, Procedure<'p>>) {
+        self.frames.last_mut().unwrap().bindings.insert(name, binding);
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Binding<Variable, Procedure<'p>>> {
+        let mut frame = self.frames.len().checked_sub(1);
+        while let Some(index) = frame {
+            let current = &self.frames[index];
+            if let Some(binding) = current.bindings.get(name) {
+                return Some(binding);
+            }
+            frame = current.static_link;
+        }
+        None
+    }
+
+    fn allocate(&mut self, ty: &Type) -> usize {
+        let address = self.memory.len();
+        initialize(ty, &mut self.memory);
+        address
+    }
+
+    fn store(&mut self, address: usize, ty: &Type, values: &[Value]) {
+        for (offset, value) in values.iter().take(ty.size()).enumerate() {
+            let cell = &mut self.memory[address + offset];
+            *cell = value.coerce(*cell);
+        }
+    }
+
+    fn declare(&mut self, declare: &'p ProgramDeclare) -> Result<()> {
+        for t in declare.type_declare.iter() {
+            let ty = self.resolve_type(&t.base)?;
+            self.bind(t.name(), Binding::Type(ty));
+        }
+
+        for v in declare.variable_declare.iter() {
+            let ty = self.resolve_type(&v.type_name)?;
+            for name in v.identifiers.iter() {
+                let address = self.allocate(&ty);
+                self.bind(name.as_str(), Binding::Variable(Variable { address, ty: ty.clone() }));
+            }
+        }
+
+        // procedures are bound to the current frame, which becomes their static link
+        let frame = self.frames.len() - 1;
+        for p in declare.procedure_declare.iter() {
+            self.bind(p.name(), Binding::Procedure(Procedure { declare: p, frame }));
+        }
+        Ok(())
+    }
+
+    fn resolve_type(&self, ty: &Positional<SNLType>) -> Result<Type> {
+        resolved(scope::resolve_type(ty, &|name: &str| self.lookup(name)))
+    }
+
+    fn execute_list(&mut self, list: &'p StatementList) -> Result<Flow> {
+        for statement in list.iter() {
+            if let Flow::Return = self.execute(statement)? {
+                return Ok(Flow::Return);
+            }
+        }
+        Ok(Flow::Continue)
+    }
+
+    fn execute(&mut self, statement: &'p Statement) -> Result<Flow> {
+        match statement {
+            Statement::Conditional(con) => {
+                if self.evaluate_relation(&con.condition)? {
+                    self.execute_list(&con.body)
+                } else {
+                    self.execute_list(&con.else_body)
+                }
+            }
+            Statement::Loop(lo) => {
+                while self.evaluate_relation(&lo.condition)? {
+                    if let Flow::Return = self.execute_list(&lo.body)? {
+                        return Ok(Flow::Return);
+                    }
+                }
+                Ok(Flow::Continue)
+            }
+            Statement::Input(input) => {
+                self.read(input)?;
+                Ok(Flow::Continue)
+            }
+            Statement::Output(output) => {
+                let value = self.evaluate(output)?;
+                writeln!(self.output, "{}", value).map_err(|e| at(output.position())(RuntimeError::from(e).into()))?;
+                Ok(Flow::Continue)
+            }
+            Statement::Return(ret) => {
+                // procedures have no result, the value is evaluated for its errors only
+                self.evaluate(ret)?;
+                Ok(Flow::Return)
+            }
+            Statement::Assign(assign) => {
+                let (address, ty) = self.locate(&assign.variable)?;
+                let values = self.evaluate_cells(&assign.value)?;
+                self.store(address, &ty, &values);
+                Ok(Flow::Continue)
+            }
+            Statement::Call(call) => {
+                self.call(call)?;
+                Ok(Flow::Continue)
+            }
+            Statement::Error(error) => resolved(Err(scope::syntax_error(error))),
+        }
+    }
+
+    fn call(&mut self, call: &'p Positional<CallStatement>) -> Result<()> {
+        let procedure = resolved(Binding::procedure(self.lookup(call.name()), call))?;
+        let (declare, frame) = (procedure.declare, procedure.frame);
+
+        // flatten parameter groups like `var integer a, b`
+        let params: Vec<(&'p Param, &'p Positional<String>)> = declare.params.iter()
+            .flat_map(|p| p.definition.identifiers.iter().map(move |id| (p.inner(), id)))
+            .collect();
+        if params.len() != call.params.len() {
+            return Err(at(call.position())(Error::CallParameterCountMismatch {
+                expected: params.len(),
+                got: call.params.len(),
+            }));
+        }
+        if self.frames.len() > self.max_call_depth {
+            return Err(at(call.position())(RuntimeError::CallDepthExceeded(self.max_call_depth).into()));
+        }
+
+        // arguments are evaluated in the frame of the caller
+        let mut arguments = Vec::with_capacity(params.len());
+        for ((param, name), exp) in params.iter().zip(call.params.iter()) {
+            arguments.push(if param.is_var {
+                let repr = resolved(scope::referenced_argument(exp, true, name))?;
+                Argument::Reference(self.locate(repr)?.0)
+            } else {
+                Argument::Value(self.evaluate_cells(exp)?)
+            });
+        }
+
+        self.frames.push(Frame {
+            bindings: Default::default(),
+            static_link: Some(frame),
+            memory_base: self.memory.len(),
+        });
+        for ((param, name), argument) in params.into_iter().zip(arguments) {
+            // parameter types are resolved in the scope of the procedure
+            let ty = self.resolve_type(&param.definition.type_name)?;
+            let address = match argument {
+                Argument::Reference(address) => address,
+                Argument::Value(values) => {
+                    let address = self.allocate(&ty);
+                    self.store(address, &ty, &values);
+                    address
+                }
+            };
+            self.bind(name.as_str(), Binding::Variable(Variable { address, ty }));
+        }
+        self.declare(&declare.declare)?;
+        self.execute_list(&declare.body)?;
+
+        let frame = self.frames.pop().unwrap();
+        self.memory.truncate(frame.memory_base);
+        Ok(())
+    }
+
+    fn read(&mut self, name: &Positional<String>) -> Result<()> {
+        let variable = resolved(Binding::variable(self.lookup(name), name))?;
+        let (address, ty) = (variable.address, variable.ty.clone());
+        let value = match resolved(scope::scalar(&ty, name))? {
+            ScalarType::Integer => read_integer(&mut self.input)
+                .map(Value::Integer)
+                .map_err(|e| RuntimeError::from_input(e, "integer")),
+            ScalarType::Char => read_char(&mut self.input)
+                .map(Value::Char)
+                .map_err(|e| RuntimeError::from_input(e, "char")),
+        }.map_err(|e| at(name.position())(e.into()))?;
+        self.store(address, &ty, &[value]);
+        Ok(())
+    }
+
+    /// Address and type of the variable a variable represent refers to
+    fn locate(&self, repr: &VariableRepresent) -> Result<(usize, Type)> {
+        let variable = resolved(Binding::variable(self.lookup(&repr.base), &repr.base))?;
+        let (mut address, mut ty) = (variable.address, variable.ty.clone());
+
+        if let Some(visit) = &repr.visit {
+            if let Some(field) = &visit.dot {
+                let (offset, field_type) = resolved(scope::field(&ty, field))?;
+                address += offset;
+                ty = field_type.clone();
+            }
+
+            if let Some(index) = &visit.sqbr {
+                let i = self.evaluate(index)?.as_integer();
+                let (base, lower_bound, upper_bound) = resolved(scope::element(&ty, repr, index))?;
+                if i < lower_bound as i64 || i > upper_bound as i64 {
+                    let error = RuntimeError::ArrayIndexOutbound(i, lower_bound as i64, upper_bound as i64);
+                    return Err(at(index.position())(error.into()));
+                }
+                address += i as usize - lower_bound;
+                ty = Type::Scalar(base);
+            }
+        }
+        Ok((address, ty))
+    }
+
+    /// Cells of an expression, structured variables are copied as a whole
+    fn evaluate_cells(&self, exp: &Expression) -> Result<Vec<Value>> {
+        match exp.as_variable() {
+            Some(repr) => {
+                let (address, ty) = self.locate(repr)?;
+                Ok(self.memory[address..address + ty.size()].to_vec())
+            }
+            None => Ok(vec![self.evaluate(exp)?]),
+        }
+    }
+
+    fn evaluate_relation(&self, rel: &RelationExpression) -> Result<bool> {
+        let left = self.evaluate(&rel.left)?.as_integer();
+        let right = self.evaluate(&rel.right)?.as_integer();
+        Ok(match rel.op.as_str() {
+            "<" => left < right,
+            "=" => left == right,
+            _ => unreachable!(),
+        })
+    }
+
+    fn evaluate(&self, exp: &Expression) -> Result<Value> {
+        match exp {
+            Expression::Binary(binary) => {
+                let left = self.evaluate(&binary.left)?;
+                let right = self.evaluate(&binary.right)?;
+                arithmetic(binary.op, left, right).map_err(at(binary.right.position()))
+            }
+            Expression::Factor(factor) => self.evaluate_factor(factor),
+        }
+    }
+
+    fn evaluate_factor(&self, factor: &Positional<ExpressionFactor>) -> Result<Value> {
+        match factor.inner() {
+            ExpressionFactor::Bracket(exp) => self.evaluate(exp),
+            ExpressionFactor::Constant(c) => Ok(Value::Integer(*c as i64)),
+            ExpressionFactor::Variable(repr) => {
+                let (address, ty) = self.locate(repr)?;
+                resolved(scope::scalar(&ty, &repr.base))?;
+                Ok(self.memory[address])
+            }
+        }
+    }
+}
+
+fn arithmetic(op: BinaryOperator, left: Value, right: Value) -> std::result::Result<Value, Error> {
+    let result = op.eval(left.as_integer(), right.as_integer()).ok_or(RuntimeError::DivideByZero)?;
+    Ok(match (left, right) {
+        // char arithmetic stays char, e.g. shifting a letter
+        (Value::Char(_), Value::Char(_)) => Value::Integer(result).coerce(left),
+        _ => Value::Integer(result),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, Interpreter};
+    use snl_rdp::Parser;
+    use snl_utils::error::RuntimeError;
+
+    fn run(source: &str, input: &str) -> Result<String, Error> {
+        let tokens = snl_lexer::read_tokens(source).unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let mut output = Vec::new();
+        Interpreter::new(&ast, input.as_bytes(), &mut output)
+            .with_max_call_depth(64)
+            .run()
+            .map_err(|e| e.into_inner())?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_read_write() {
+        let output = run(include_str!("../../examples/program1.snl"), "5").unwrap();
+        assert_eq!(output, "15\n");
+    }
+
+    #[test]
+    fn test_evaluation_order() {
+        let output = run("program p\nvar integer a;\nbegin\n  read(a);\n  write(a - 4 - 3);\n  write(a / 4 / 2 * 3)\nend.", "16").unwrap();
+        assert_eq!(output, "9\n6\n");
+    }
+
+    #[test]
+    fn test_parameters() {
+        let output = run(r#"program swap
+var integer a, b;
+procedure swap(var integer x, y);
+var integer t;
+begin
+  t := x;
+  x := y;
+  y := t
+end
+procedure change(integer x);
+begin
+  x := 0
+end
+begin
+  read(a);
+  read(b);
+  swap(a, b);
+  change(a);
+  write(a);
+  write(b)
+end."#, "1 2").unwrap();
+        assert_eq!(output, "2\n1\n");
+    }
+
+    #[test]
+    fn test_nested_scope() {
+        let output = run(r#"program nested
+type pair = record
+  integer first;
+  array [1..3] of char tag;
+end;
+var pair p;
+    array [2..4] of integer a;
+    integer offset;
+    char c;
+procedure outer(integer base);
+var integer offset;
+procedure inner(integer k);
+begin
+  a[k] := base + offset + k
+end
+begin
+  offset := 100;
+  inner(2);
+  inner(4)
+end
+begin
+  offset := 7;
+  outer(10);
+  read(c);
+  p.tag[2] := c;
+  p.first := a[4];
+  write(a[2]);
+  write(p.first);
+  write(p.tag[2]);
+  write(offset)
+end."#, " x").unwrap();
+        assert_eq!(output, "112\n114\nx\n7\n");
+    }
+
+    #[test]
+    fn test_recursion() {
+        let source = r#"program recursion
+var integer n, result;
+procedure fact(integer k);
+begin
+  if k < 1 then
+    result := 1
+  else
+    fact(k - 1);
+    result := result * k
+  fi
+end
+begin
+  read(n);
+  fact(n);
+  write(result)
+end."#;
+        assert_eq!(run(source, "5").unwrap(), "120\n");
+        assert!(matches!(run(source, "100000"), Err(Error::Runtime(RuntimeError::CallDepthExceeded(_)))));
+    }
+
+    #[test]
+    fn test_runtime_error() {
+        let source = r#"program p
+var array [1..3] of integer a;
+    integer i;
+begin
+  read(i);
+  a[i] := 10 / (i - 2)
+end."#;
+        assert!(run(source, "1").is_ok());
+        assert!(matches!(run(source, "2"), Err(Error::Runtime(RuntimeError::DivideByZero))));
+        assert!(matches!(run(source, "4"), Err(Error::Runtime(RuntimeError::ArrayIndexOutbound(4, 1, 3)))));
+        assert!(matches!(run(source, ""), Err(Error::Runtime(RuntimeError::UnexpectedEndOfInput))));
+    }
+}
@@ -0,0 +1,6 @@
+pub use interpreter::Interpreter;
+pub use error::Error;
+
+mod error;
+mod interpreter;
+mod value;
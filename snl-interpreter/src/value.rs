@@ -0,0 +1,56 @@
+use snl_ir::{ScalarType, Type};
+use std::fmt::{Display, Formatter};
+
+/// Content of a single memory cell.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    Char(char),
+}
+
+impl Value {
+    pub fn as_integer(&self) -> i64 {
+        match self {
+            Value::Integer(i) => *i,
+            Value::Char(c) => *c as i64,
+        }
+    }
+
+    /// Convert value to the same variant as `cell`, so cells keep their type after store
+    pub fn coerce(self, cell: Value) -> Value {
+        match (cell, self) {
+            (Value::Char(_), Value::Integer(i)) => Value::Char(std::char::from_u32(i as u32).unwrap_or('\0')),
+            (Value::Integer(_), Value::Char(c)) => Value::Integer(c as i64),
+            (_, value) => value,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Integer(i) => write!(f, "{}", i),
+            Value::Char(c) => write!(f, "{}", c),
+        }
+    }
+}
+
+/// Append the initial cells of a variable of type `ty` to memory
+pub fn initialize(ty: &Type, memory: &mut Vec<Value>) {
+    match ty {
+        Type::Scalar(scalar) => memory.push(zero(*scalar)),
+        Type::Array { base, .. } => memory.extend((0..ty.size()).map(|_| zero(*base))),
+        Type::Record(fields) => {
+            for (_, ty) in fields {
+                initialize(ty, memory);
+            }
+        }
+    }
+}
+
+fn zero(ty: ScalarType) -> Value {
+    match ty {
+        ScalarType::Integer => Value::Integer(0),
+        ScalarType::Char => Value::Char('\0'),
+    }
+}
@@ -0,0 +1,36 @@
+use snl_utils::diagnostic::Diagnose;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Statement left unparsed by an error-recovering parser
+    #[error("Syntax error: {0}")]
+    SyntaxError(String),
+    /// Identifier is not found in symbol table
+    #[error("Undefined identifier '{0}'")]
+    UndefinedIdentifier(String),
+    /// Identifier is found, but not the expected kind of symbol
+    #[error("'{0}' is not a {1}")]
+    UnexpectedSymbol(String, &'static str),
+    /// Record has no field with the given name
+    #[error("Undefined record field '{0}'")]
+    UndefinedRecordField(String),
+    /// Structured variable used where a single integer or char is expected
+    #[error("Variable '{0}' of structured type can not be used as a value")]
+    NonScalarOperand(String),
+    /// Var parameter received an expression that can not be referenced
+    #[error("Var parameter '{0}' requires a variable argument")]
+    InvalidVarArgument(String),
+}
+
+impl Diagnose for Error {
+    fn code(&self) -> &'static str {
+        match self {
+            Error::SyntaxError(_) => "E0401",
+            Error::UndefinedIdentifier(_) => "E0402",
+            Error::UnexpectedSymbol(..) => "E0403",
+            Error::UndefinedRecordField(_) => "E0404",
+            Error::NonScalarOperand(_) => "E0405",
+            Error::InvalidVarArgument(_) => "E0406",
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use snl_utils::ast::BinaryOperator;
+use std::fmt::{Display, Formatter};
+
+pub type Temp = usize;
+pub type Label = usize;
+pub type VariableId = usize;
+pub type ProcedureId = usize;
+
+/// Type of a single memory cell
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ScalarType {
+    Integer,
+    Char,
+}
+
+impl Display for ScalarType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScalarType::Integer => write!(f, "integer"),
+            ScalarType::Char => write!(f, "char"),
+        }
+    }
+}
+
+/// Type with all aliases resolved.
+///
+/// Sizes and offsets are counted in cells, every scalar takes one cell.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Scalar(ScalarType),
+    Array { base: ScalarType, lower_bound: usize, upper_bound: usize },
+    Record(Vec<(String, Type)>),
+}
+
+impl Type {
+    pub fn size(&self) -> usize {
+        match self {
+            Type::Scalar(_) => 1,
+            Type::Array { lower_bound, upper_bound, .. } => (upper_bound + 1).saturating_sub(*lower_bound),
+            Type::Record(fields) => fields.iter().map(|(_, ty)| ty.size()).sum(),
+        }
+    }
+
+    pub fn scalar(&self) -> Option<ScalarType> {
+        match self {
+            Type::Scalar(ty) => Some(*ty),
+            _ => None,
+        }
+    }
+
+    /// Offset and type of a record field
+    pub fn field(&self, name: &str) -> Option<(usize, &Type)> {
+        match self {
+            Type::Record(fields) => {
+                let mut offset = 0;
+                for (field, ty) in fields {
+                    if field == name {
+                        return Some((offset, ty));
+                    }
+                    offset += ty.size();
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Scalar(ty) => write!(f, "{}", ty),
+            Type::Array { base, lower_bound, upper_bound } => {
+                write!(f, "array [{}..{}] of {}", lower_bound, upper_bound, base)
+            }
+            Type::Record(fields) => {
+                write!(f, "record")?;
+                for (name, ty) in fields {
+                    write!(f, " {} {};", ty, name)?;
+                }
+                write!(f, " end")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VariableKind {
+    Local,
+    /// Value parameter, the argument is copied into the frame
+    Param,
+    /// Reference parameter, the cell holds the address of the argument
+    VarParam,
+}
+
+#[derive(Debug, Clone)]
+pub struct Variable {
+    pub name: String,
+    /// Procedure whose frame holds the variable
+    pub procedure: ProcedureId,
+    pub kind: VariableKind,
+    pub ty: Type,
+    /// Offset in cells from the start of the frame
+    pub offset: usize,
+}
+
+impl Variable {
+    /// Count of cells the variable takes in its frame
+    pub fn size(&self) -> usize {
+        match self.kind {
+            VariableKind::VarParam => 1,
+            _ => self.ty.size(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Procedure {
+    /// Name qualified by enclosing procedures, e.g. `outer.inner`
+    pub name: String,
+    /// 0 for the main program, procedures declared in it are level 1
+    pub level: usize,
+    /// Lexically enclosing procedure, the target of the static link
+    pub parent: Option<ProcedureId>,
+    /// Parameters in declaration order
+    pub params: Vec<VariableId>,
+    /// Parameters and local variables in frame order
+    pub variables: Vec<VariableId>,
+    pub frame_size: usize,
+    pub temps: usize,
+    pub labels: usize,
+    pub code: Vec<Quad>,
+}
+
+/// Lowered program, `procedures[0]` is the main program body
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub name: String,
+    pub procedures: Vec<Procedure>,
+    pub variables: Vec<Variable>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Operand {
+    Const(i64),
+    Temp(Temp),
+    /// Content of the first cell of a variable
+    Var(VariableId),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Place {
+    Temp(Temp),
+    Var(VariableId),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl From<BinaryOperator> for BinaryOp {
+    fn from(op: BinaryOperator) -> Self {
+        match op {
+            BinaryOperator::Add => BinaryOp::Add,
+            BinaryOperator::Sub => BinaryOp::Sub,
+            BinaryOperator::Mul => BinaryOp::Mul,
+            BinaryOperator::Div => BinaryOp::Div,
+        }
+    }
+}
+
+impl From<BinaryOp> for BinaryOperator {
+    fn from(op: BinaryOp) -> Self {
+        match op {
+            BinaryOp::Add => BinaryOperator::Add,
+            BinaryOp::Sub => BinaryOperator::Sub,
+            BinaryOp::Mul => BinaryOperator::Mul,
+            BinaryOp::Div => BinaryOperator::Div,
+        }
+    }
+}
+
+impl Display for BinaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RelOp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl RelOp {
+    pub fn from_image(op: &str) -> Self {
+        match op {
+            "<" => RelOp::Lt,
+            "=" => RelOp::Eq,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn negate(self) -> Self {
+        match self {
+            RelOp::Lt => RelOp::Ge,
+            RelOp::Le => RelOp::Gt,
+            RelOp::Gt => RelOp::Le,
+            RelOp::Ge => RelOp::Lt,
+            RelOp::Eq => RelOp::Ne,
+            RelOp::Ne => RelOp::Eq,
+        }
+    }
+
+    pub fn eval(self, lhs: i64, rhs: i64) -> bool {
+        match self {
+            RelOp::Lt => lhs < rhs,
+            RelOp::Le => lhs <= rhs,
+            RelOp::Gt => lhs > rhs,
+            RelOp::Ge => lhs >= rhs,
+            RelOp::Eq => lhs == rhs,
+            RelOp::Ne => lhs != rhs,
+        }
+    }
+}
+
+impl Display for RelOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            RelOp::Lt => "<",
+            RelOp::Le => "<=",
+            RelOp::Gt => ">",
+            RelOp::Ge => ">=",
+            RelOp::Eq => "=",
+            RelOp::Ne => "<>",
+        })
+    }
+}
+
+/// Three-address instruction.
+///
+/// Addresses point to cells, `Offset` counts in cells and leaves scaling to the backend.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Quad {
+    Label(Label),
+    /// `dst = lhs op rhs`
+    Binary { op: BinaryOp, dst: Temp, lhs: Operand, rhs: Operand },
+    /// `dst = src`
+    Copy { dst: Place, src: Operand },
+    /// `dst = &var`
+    AddressOf { dst: Temp, var: VariableId },
+    /// `dst = &base[index]`, the address `index` cells after `base`
+    Offset { dst: Temp, base: Operand, index: Operand },
+    /// `dst = *addr`
+    Load { dst: Temp, addr: Operand },
+    /// `*addr = src`
+    Store { addr: Operand, src: Operand },
+    /// Copy `size` cells from address `src` to address `dst`
+    CopyBlock { dst: Operand, src: Operand, size: usize },
+    /// Stop with a runtime error unless `lower_bound <= index <= upper_bound`
+    BoundsCheck { index: Operand, lower_bound: usize, upper_bound: usize },
+    /// `goto target`
+    Jump(Label),
+    /// `if lhs rel rhs goto target`
+    Branch { rel: RelOp, lhs: Operand, rhs: Operand, target: Label },
+    /// Pass a scalar value or, for `var` parameters, an address to the next call
+    Param(Operand),
+    /// Pass `size` cells starting at `addr` by value to the next call
+    ParamBlock { addr: Operand, size: usize },
+    /// Call procedure with the last `argc` params
+    Call { procedure: ProcedureId, argc: usize },
+    Return(Option<Operand>),
+    Read { dst: Temp, ty: ScalarType },
+    Write { src: Operand, ty: ScalarType },
+}
+
+impl Module {
+    fn operand(&self, current: ProcedureId, operand: &Operand) -> String {
+        match operand {
+            Operand::Const(c) => c.to_string(),
+            Operand::Temp(t) => format!("t{}", t),
+            Operand::Var(v) => self.variable_name(current, *v),
+        }
+    }
+
+    fn place(&self, current: ProcedureId, place: &Place) -> String {
+        match place {
+            Place::Temp(t) => format!("t{}", t),
+            Place::Var(v) => self.variable_name(current, *v),
+        }
+    }
+
+    /// Local variables are printed plain, others qualified by their procedure
+    fn variable_name(&self, current: ProcedureId, variable: VariableId) -> String {
+        let variable = &self.variables[variable];
+        if variable.procedure == current {
+            variable.name.clone()
+        } else {
+            format!("{}.{}", self.procedures[variable.procedure].name, variable.name)
+        }
+    }
+
+    fn fmt_quad(&self, current: ProcedureId, quad: &Quad, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let op = |operand: &Operand| self.operand(current, operand);
+        match quad {
+            Quad::Label(label) => writeln!(f, "L{}:", label),
+            Quad::Binary { op: bin, dst, lhs, rhs } => writeln!(f, "    t{} = {} {} {}", dst, op(lhs), bin, op(rhs)),
+            Quad::Copy { dst, src } => writeln!(f, "    {} = {}", self.place(current, dst), op(src)),
+            Quad::AddressOf { dst, var } => writeln!(f, "    t{} = &{}", dst, self.variable_name(current, *var)),
+            Quad::Offset { dst, base, index } => writeln!(f, "    t{} = &{}[{}]", dst, op(base), op(index)),
+            Quad::Load { dst, addr } => writeln!(f, "    t{} = *{}", dst, op(addr)),
+            Quad::Store { addr, src } => writeln!(f, "    *{} = {}", op(addr), op(src)),
+            Quad::CopyBlock { dst, src, size } => writeln!(f, "    *{} = *{} [{}]", op(dst), op(src), size),
+            Quad::BoundsCheck { index, lower_bound, upper_bound } => {
+                writeln!(f, "    check {} in {}..{}", op(index), lower_bound, upper_bound)
+            }
+            Quad::Jump(label) => writeln!(f, "    goto L{}", label),
+            Quad::Branch { rel, lhs, rhs, target } => {
+                writeln!(f, "    if {} {} {} goto L{}", op(lhs), rel, op(rhs), target)
+            }
+            Quad::Param(value) => writeln!(f, "    param {}", op(value)),
+            Quad::ParamBlock { addr, size } => writeln!(f, "    param *{} [{}]", op(addr), size),
+            Quad::Call { procedure, argc } => writeln!(f, "    call {}, {}", self.procedures[*procedure].name, argc),
+            Quad::Return(None) => writeln!(f, "    return"),
+            Quad::Return(Some(value)) => writeln!(f, "    return {}", op(value)),
+            Quad::Read { dst, ty } => writeln!(f, "    t{} = read {}", dst, ty),
+            Quad::Write { src, ty } => writeln!(f, "    write {} {}", ty, op(src)),
+        }
+    }
+}
+
+impl Display for Module {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (id, procedure) in self.procedures.iter().enumerate() {
+            if id != 0 {
+                writeln!(f)?;
+            }
+            let kind = if id == 0 { "program" } else { "procedure" };
+            writeln!(f, "{} {} [level {}, frame {}]", kind, procedure.name, procedure.level, procedure.frame_size)?;
+            for v in procedure.variables.iter() {
+                let variable = &self.variables[*v];
+                let kind = match variable.kind {
+                    VariableKind::Local => "var",
+                    VariableKind::Param => "param",
+                    VariableKind::VarParam => "param var",
+                };
+                writeln!(f, "    {} {}: {} @{}", kind, variable.name, variable.ty, variable.offset)?;
+            }
+            for quad in procedure.code.iter() {
+                self.fmt_quad(id, quad, f)?;
+            }
+        }
+        Ok(())
+    }
+}
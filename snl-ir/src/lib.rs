@@ -0,0 +1,10 @@
+pub use error::Error;
+pub use ir::*;
+pub use lower::lower;
+pub use optimize::optimize;
+
+mod error;
+mod ir;
+mod lower;
+mod optimize;
+pub mod scope;
@@ -0,0 +1,439 @@
+use crate::ir::*;
+use crate::scope::{self, Binding, Result, Scope};
+use snl_utils::ast::*;
+use snl_utils::options::CodegenOptions;
+
+/// Where the value of a variable represent lives
+enum Access {
+    /// First cell of a variable, read and written directly
+    Direct(VariableId),
+    /// Cell at a computed address
+    Indirect(Operand),
+}
+
+struct Lower {
+    scope: Scope<VariableId, ProcedureId>,
+    module: Module,
+    current: ProcedureId,
+    options: CodegenOptions,
+}
+
+/// Lower a semantically checked program into three-address code
+pub fn lower(program: &Positional<Program>, options: CodegenOptions) -> Result<Module> {
+    let mut lower = Lower {
+        scope: Default::default(),
+        module: Module {
+            name: program.name.clone(),
+            procedures: vec![new_procedure(program.name.clone(), 0, None)],
+            variables: Default::default(),
+        },
+        current: 0,
+        options,
+    };
+    lower.lower_declare(&program.declare)?;
+    lower.lower_statement_list(&program.body)?;
+    lower.emit(Quad::Return(None));
+    Ok(lower.module)
+}
+
+fn new_procedure(name: String, level: usize, parent: Option<ProcedureId>) -> Procedure {
+    Procedure {
+        name,
+        level,
+        parent,
+        params: Default::default(),
+        variables: Default::default(),
+        frame_size: 0,
+        temps: 0,
+        labels: 0,
+        code: Default::default(),
+    }
+}
+
+impl Lower {
+    fn procedure(&mut self) -> &mut Procedure {
+        &mut self.module.procedures[self.current]
+    }
+
+    fn emit(&mut self, quad: Quad) {
+        self.procedure().code.push(quad);
+    }
+
+    fn new_temp(&mut self) -> Temp {
+        let procedure = self.procedure();
+        procedure.temps += 1;
+        procedure.temps - 1
+    }
+
+    fn new_label(&mut self) -> Label {
+        let procedure = self.procedure();
+        procedure.labels += 1;
+        procedure.labels - 1
+    }
+
+    /// Allocate a variable in the frame of the current procedure
+    fn new_variable(&mut self, name: &str, kind: VariableKind, ty: Type) -> VariableId {
+        let id = self.module.variables.len();
+        let variable = Variable {
+            name: name.to_owned(),
+            procedure: self.current,
+            kind,
+            ty,
+            offset: self.module.procedures[self.current].frame_size,
+        };
+        let procedure = &mut self.module.procedures[self.current];
+        procedure.frame_size += variable.size();
+        procedure.variables.push(id);
+        if kind != VariableKind::Local {
+            procedure.params.push(id);
+        }
+        self.module.variables.push(variable);
+        self.scope.insert(name.to_owned(), Binding::Variable(id));
+        id
+    }
+
+    fn lower_declare(&mut self, declare: &ProgramDeclare) -> Result<()> {
+        for t in declare.type_declare.iter() {
+            let ty = self.scope.resolve_type(&t.base)?;
+            self.scope.insert(t.name().to_owned(), Binding::Type(ty));
+        }
+
+        for v in declare.variable_declare.iter() {
+            let ty = self.scope.resolve_type(&v.type_name)?;
+            for name in v.identifiers.iter() {
+                self.new_variable(name, VariableKind::Local, ty.clone());
+            }
+        }
+
+        for p in declare.procedure_declare.iter() {
+            let parent = self.current;
+            let name = if parent == 0 {
+                p.name().to_owned()
+            } else {
+                format!("{}.{}", self.module.procedures[parent].name, p.name())
+            };
+            let level = self.module.procedures[parent].level + 1;
+            let id = self.module.procedures.len();
+            self.module.procedures.push(new_procedure(name, level, Some(parent)));
+            self.scope.insert(p.name().to_owned(), Binding::Procedure(id));
+
+            self.scope.step_in();
+            self.current = id;
+            for param in p.params.iter() {
+                let ty = self.scope.resolve_type(&param.definition.type_name)?;
+                let kind = if param.is_var { VariableKind::VarParam } else { VariableKind::Param };
+                for name in param.definition.identifiers.iter() {
+                    self.new_variable(name, kind, ty.clone());
+                }
+            }
+            self.lower_declare(&p.declare)?;
+            self.lower_statement_list(&p.body)?;
+            self.emit(Quad::Return(None));
+            self.current = parent;
+            self.scope.step_out();
+        }
+        Ok(())
+    }
+
+    fn lower_statement_list(&mut self, list: &StatementList) -> Result<()> {
+        for statement in list.iter() {
+            self.lower_statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn lower_statement(&mut self, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::Conditional(con) => {
+                let else_label = self.new_label();
+                let end_label = self.new_label();
+                self.lower_condition(&con.condition, else_label)?;
+                self.lower_statement_list(&con.body)?;
+                self.emit(Quad::Jump(end_label));
+                self.emit(Quad::Label(else_label));
+                self.lower_statement_list(&con.else_body)?;
+                self.emit(Quad::Label(end_label));
+            }
+            Statement::Loop(lo) => {
+                let cond_label = self.new_label();
+                let end_label = self.new_label();
+                self.emit(Quad::Label(cond_label));
+                self.lower_condition(&lo.condition, end_label)?;
+                self.lower_statement_list(&lo.body)?;
+                self.emit(Quad::Jump(cond_label));
+                self.emit(Quad::Label(end_label));
+            }
+            Statement::Input(input) => {
+                let variable = *self.scope.variable(input)?;
+                let access = self.variable_access(variable);
+                let ty = scope::scalar(&self.module.variables[variable].ty, input)?;
+                let dst = self.new_temp();
+                self.emit(Quad::Read { dst, ty });
+                self.store(access, Operand::Temp(dst));
+            }
+            Statement::Output(output) => {
+                let (src, ty) = self.lower_expression(output)?;
+                self.emit(Quad::Write { src, ty });
+            }
+            Statement::Return(ret) => {
+                let (value, _) = self.lower_expression(ret)?;
+                self.emit(Quad::Return(Some(value)));
+            }
+            Statement::Assign(assign) => {
+                let (access, ty) = self.lower_access(&assign.variable)?;
+                if ty.scalar().is_some() {
+                    let (value, _) = self.lower_expression(&assign.value)?;
+                    self.store(access, value);
+                } else {
+                    let (src, _) = self.lower_access(scope::structured_source(assign)?)?;
+                    let dst = self.address(access);
+                    let src = self.address(src);
+                    self.emit(Quad::CopyBlock { dst, src, size: ty.size() });
+                }
+            }
+            Statement::Call(call) => self.lower_call(call)?,
+            Statement::Error(error) => return Err(scope::syntax_error(error)),
+        }
+        Ok(())
+    }
+
+    fn lower_call(&mut self, call: &Positional<CallStatement>) -> Result<()> {
+        let procedure = *self.scope.procedure(call)?;
+
+        let params = self.module.procedures[procedure].params.clone();
+        for (param, exp) in params.iter().zip(call.params.iter()) {
+            let param = self.module.variables[*param].clone();
+            if param.kind == VariableKind::VarParam || param.ty.scalar().is_none() {
+                let repr = scope::referenced_argument(exp, param.kind == VariableKind::VarParam, &param.name)?;
+                let (access, _) = self.lower_access(repr)?;
+                let addr = self.address(access);
+                if param.kind == VariableKind::VarParam {
+                    self.emit(Quad::Param(addr));
+                } else {
+                    self.emit(Quad::ParamBlock { addr, size: param.ty.size() });
+                }
+            } else {
+                let (value, _) = self.lower_expression(exp)?;
+                self.emit(Quad::Param(value));
+            }
+        }
+        self.emit(Quad::Call { procedure, argc: call.params.len() });
+        Ok(())
+    }
+
+    /// Jump to `target` if the relation does not hold
+    fn lower_condition(&mut self, rel: &RelationExpression, target: Label) -> Result<()> {
+        let (lhs, _) = self.lower_expression(&rel.left)?;
+        let (rhs, _) = self.lower_expression(&rel.right)?;
+        self.emit(Quad::Branch { rel: RelOp::from_image(&rel.op).negate(), lhs, rhs, target });
+        Ok(())
+    }
+
+    fn lower_expression(&mut self, exp: &Expression) -> Result<(Operand, ScalarType)> {
+        match exp {
+            Expression::Binary(binary) => {
+                let left = self.lower_expression(&binary.left)?;
+                let right = self.lower_expression(&binary.right)?;
+                Ok(self.binary(binary.op, left, right))
+            }
+            Expression::Factor(factor) => self.lower_factor(factor),
+        }
+    }
+
+    fn binary(&mut self, op: BinaryOperator, (lhs, lhs_type): (Operand, ScalarType), (rhs, rhs_type): (Operand, ScalarType)) -> (Operand, ScalarType) {
+        let dst = self.new_temp();
+        self.emit(Quad::Binary { op: op.into(), dst, lhs, rhs });
+        let ty = if lhs_type == ScalarType::Char && rhs_type == ScalarType::Char {
+            ScalarType::Char
+        } else {
+            ScalarType::Integer
+        };
+        (Operand::Temp(dst), ty)
+    }
+
+    fn lower_factor(&mut self, factor: &Positional<ExpressionFactor>) -> Result<(Operand, ScalarType)> {
+        match factor.inner() {
+            ExpressionFactor::Bracket(exp) => self.lower_expression(exp),
+            ExpressionFactor::Constant(c) => Ok((Operand::Const(*c as i64), ScalarType::Integer)),
+            ExpressionFactor::Variable(repr) => {
+                let (access, ty) = self.lower_access(repr)?;
+                let ty = scope::scalar(&ty, &repr.base)?;
+                Ok((self.load(access), ty))
+            }
+        }
+    }
+
+    fn variable_access(&self, variable: VariableId) -> Access {
+        match self.module.variables[variable].kind {
+            // the cell of a var parameter holds the address of the argument
+            VariableKind::VarParam => Access::Indirect(Operand::Var(variable)),
+            _ => Access::Direct(variable),
+        }
+    }
+
+    /// Compute where a variable represent lives, with field and index offsets applied
+    fn lower_access(&mut self, repr: &VariableRepresent) -> Result<(Access, Type)> {
+        let variable = *self.scope.variable(&repr.base)?;
+        let mut access = self.variable_access(variable);
+        let mut ty = self.module.variables[variable].ty.clone();
+
+        if let Some(visit) = &repr.visit {
+            if let Some(field) = &visit.dot {
+                let (offset, field_type) = scope::field(&ty, field)?;
+                let field_type = field_type.clone();
+                let base = self.address(access);
+                access = Access::Indirect(self.offset(base, Operand::Const(offset as i64)));
+                ty = field_type;
+            }
+
+            if let Some(index) = &visit.sqbr {
+                let (base_type, lower_bound, upper_bound) = scope::element(&ty, repr, index)?;
+                let checked = self.options.bounds_check && index.constant().is_none();
+                let (index, _) = self.lower_expression(index)?;
+                if checked {
+                    self.emit(Quad::BoundsCheck { index, lower_bound, upper_bound });
+                }
+                // rebase index to start from zero
+                let index = if lower_bound == 0 {
+                    index
+                } else {
+                    let dst = self.new_temp();
+                    self.emit(Quad::Binary { op: BinaryOp::Sub, dst, lhs: index, rhs: Operand::Const(lower_bound as i64) });
+                    Operand::Temp(dst)
+                };
+                let base = self.address(access);
+                access = Access::Indirect(self.offset(base, index));
+                ty = Type::Scalar(base_type);
+            }
+        }
+        Ok((access, ty))
+    }
+
+    fn offset(&mut self, base: Operand, index: Operand) -> Operand {
+        if index == Operand::Const(0) {
+            return base;
+        }
+        let dst = self.new_temp();
+        self.emit(Quad::Offset { dst, base, index });
+        Operand::Temp(dst)
+    }
+
+    fn address(&mut self, access: Access) -> Operand {
+        match access {
+            Access::Direct(var) => {
+                let dst = self.new_temp();
+                self.emit(Quad::AddressOf { dst, var });
+                Operand::Temp(dst)
+            }
+            Access::Indirect(addr) => addr,
+        }
+    }
+
+    fn load(&mut self, access: Access) -> Operand {
+        match access {
+            Access::Direct(var) => Operand::Var(var),
+            Access::Indirect(addr) => {
+                let dst = self.new_temp();
+                self.emit(Quad::Load { dst, addr });
+                Operand::Temp(dst)
+            }
+        }
+    }
+
+    fn store(&mut self, access: Access, src: Operand) {
+        match access {
+            Access::Direct(var) => self.emit(Quad::Copy { dst: Place::Var(var), src }),
+            Access::Indirect(addr) => self.emit(Quad::Store { addr, src }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{lower, Quad, VariableKind};
+    use snl_rdp::Parser;
+
+    fn dump(source: &str) -> String {
+        let tokens = snl_lexer::read_tokens(source).unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        lower(&ast, Default::default()).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_lower_program() {
+        assert_eq!(dump(include_str!("../../examples/program1.snl")), r#"program p [level 0, frame 2]
+    var v1: integer @0
+    var v2: char @1
+    t0 = read integer
+    v1 = t0
+    t1 = v1 + 10
+    v1 = t1
+    write integer v1
+    return
+"#);
+    }
+
+    #[test]
+    fn test_lower_access() {
+        assert_eq!(dump(r#"program p
+type r = record
+  integer a;
+  array [2..5] of char b;
+end;
+var r x;
+    integer i;
+procedure q(var integer k);
+begin
+  k := x.b[i + 1];
+  if k < 3 then
+    x := x
+  else
+    write(k)
+  fi
+end
+begin
+  q(i)
+end."#), r#"program p [level 0, frame 6]
+    var x: record integer a; array [2..5] of char b; end @0
+    var i: integer @5
+    t0 = &i
+    param t0
+    call q, 1
+    return
+
+procedure q [level 1, frame 1]
+    param var k: integer @0
+    t0 = &p.x
+    t1 = &t0[1]
+    t2 = p.i + 1
+    t3 = t2 - 2
+    t4 = &t1[t3]
+    t5 = *t4
+    *k = t5
+    t6 = *k
+    if t6 >= 3 goto L0
+    t7 = &p.x
+    t8 = &p.x
+    *t7 = *t8 [5]
+    goto L1
+L0:
+    t9 = *k
+    write integer t9
+L1:
+    return
+"#);
+    }
+
+    #[test]
+    fn test_lower_nested() {
+        let tokens = snl_lexer::read_tokens(include_str!("../../examples/bubble.snl")).unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let module = lower(&ast, Default::default()).unwrap();
+        assert_eq!(module.procedures.len(), 2);
+        let q = &module.procedures[1];
+        assert_eq!((q.name.as_str(), q.level, q.parent), ("q", 1, Some(0)));
+        assert_eq!(module.variables[q.params[0]].kind, VariableKind::Param);
+        assert_eq!(module.procedures[0].frame_size, 23);
+        assert!(module.procedures[0].code.contains(&Quad::Call { procedure: 1, argc: 1 }));
+    }
+}
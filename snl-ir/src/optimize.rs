@@ -0,0 +1,257 @@
+use crate::ir::*;
+use snl_utils::ast::BinaryOperator;
+use std::collections::{HashMap, HashSet};
+
+/// Fold constant arithmetic, propagate constants through straight-line code and
+/// remove branches decided by constants, along with the code they make unreachable
+pub fn optimize(module: &mut Module) {
+    // variables which can be written through an address
+    let aliased: HashSet<VariableId> = module
+        .procedures
+        .iter()
+        .flat_map(|procedure| procedure.code.iter())
+        .filter_map(|quad| match quad {
+            Quad::AddressOf { var, .. } => Some(*var),
+            _ => None,
+        })
+        .collect();
+
+    for procedure in module.procedures.iter_mut() {
+        // removing a branch can join blocks, which exposes more constants
+        loop {
+            let code = propagate(&procedure.code, &aliased);
+            let code = remove_unreachable(code);
+            let code = remove_unused_temps(code);
+            if code == procedure.code {
+                break;
+            }
+            procedure.code = code;
+        }
+    }
+}
+
+/// Replace operands known to be constant inside each block, folding what becomes constant
+fn propagate(code: &[Quad], aliased: &HashSet<VariableId>) -> Vec<Quad> {
+    let mut known: HashMap<Place, i64> = HashMap::new();
+    let mut result = Vec::with_capacity(code.len());
+    for quad in code.iter() {
+        let mut quad = quad.clone();
+        match &mut quad {
+            // control may come from elsewhere
+            Quad::Label(_) => known.clear(),
+            Quad::Binary { op, dst, lhs, rhs } => {
+                substitute(lhs, &known);
+                substitute(rhs, &known);
+                let dst = *dst;
+                match (*lhs, *rhs) {
+                    (Operand::Const(lhs), Operand::Const(rhs)) => match BinaryOperator::from(*op).eval(lhs, rhs) {
+                        Some(value) => {
+                            known.insert(Place::Temp(dst), value);
+                            quad = Quad::Copy { dst: Place::Temp(dst), src: Operand::Const(value) };
+                        }
+                        None => {
+                            known.remove(&Place::Temp(dst));
+                        }
+                    },
+                    _ => {
+                        known.remove(&Place::Temp(dst));
+                    }
+                }
+            }
+            Quad::Copy { dst, src } => {
+                substitute(src, &known);
+                match src {
+                    Operand::Const(value) => known.insert(*dst, *value),
+                    _ => known.remove(dst),
+                };
+            }
+            Quad::AddressOf { dst, .. } | Quad::Load { dst, .. } | Quad::Read { dst, .. } => {
+                known.remove(&Place::Temp(*dst));
+            }
+            Quad::Offset { dst, index, .. } => {
+                substitute(index, &known);
+                known.remove(&Place::Temp(*dst));
+            }
+            Quad::Store { src, .. } => {
+                substitute(src, &known);
+                known.retain(|place, _| !matches!(place, Place::Var(var) if aliased.contains(var)));
+            }
+            Quad::CopyBlock { .. } => {
+                known.retain(|place, _| !matches!(place, Place::Var(var) if aliased.contains(var)));
+            }
+            Quad::BoundsCheck { index, lower_bound, upper_bound } => {
+                substitute(index, &known);
+                if let Operand::Const(index) = *index {
+                    if index >= *lower_bound as i64 && index <= *upper_bound as i64 {
+                        continue;
+                    }
+                }
+            }
+            Quad::Branch { rel, lhs, rhs, target } => {
+                substitute(lhs, &known);
+                substitute(rhs, &known);
+                if let (Operand::Const(lhs), Operand::Const(rhs)) = (*lhs, *rhs) {
+                    if !rel.eval(lhs, rhs) {
+                        continue;
+                    }
+                    quad = Quad::Jump(*target);
+                }
+            }
+            Quad::Param(value) | Quad::Write { src: value, .. } | Quad::Return(Some(value)) => {
+                substitute(value, &known);
+            }
+            // the callee may write any variable it can reach
+            Quad::Call { .. } => known.retain(|place, _| matches!(place, Place::Temp(_))),
+            Quad::Jump(_) | Quad::ParamBlock { .. } | Quad::Return(None) => {}
+        }
+        result.push(quad);
+    }
+    result
+}
+
+fn substitute(operand: &mut Operand, known: &HashMap<Place, i64>) {
+    let place = match *operand {
+        Operand::Temp(temp) => Place::Temp(temp),
+        Operand::Var(var) => Place::Var(var),
+        Operand::Const(_) => return,
+    };
+    if let Some(value) = known.get(&place) {
+        *operand = Operand::Const(*value);
+    }
+}
+
+/// Remove code following jumps and returns up to the next label jumped to,
+/// labels nothing jumps to and jumps to the label right after them
+fn remove_unreachable(code: Vec<Quad>) -> Vec<Quad> {
+    let targets: HashSet<Label> = code
+        .iter()
+        .filter_map(|quad| match quad {
+            Quad::Jump(target) | Quad::Branch { target, .. } => Some(*target),
+            _ => None,
+        })
+        .collect();
+
+    let mut result = Vec::with_capacity(code.len());
+    let mut reachable = true;
+    for (i, quad) in code.iter().enumerate() {
+        match quad {
+            Quad::Label(label) => {
+                if !targets.contains(label) {
+                    continue;
+                }
+                reachable = true;
+            }
+            _ if !reachable => continue,
+            Quad::Jump(target) => {
+                reachable = false;
+                if code[i + 1..].iter().take_while(|quad| matches!(quad, Quad::Label(_))).any(|quad| quad == &Quad::Label(*target)) {
+                    continue;
+                }
+            }
+            Quad::Return(_) => reachable = false,
+            _ => {}
+        }
+        result.push(quad.clone());
+    }
+    result
+}
+
+/// Remove constants copied to temps which are no longer read
+fn remove_unused_temps(code: Vec<Quad>) -> Vec<Quad> {
+    let mut used = HashSet::new();
+    for quad in code.iter() {
+        let operands: Vec<&Operand> = match quad {
+            Quad::Binary { lhs, rhs, .. } | Quad::Branch { lhs, rhs, .. } => vec![lhs, rhs],
+            Quad::Copy { src, .. } | Quad::Param(src) | Quad::Write { src, .. } | Quad::Return(Some(src)) => vec![src],
+            Quad::Offset { base, index, .. } => vec![base, index],
+            Quad::Load { addr, .. } | Quad::ParamBlock { addr, .. } => vec![addr],
+            Quad::Store { addr, src } => vec![addr, src],
+            Quad::CopyBlock { dst, src, .. } => vec![dst, src],
+            Quad::BoundsCheck { index, .. } => vec![index],
+            _ => vec![],
+        };
+        for operand in operands {
+            if let Operand::Temp(temp) = operand {
+                used.insert(*temp);
+            }
+        }
+    }
+    code.into_iter()
+        .filter(|quad| !matches!(quad, Quad::Copy { dst: Place::Temp(temp), src: Operand::Const(_) } if !used.contains(temp)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{lower, optimize, Quad, Operand};
+    use snl_rdp::Parser;
+    use snl_semantic::Semantic;
+
+    #[test]
+    fn test_optimize() {
+        let tokens = snl_lexer::read_tokens("program p
+var integer a, b;
+    array [1..3] of integer c;
+begin
+  a := 2 * (3 + 1);
+  b := a - 5;
+  if b < 1 then
+    write(a)
+  else
+    write(b / 0)
+  fi;
+  c[b] := a;
+  read(a);
+  write(a + b)
+end.").unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let mut module = lower(&ast, Default::default()).unwrap();
+        optimize(&mut module);
+        assert_eq!(module.to_string(), "program p [level 0, frame 5]
+    var a: integer @0
+    var b: integer @1
+    var c: array [1..3] of integer @2
+    a = 8
+    b = 3
+    t3 = 3 / 0
+    write integer t3
+    t5 = &c
+    t6 = &t5[2]
+    *t6 = 8
+    t7 = read integer
+    a = t7
+    t8 = a + 3
+    write integer t8
+    return
+");
+    }
+
+    #[test]
+    fn test_optimize_examples() {
+        let examples = std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/../examples")).unwrap();
+        for entry in examples {
+            let source = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            let tokens = snl_lexer::read_tokens(&source).unwrap();
+            let ast = Parser::new(tokens).parse().unwrap();
+            // some examples show off semantic errors, only correct programs are lowered
+            if !Semantic::new(&ast).analyze().0.is_empty() {
+                continue;
+            }
+            let mut module = lower(&ast, Default::default()).unwrap();
+            let before = module.clone();
+            optimize(&mut module);
+            for (procedure, original) in module.procedures.iter().zip(before.procedures.iter()) {
+                assert!(procedure.code.len() <= original.code.len());
+                // nothing is left to fold
+                assert!(!procedure.code.iter().any(|quad| matches!(
+                    quad,
+                    Quad::Binary { lhs: Operand::Const(_), rhs: Operand::Const(_), .. }
+                        | Quad::Branch { lhs: Operand::Const(_), rhs: Operand::Const(_), .. }
+                )));
+            }
+            let optimized = module.to_string();
+            optimize(&mut module);
+            assert_eq!(module.to_string(), optimized);
+        }
+    }
+}
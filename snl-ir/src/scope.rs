@@ -0,0 +1,152 @@
+use crate::error::Error;
+use crate::ir::{ScalarType, Type};
+use snl_semantic::symbol::SymbolTable;
+use snl_utils::ast::*;
+
+pub type Result<T> = std::result::Result<T, Positional<Error>>;
+
+fn at(position: (u32, u32)) -> impl Fn(Error) -> Positional<Error> {
+    move |error| Positional::from_position(position, error)
+}
+
+/// What a name is bound to, `V` and `P` are whatever a backend keeps for variables and procedures
+pub enum Binding<V, P> {
+    Variable(V),
+    Procedure(P),
+    Type(Type),
+}
+
+impl<V, P> Binding<V, P> {
+    /// Variable `name` is bound to, given the binding found for it
+    pub fn variable<'b>(binding: Option<&'b Self>, name: &Positional<String>) -> Result<&'b V> {
+        match binding {
+            Some(Binding::Variable(variable)) => Ok(variable),
+            Some(_) => Err(at(name.position())(Error::UnexpectedSymbol(name.inner().clone(), "variable"))),
+            None => Err(at(name.position())(Error::UndefinedIdentifier(name.inner().clone()))),
+        }
+    }
+
+    /// Procedure called by `call`, given the binding found for its name
+    pub fn procedure<'b>(binding: Option<&'b Self>, call: &Positional<CallStatement>) -> Result<&'b P> {
+        match binding {
+            Some(Binding::Procedure(procedure)) => Ok(procedure),
+            Some(_) => Err(at(call.position())(Error::UnexpectedSymbol(call.name().to_owned(), "procedure"))),
+            None => Err(at(call.position())(Error::UndefinedIdentifier(call.name().to_owned()))),
+        }
+    }
+}
+
+/// Resolve a declared type, `lookup` finds the binding of a type name where the type is declared
+pub fn resolve_type<'b, V: 'b, P: 'b, F>(ty: &Positional<SNLType>, lookup: &F) -> Result<Type>
+    where F: Fn(&str) -> Option<&'b Binding<V, P>> {
+    Ok(match ty.inner() {
+        SNLType::Integer => Type::Scalar(ScalarType::Integer),
+        SNLType::Char => Type::Scalar(ScalarType::Char),
+        SNLType::Array(arr) => Type::Array {
+            base: match arr.base {
+                SNLBaseType::Integer => ScalarType::Integer,
+                SNLBaseType::Char => ScalarType::Char,
+            },
+            lower_bound: arr.lower_bound,
+            upper_bound: arr.upper_bound,
+        },
+        SNLType::Record(record) => {
+            let mut fields = Vec::new();
+            for r in record.iter() {
+                let field_type = resolve_type(&r.type_name, lookup)?;
+                for id in r.identifiers.iter() {
+                    fields.push((id.inner().clone(), field_type.clone()));
+                }
+            }
+            Type::Record(fields)
+        }
+        SNLType::Others(name) => match lookup(name) {
+            Some(Binding::Type(ty)) => ty.clone(),
+            Some(_) => return Err(at(ty.position())(Error::UnexpectedSymbol(name.clone(), "type"))),
+            None => return Err(at(ty.position())(Error::UndefinedIdentifier(name.clone()))),
+        },
+    })
+}
+
+/// Nested scopes of bindings, for backends which see every declaration once
+pub struct Scope<V, P> {
+    symbols: SymbolTable<Binding<V, P>>,
+}
+
+impl<V, P> Default for Scope<V, P> {
+    fn default() -> Self {
+        Self { symbols: Default::default() }
+    }
+}
+
+impl<V, P> Scope<V, P> {
+    pub fn step_in(&mut self) {
+        self.symbols.step_in();
+    }
+
+    pub fn step_out(&mut self) {
+        self.symbols.step_out();
+    }
+
+    pub fn insert(&mut self, name: String, binding: Binding<V, P>) {
+        self.symbols.insert(name, binding);
+    }
+
+    pub fn variable(&self, name: &Positional<String>) -> Result<&V> {
+        Binding::variable(self.symbols.query(name), name)
+    }
+
+    pub fn procedure(&self, call: &Positional<CallStatement>) -> Result<&P> {
+        Binding::procedure(self.symbols.query(call.name()), call)
+    }
+
+    pub fn resolve_type(&self, ty: &Positional<SNLType>) -> Result<Type> {
+        resolve_type(ty, &|name: &str| self.symbols.query(name))
+    }
+}
+
+/// Type of a variable used as an operand, which has to be a scalar
+pub fn scalar(ty: &Type, name: &Positional<String>) -> Result<ScalarType> {
+    ty.scalar().ok_or_else(|| at(name.position())(Error::NonScalarOperand(name.inner().clone())))
+}
+
+/// Offset and type of the record field visited by `.field`
+pub fn field<'t>(ty: &'t Type, field: &Positional<String>) -> Result<(usize, &'t Type)> {
+    ty.field(field).ok_or_else(|| at(field.position())(Error::UndefinedRecordField(field.inner().clone())))
+}
+
+/// Element type and bounds of the array `repr` visits with `index`
+pub fn element(ty: &Type, repr: &VariableRepresent, index: &Expression) -> Result<(ScalarType, usize, usize)> {
+    match ty {
+        Type::Array { base, lower_bound, upper_bound } => Ok((*base, *lower_bound, *upper_bound)),
+        _ => Err(at(index.position())(Error::UnexpectedSymbol(repr.base.inner().clone(), "array"))),
+    }
+}
+
+/// Variable a structured value is assigned from, no other expression has a structured value
+pub fn structured_source(assign: &AssignStatement) -> Result<&VariableRepresent> {
+    assign.value.as_variable().ok_or_else(|| {
+        at(assign.value.position())(Error::NonScalarOperand(assign.variable.base.inner().clone()))
+    })
+}
+
+/// Variable passed to a `var` param, or to a param of structured type, `name` is reported if it is not one
+pub fn referenced_argument<'e>(exp: &'e Expression, is_var: bool, name: &str) -> Result<&'e VariableRepresent> {
+    exp.as_variable().ok_or_else(|| {
+        at(exp.position())(if is_var {
+            Error::InvalidVarArgument(name.to_owned())
+        } else {
+            Error::NonScalarOperand(name.to_owned())
+        })
+    })
+}
+
+/// Variable read by `read(name)`, which takes a plain name
+pub fn input_variable(name: &Positional<String>) -> VariableRepresent {
+    VariableRepresent { base: Positional::from_span(name.span, name.inner().clone()), visit: None }
+}
+
+/// Error of a statement an error-recovering parser left unparsed
+pub fn syntax_error(error: &Positional<String>) -> Positional<Error> {
+    at(error.position())(Error::SyntaxError(error.inner().clone()))
+}
@@ -1,3 +1,6 @@
+use snl_utils::ast::Positional;
+use snl_utils::error::LexError;
+use snl_utils::span::{FileId, Location, Span};
 use snl_utils::token::{Token, TokenType};
 use std::str::FromStr;
 
@@ -11,17 +14,43 @@ enum LexerState {
     InputDot,
 }
 
-pub fn read_tokens(input: &str) -> Result<Vec<Token>, String> {
+/// Locations of every character in `input`, followed by the end of input
+fn locations(input: &str) -> Vec<Location> {
+    let mut result = Vec::new();
+    let (mut line, mut column) = (1, 1);
+    for (offset, ch) in input.char_indices() {
+        result.push(Location { offset, line, column });
+        if ch == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    result.push(Location { offset: input.len(), line, column });
+    result
+}
+
+/// Split `input` into tokens, spans refer to the default file
+pub fn read_tokens(input: &str) -> Result<Vec<Token>, Positional<LexError>> {
+    read_tokens_in(input, FileId::default())
+}
+
+/// Split `input` into tokens, spans refer to `file`
+pub fn read_tokens_in(input: &str, file: FileId) -> Result<Vec<Token>, Positional<LexError>> {
     let mut tokens = Vec::new();
 
     let mut state = LexerState::Start;
     let mut image = String::new();
 
-    let mut line = 1u32;
-    let mut column = 1u32;
-
-    let mut start_line = 0;
-    let mut start_column = 0;
+    let locations = locations(input);
+    // span of characters from index `start` to `end`, the trailing blank is at the end of input
+    let span = |start: usize, end: usize| Span {
+        file,
+        start: locations[start.min(locations.len() - 1)],
+        end: locations[end.min(locations.len() - 1)],
+    };
+    let mut start = 0;
 
     let mut chars: Vec<_> = input.chars().collect();
     chars.push(' ');
@@ -32,13 +61,6 @@ pub fn read_tokens(input: &str) -> Result<Vec<Token>, String> {
         }
 
         let ch = chars[i];
-        if ch == '\n' && (state == LexerState::Start || state == LexerState::Comment) {
-            line += 1;
-            column = 0;
-        } else {
-            column += ch.len_utf8() as u32;
-        }
-
         match state {
             LexerState::Start => {
                 i += 1;
@@ -47,8 +69,7 @@ pub fn read_tokens(input: &str) -> Result<Vec<Token>, String> {
                 }
                 image.push(ch);
 
-                start_line = line;
-                start_column = column;
+                start = i - 1;
                 if ch.is_alphabetic() {
                     state = LexerState::InputIdentifier;
                 } else if ch.is_numeric() {
@@ -61,11 +82,13 @@ pub fn read_tokens(input: &str) -> Result<Vec<Token>, String> {
                     image.pop();
                     state = LexerState::Comment;
                 } else {
+                    let token_type = TokenType::from_str(&image).map_err(|_| {
+                        Positional::from_span(span(start, i), LexError::InvalidCharacter(ch))
+                    })?;
                     tokens.push(Token {
-                        token_type: TokenType::from_str(&image)?,
+                        token_type,
                         image,
-                        line: start_line,
-                        column: start_column,
+                        span: span(start, i),
                     });
                     image = String::new();
                     continue;
@@ -83,12 +106,10 @@ pub fn read_tokens(input: &str) -> Result<Vec<Token>, String> {
                     i += 1;
                     image.push(ch);
                 } else {
-                    column -= ch.len_utf8() as u32;
                     tokens.push(Token {
                         token_type: TokenType::from_str(&image).unwrap_or(TokenType::Identifier),
                         image,
-                        line: start_line,
-                        column: start_column,
+                        span: span(start, i),
                     });
                     image = String::new();
                     state = LexerState::Start;
@@ -99,12 +120,10 @@ pub fn read_tokens(input: &str) -> Result<Vec<Token>, String> {
                     i += 1;
                     image.push(ch);
                 } else {
-                    column -= ch.len_utf8() as u32;
                     tokens.push(Token {
                         token_type: TokenType::Int,
                         image,
-                        line: start_line,
-                        column: start_column,
+                        span: span(start, i),
                     });
                     image = String::new();
                     state = LexerState::Start;
@@ -117,27 +136,27 @@ pub fn read_tokens(input: &str) -> Result<Vec<Token>, String> {
                     image.push(ch);
                     tokens.push(Token {
                         token_type: TokenType::Assign,
-                        image: image,
-                        line: start_line,
-                        column: start_column,
+                        image,
+                        span: span(start, i),
                     });
                     image = String::new();
                 } else {
-                    return Err(format!("invalid character {} after ':', expected '='", ch));
+                    return Err(Positional::from_span(span(i, i + 1), LexError::UnexpectedCharacter {
+                        after: ':',
+                        expected: '=',
+                        found: ch,
+                    }));
                 }
             }
             LexerState::InputDot => {
                 if ch == '.' {
                     i += 1;
                     image.push(ch);
-                } else {
-                    column -= ch.len_utf8() as u32;
                 }
                 tokens.push(Token {
-                    token_type: TokenType::from_str(&image)?,
-                    image: image,
-                    line: start_line,
-                    column: start_column,
+                    token_type: TokenType::from_str(&image).unwrap(),
+                    image,
+                    span: span(start, i),
                 });
                 image = String::new();
                 state = LexerState::Start;
@@ -145,18 +164,20 @@ pub fn read_tokens(input: &str) -> Result<Vec<Token>, String> {
         }
     }
 
+    let end = input.chars().count();
     tokens.push(Token {
         token_type: TokenType::EOF,
         image: "".to_string(),
-        line,
-        column,
+        span: span(end, end),
     });
     Ok(tokens)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::read_tokens;
+    use crate::{read_tokens, read_tokens_in};
+    use snl_utils::error::LexError;
+    use snl_utils::span::{FileId, Location};
 
     #[test]
     fn test_read_token() {
@@ -170,7 +191,36 @@ mod tests {
     write(v1);
 end."#).unwrap();
         for token in result {
-            println!("{}\t{:?}", token.line, token.token_type);
+            println!("{}\t{:?}", token.span.start.line, token.token_type);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_lex_errors() {
+        let error = read_tokens("program p\nbegin\n  a :- 1\nend.").unwrap_err();
+        assert_eq!(error.position(), (3, 6));
+        assert_eq!(error.inner(), &LexError::UnexpectedCharacter { after: ':', expected: '=', found: '-' });
+
+        let error = read_tokens("program p\nbegin\n  a := @\nend.").unwrap_err();
+        assert_eq!(error.position(), (3, 8));
+        assert_eq!(error.inner(), &LexError::InvalidCharacter('@'));
+    }
+
+    #[test]
+    fn test_token_spans() {
+        let tokens = read_tokens_in("program p\n{ é }\n  v1 := 10..\n", FileId(2)).unwrap();
+        let spans: Vec<_> = tokens.iter().map(|t| ((t.span.start.line, t.span.start.column), t.span.end.column)).collect();
+        assert_eq!(spans, vec![
+            ((1, 1), 8),
+            ((1, 9), 10),
+            ((3, 3), 5),
+            ((3, 6), 8),
+            ((3, 9), 11),
+            ((3, 11), 13),
+            ((4, 1), 1),
+        ]);
+        // offsets are in bytes, 'é' takes two of them
+        assert_eq!(tokens[2].span.start, Location { offset: 19, line: 3, column: 3 });
+        assert!(tokens.iter().all(|t| t.span.file == FileId(2)));
+    }
+}
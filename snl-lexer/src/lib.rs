@@ -1,4 +1,4 @@
-pub use lexer::read_tokens;
+pub use lexer::{read_tokens, read_tokens_in};
 
 mod lexer;
 
@@ -1,5 +1,6 @@
 use snl_utils::token::{Token, TokenType};
 use snl_utils::ast::*;
+use snl_utils::error::SyntaxError;
 use std::collections::HashMap;
 use std::str::FromStr;
 
@@ -20,7 +21,7 @@ pub enum ASTNodeValue {
     TypeName(Positional<SNLType>),
     BaseType(Positional<SNLBaseType>),
     ArrayType(Positional<SNLTypeArray>),
-    RecordType(SNLTypeRecord),
+    RecordType(Positional<SNLTypeRecord>),
 
     VarDeclaration(PositionalVec<TypedIdentifiers>),
     IdentifierList(PositionalVec<String>),
@@ -32,25 +33,34 @@ pub enum ASTNodeValue {
 
     Statement(Statement),
     StatementList(StatementList),
+    /// Statements between `begin` and `end`, spanning both
+    ProgramBody(Positional<StatementList>),
 
-    VariableVisit(VariableVisit),
+    /// Spans from the `.` or `[` to the field or `]`
+    VariableVisit(Positional<VariableVisit>),
     VariableVisitDot(Positional<String>),
-    VariableVisitSqbr(Box<Expression>),
+    VariableVisitSqbr(Positional<Box<Expression>>),
     CallStatementRest(Vec<Expression>),
+    /// Arguments spanning the brackets around them
+    CallArguments(Positional<Vec<Expression>>),
     AssignStatementRest((Option<VariableVisit>, Expression)),
 
     Operator(String),
-    Variable(VariableRepresent),
+    /// Spans from the name to the end of the visit
+    Variable(Positional<VariableRepresent>),
     Factor(Positional<ExpressionFactor>),
-    Term(ExpressionTerm),
-    TermPostFix((String, Positional<Box<ExpressionTerm>>)),
+    Term(Expression),
+    /// Operators and operands following the first operand, in source order
+    TermPostFix(Vec<(BinaryOperator, Expression)>),
 
     Expression(Expression),
-    ExpressionPostFix((String, Positional<Box<Expression>>)),
+    ExpressionPostFix(Vec<(BinaryOperator, Expression)>),
     RelationExpression(RelationExpression),
 }
 
-pub struct ConstructTable(HashMap<&'static str, fn(Vec<ASTNodeValue>) -> Result<ASTNodeValue, String>>);
+type Constructor = fn(Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>>;
+
+pub struct ConstructTable(HashMap<&'static str, Constructor>);
 
 impl Default for ConstructTable {
     fn default() -> Self {
@@ -121,7 +131,7 @@ impl Default for ConstructTable {
 }
 
 impl ConstructTable {
-    pub fn construct(&self, ty: &'static str, input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+    pub fn construct(&self, ty: &'static str, input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
         self.0[ty](input)
     }
 }
@@ -181,27 +191,35 @@ macro_rules! identifier {
     };
 }
 
-fn construct_program(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+/// Value of an integer literal, which may not fit in `T`
+fn integer<T: FromStr>(token: &Token) -> Result<T, Positional<SyntaxError>> {
+    T::from_str(&token.image).map_err(|_| Positional::from_token(token, SyntaxError::IntegerOverflow(token.image.clone())))
+}
+
+fn construct_program(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     let name = node!(input, ProgramHead);
     let declare = node!(input, DeclarePart);
-    let body = node!(input, StatementList);
-    Ok(ASTNodeValue::Program(Positional::from_position(name.position(), Program {
+    let body = node!(input, ProgramBody);
+    let dot = token!(input);
+    Ok(ASTNodeValue::Program(Positional::from_span(name.span.to(dot.span), Program {
         name: name.into_inner(),
         declare,
-        body,
+        body: body.into_inner(),
     })))
 }
 
-fn construct_program_head(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
-    pop!(input);
-    Ok(input.pop().unwrap())
+/// The program name, positioned at the `program` keyword where the program starts
+fn construct_program_head(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
+    let program = token!(input);
+    let name = node!(input, ProgramHead);
+    Ok(ASTNodeValue::ProgramHead(Positional::from_span(program.span, name.into_inner())))
 }
 
-fn construct_program_name(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_program_name(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     Ok(ASTNodeValue::ProgramHead(identifier!(input)))
 }
 
-fn construct_declare_part(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_declare_part(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     let type_declare = node_default!(input, TypeDeclaration);
     let variable_declare = node_default!(input, VarDeclaration);
     let procedure_declare = node_default!(input, ProcedureDeclaration);
@@ -212,25 +230,25 @@ fn construct_declare_part(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue,
     }))
 }
 
-fn construct_type_dec(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_type_dec(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     let result = node_default!(input, TypeDeclaration);
     Ok(ASTNodeValue::TypeDeclaration(result))
 }
 
-fn construct_type_declaration(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_type_declaration(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     pop!(input);
     Ok(pop!(input))
 }
 
-fn construct_type_dec_list(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_type_dec_list(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     let name = node!(input, String);
     pop!(input);
     let base = node!(input,TypeName);
     pop!(input);
 
     let mut more = node_default!(input, TypeDeclaration);
-    more.insert(0, Positional::from_position(
-        base.position(),
+    more.insert(0, Positional::from_span(
+        name.span,
         TypeDeclare {
             base,
             name: name.into_inner(),
@@ -239,40 +257,40 @@ fn construct_type_dec_list(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue,
     Ok(ASTNodeValue::TypeDeclaration(more))
 }
 
-fn construct_type_dec_list_more(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_type_dec_list_more(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     Ok(pop!(input))
 }
 
-fn construct_type_id(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_type_id(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     Ok(ASTNodeValue::String(identifier!(input)))
 }
 
-fn construct_type_name(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_type_name(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     Ok(ASTNodeValue::TypeName(match pop!(input) {
-        ASTNodeValue::BaseType(ty) => Positional::from_position(ty.position(), ty.into_inner().into()),
-        ASTNodeValue::ArrayType(ty) => Positional::from_position(ty.position(), SNLType::Array(ty.into_inner())),
-        ASTNodeValue::RecordType(ty) => Positional::from_position(ty[0].type_name.position(), SNLType::Record(ty)),
+        ASTNodeValue::BaseType(ty) => Positional::from_span(ty.span, ty.into_inner().into()),
+        ASTNodeValue::ArrayType(ty) => Positional::from_span(ty.span, SNLType::Array(ty.into_inner())),
+        ASTNodeValue::RecordType(ty) => Positional::from_span(ty.span, SNLType::Record(ty.into_inner())),
         ASTNodeValue::Terminal(token) => {
-            Positional::from_position(token.position(), SNLType::Others(token.image))
+            Positional::from_span(token.span, SNLType::Others(token.image))
         }
         _ => unreachable!(),
     }))
 }
 
-fn construct_base_type(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_base_type(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     let token = token!(input);
-    Ok(ASTNodeValue::BaseType(Positional::from_position(token.position(), match token.token_type {
+    Ok(ASTNodeValue::BaseType(Positional::from_span(token.span, match token.token_type {
         TokenType::Integer => SNLBaseType::Integer,
         TokenType::Char => SNLBaseType::Char,
         _ => unreachable!(),
     })))
 }
 
-fn construct_structure_type(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_structure_type(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     Ok(pop!(input))
 }
 
-fn construct_array_type(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_array_type(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     let arr = token!(input);
     pop!(input);
     let low = node!(input, Int);
@@ -281,24 +299,26 @@ fn construct_array_type(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, St
     pop!(input);
     pop!(input);
     let base_type = node!(input, BaseType);
-    Ok(ASTNodeValue::ArrayType(Positional::from_position(arr.position(), SNLTypeArray {
+    Ok(ASTNodeValue::ArrayType(Positional::from_span(arr.span.to(base_type.span), SNLTypeArray {
         base: base_type.into_inner(),
         lower_bound: low,
         upper_bound: top,
     })))
 }
 
-fn construct_int(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
-    Ok(ASTNodeValue::Int(usize::from_str(&token!(input).image).unwrap()))
+fn construct_int(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
+    let token = token!(input);
+    Ok(ASTNodeValue::Int(integer(&token)?))
 }
 
-fn construct_record_type(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
-    pop!(input);
+fn construct_record_type(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
+    let record = token!(input);
     let list = node!(input, FieldDecList);
-    Ok(ASTNodeValue::RecordType(list))
+    let end = token!(input);
+    Ok(ASTNodeValue::RecordType(Positional::from_span(record.span.to(end.span), list)))
 }
 
-fn construct_field_dec_list(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_field_dec_list(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     let type_name = node!(input, TypeName);
     let identifiers = node!(input, IdentifierList);
     pop!(input);
@@ -310,7 +330,7 @@ fn construct_field_dec_list(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue
     Ok(ASTNodeValue::FieldDecList(list))
 }
 
-fn construct_field_dec_list_more(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_field_dec_list_more(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     if input.is_empty() {
         Ok(ASTNodeValue::None)
     } else {
@@ -318,18 +338,18 @@ fn construct_field_dec_list_more(mut input: Vec<ASTNodeValue>) -> Result<ASTNode
     }
 }
 
-fn construct_field_dec_type(input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_field_dec_type(input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     construct_type_name(input)
 }
 
-fn construct_identifier_list(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_identifier_list(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     let id = identifier!(input);
     let mut list = node_default!(input, IdentifierList);
     list.insert(0, id);
     Ok(ASTNodeValue::IdentifierList(list))
 }
 
-fn construct_identifier_list_more(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_identifier_list_more(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     Ok(if input.is_empty() {
         ASTNodeValue::None
     } else {
@@ -338,59 +358,59 @@ fn construct_identifier_list_more(mut input: Vec<ASTNodeValue>) -> Result<ASTNod
     })
 }
 
-fn construct_var_dec(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_var_dec(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     Ok(pop!(input))
 }
 
-fn construct_var_declaration(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_var_declaration(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     pop!(input);
     Ok(pop!(input))
 }
 
-fn construct_var_dec_list(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_var_dec_list(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     let type_name = node!(input, TypeName);
     let identifiers = node!(input, IdentifierList);
     pop!(input);
     let mut more = node_default!(input, VarDeclaration);
-    more.insert(0, Positional::from_position(type_name.position(), TypedIdentifiers {
+    more.insert(0, Positional::from_span(type_name.span.to(identifiers.last().unwrap().span), TypedIdentifiers {
         type_name,
         identifiers,
     }));
     Ok(ASTNodeValue::VarDeclaration(more))
 }
 
-fn construct_var_dec_list_more(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_var_dec_list_more(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     Ok(pop!(input))
 }
 
-fn construct_proc_dec(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_proc_dec(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     let result = node_default!(input, ProcedureDeclaration);
     Ok(ASTNodeValue::ProcedureDeclaration(result))
 }
 
-fn construct_proc_declaration(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
-    let start = token!(input);
+fn construct_proc_declaration(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
+    pop!(input);
     let name = node!(input, String);
     pop!(input);
     let params = node_default!(input, ParamList);
     pop!(input, 2);
     let declare = node!(input, DeclarePart);
-    let body = node!(input, StatementList);
+    let body = node!(input, ProgramBody);
     let mut list = node_default!(input, ProcedureDeclaration);
-    list.insert(0, Positional::from_position(start.position(), ProcedureDeclare {
+    list.insert(0, Positional::from_span(name.span, ProcedureDeclare {
         name: name.into_inner(),
         params,
         declare: Box::new(declare),
-        body,
+        body: body.into_inner(),
     }));
     Ok(ASTNodeValue::ProcedureDeclaration(list))
 }
 
-fn construct_proc_name(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_proc_name(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     Ok(ASTNodeValue::String(identifier!(input)))
 }
 
-fn construct_param_list(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_param_list(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     Ok(if input.is_empty() {
         ASTNodeValue::None
     } else {
@@ -401,7 +421,7 @@ fn construct_param_list(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, St
     })
 }
 
-fn construct_param_list_more(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_param_list_more(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     Ok(if input.is_empty() {
         ASTNodeValue::None
     } else {
@@ -413,16 +433,15 @@ fn construct_param_list_more(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValu
     })
 }
 
-fn construct_param(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_param(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     let is_var = input.len() == 3;
-    let pos = if is_var {
-        Some(token!(input).position())
-    } else {
-        None
-    };
+    if is_var {
+        pop!(input);
+    }
     let type_name = node!(input, TypeName);
     let identifiers = node!(input, IdentifierList);
-    Ok(ASTNodeValue::Param(Positional::from_position(pos.unwrap_or(type_name.position()), Param {
+    // `var` is not part of the span
+    Ok(ASTNodeValue::Param(Positional::from_span(type_name.span.to(identifiers.last().unwrap().span), Param {
         is_var,
         definition: TypedIdentifiers {
             type_name,
@@ -431,27 +450,29 @@ fn construct_param(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String>
     })))
 }
 
-fn construct_proc_dec_part(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_proc_dec_part(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     Ok(pop!(input))
 }
 
-fn construct_proc_body(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_proc_body(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     Ok(pop!(input))
 }
 
-fn construct_program_body(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
-    pop!(input);
-    Ok(pop!(input))
+fn construct_program_body(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
+    let begin = token!(input);
+    let body = node!(input, StatementList);
+    let end = token!(input);
+    Ok(ASTNodeValue::ProgramBody(Positional::from_span(begin.span.to(end.span), body)))
 }
 
-fn construct_statement_list(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_statement_list(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     let stmt = node!(input, Statement);
     let mut list = node_default!(input, StatementList);
     list.insert(0, stmt);
     Ok(ASTNodeValue::StatementList(list))
 }
 
-fn construct_more_statement(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_more_statement(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     if input.is_empty() {
         Ok(ASTNodeValue::None)
     } else {
@@ -463,15 +484,15 @@ fn construct_more_statement(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue
     }
 }
 
-fn construct_statement(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_statement(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     Ok(match input.pop().unwrap() {
         ASTNodeValue::Statement(statement) => ASTNodeValue::Statement(statement),
         ASTNodeValue::Terminal(token) => {
             match input.pop().unwrap() {
-                ASTNodeValue::CallStatementRest(params) => {
-                    ASTNodeValue::Statement(Statement::Call(Positional::from_position(token.position(), CallStatement {
+                ASTNodeValue::CallArguments(params) => {
+                    ASTNodeValue::Statement(Statement::Call(Positional::from_span(token.span.to(params.span), CallStatement {
                         name: token.image,
-                        params,
+                        params: params.into_inner(),
                     })))
                 }
                 ASTNodeValue::AssignStatementRest((visit, exp)) => {
@@ -490,18 +511,18 @@ fn construct_statement(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Str
     })
 }
 
-fn construct_ass_call(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_ass_call(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     Ok(pop!(input))
 }
 
-fn construct_assignment_rest(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_assignment_rest(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     let visit = node_optional!(input, VariableVisit);
     pop!(input);
     let exp = node!(input, Expression);
-    Ok(ASTNodeValue::AssignStatementRest((visit, exp)))
+    Ok(ASTNodeValue::AssignStatementRest((visit.map(Positional::into_inner), exp)))
 }
 
-fn construct_conditional_statement(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_conditional_statement(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     pop!(input);
     let condition = node!(input, RelationExpression);
     pop!(input);
@@ -515,7 +536,7 @@ fn construct_conditional_statement(mut input: Vec<ASTNodeValue>) -> Result<ASTNo
     })))
 }
 
-fn construct_loop_statement(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_loop_statement(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     pop!(input);
     let condition = node!(input, RelationExpression);
     pop!(input);
@@ -523,29 +544,31 @@ fn construct_loop_statement(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue
     Ok(ASTNodeValue::Statement(Statement::Loop(LoopStatement { condition, body })))
 }
 
-fn construct_input_statement(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_input_statement(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     pop!(input, 2);
     Ok(ASTNodeValue::Statement(Statement::Input(identifier!(input))))
 }
 
-fn construct_output_statement(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_output_statement(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     pop!(input, 2);
     let exp = node!(input, Expression);
     Ok(ASTNodeValue::Statement(Statement::Output(exp)))
 }
 
-fn construct_return_statement(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_return_statement(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     pop!(input, 2);
     let exp = node!(input, Expression);
     Ok(ASTNodeValue::Statement(Statement::Return(exp)))
 }
 
-fn construct_call_statement_rest(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
-    pop!(input);
-    Ok(ASTNodeValue::CallStatementRest(node_default!(input, CallStatementRest)))
+fn construct_call_statement_rest(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
+    let open = token!(input);
+    let params = node_default!(input, CallStatementRest);
+    let close = token!(input);
+    Ok(ASTNodeValue::CallArguments(Positional::from_span(open.span.to(close.span), params)))
 }
 
-fn construct_call_statement_rest_exp(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_call_statement_rest_exp(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     Ok(if input.is_empty() {
         ASTNodeValue::None
     } else {
@@ -556,7 +579,7 @@ fn construct_call_statement_rest_exp(mut input: Vec<ASTNodeValue>) -> Result<AST
     })
 }
 
-fn construct_comma_exp(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_comma_exp(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     Ok(if input.is_empty() {
         ASTNodeValue::None
     } else {
@@ -568,7 +591,7 @@ fn construct_comma_exp(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Str
     })
 }
 
-fn construct_rel_exp(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_rel_exp(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     let left = node!(input, Expression);
     let op = node!(input, Operator);
     let right = node!(input, Expression);
@@ -579,87 +602,94 @@ fn construct_rel_exp(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Strin
     }))
 }
 
-fn construct_exp(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_exp(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     let term = node!(input, Term);
-    let (op, right) = match node_optional!(input, ExpressionPostFix) {
-        Some((op, right)) => (Some(op), Some(right)),
-        None => (None, None),
-    };
-    Ok(ASTNodeValue::Expression(Expression {
-        left: Positional::from_position(term.left.position(), term),
-        op,
-        right,
-    }))
+    let rest = node_optional!(input, ExpressionPostFix).unwrap_or_default();
+    Ok(ASTNodeValue::Expression(fold_left(term, rest)))
 }
 
-fn construct_exp_postfix(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_exp_postfix(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     Ok(if input.is_empty() {
         ASTNodeValue::None
     } else {
         let op = node!(input, Operator);
-        let exp = node!(input, Expression);
-        ASTNodeValue::ExpressionPostFix((op, Positional::from_position(exp.left.position(), Box::new(exp))))
+        let term = node!(input, Term);
+        let mut rest = node_optional!(input, ExpressionPostFix).unwrap_or_default();
+        rest.insert(0, (op.parse().unwrap(), term));
+        ASTNodeValue::ExpressionPostFix(rest)
     })
 }
 
-fn construct_term(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_term(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     let factor = node!(input, Factor);
-    let (op, right) = match node_optional!(input, TermPostFix) {
-        Some((op, right)) => (Some(op), Some(right)),
-        None => (None, None),
-    };
-    Ok(ASTNodeValue::Term(ExpressionTerm {
-        left: factor,
-        op,
-        right,
-    }))
+    let rest = node_optional!(input, TermPostFix).unwrap_or_default();
+    Ok(ASTNodeValue::Term(fold_left(Expression::Factor(factor), rest)))
 }
 
-fn construct_term_postfix(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_term_postfix(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     Ok(if input.is_empty() {
         ASTNodeValue::None
     } else {
         let op = node!(input, Operator);
-        let term = node!(input, Term);
-        ASTNodeValue::TermPostFix((op, Positional::from_position(term.left.position(), Box::new(term))))
+        let factor = node!(input, Factor);
+        let mut rest = node_optional!(input, TermPostFix).unwrap_or_default();
+        rest.insert(0, (op.parse().unwrap(), Expression::Factor(factor)));
+        ASTNodeValue::TermPostFix(rest)
     })
 }
 
-fn construct_factor(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+/// The grammar has no left recursion, operators of the same precedence
+/// are collected by the postfix rules and associate to the left here
+fn fold_left(first: Expression, rest: Vec<(BinaryOperator, Expression)>) -> Expression {
+    rest.into_iter().fold(first, |left, (op, right)| Expression::binary(left, op, right))
+}
+
+fn construct_factor(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     let (pos, factor) = match input.pop().unwrap() {
         ASTNodeValue::Terminal(token) => {
-            let pos = token.position();
-            let factor = match token.token_type {
-                TokenType::BracketOpen => ExpressionFactor::Bracket(Box::new(node!(input, Expression))),
-                TokenType::Int => ExpressionFactor::Constant(u32::from_str(&token.image).unwrap()),
+            match token.token_type {
+                TokenType::BracketOpen => {
+                    let exp = node!(input, Expression);
+                    let close = token!(input);
+                    (token.span.to(close.span), ExpressionFactor::Bracket(Box::new(exp)))
+                }
+                TokenType::Int => (token.span, ExpressionFactor::Constant(integer(&token)?)),
                 _ => unreachable!()
-            };
-            (pos, factor)
+            }
         }
-        ASTNodeValue::Variable(var) => (var.base.position(), ExpressionFactor::Variable(var)),
+        ASTNodeValue::Variable(var) => (var.span, ExpressionFactor::Variable(var.into_inner())),
         _ => unreachable!()
     };
-    Ok(ASTNodeValue::Factor(Positional::from_position(pos, factor)))
+    Ok(ASTNodeValue::Factor(Positional::from_span(pos, factor)))
 }
 
-fn construct_variable(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_variable(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     let id = identifier!(input);
     let visit = node_optional!(input, VariableVisit);
-    Ok(ASTNodeValue::Variable(VariableRepresent { base: id, visit }))
+    let span = visit.as_ref().map_or(id.span, |visit| id.span.to(visit.span));
+    Ok(ASTNodeValue::Variable(Positional::from_span(span, VariableRepresent {
+        base: id,
+        visit: visit.map(Positional::into_inner),
+    })))
 }
 
-fn construct_variable_visit(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_variable_visit(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     let dot = node_optional!(input, VariableVisitDot);
     let sqbr = node_optional!(input, VariableVisitSqbr);
 
-    Ok(if let (None, None) = (&dot, &sqbr) {
-        ASTNodeValue::None
-    } else {
-        ASTNodeValue::VariableVisit(VariableVisit { dot, sqbr })
-    })
+    let span = match (&dot, &sqbr) {
+        (None, None) => return Ok(ASTNodeValue::None),
+        (Some(dot), None) => dot.span,
+        (None, Some(sqbr)) => sqbr.span,
+        (Some(dot), Some(sqbr)) => dot.span.to(sqbr.span),
+    };
+    Ok(ASTNodeValue::VariableVisit(Positional::from_span(span, VariableVisit {
+        dot,
+        sqbr: sqbr.map(Positional::into_inner),
+    })))
 }
 
-fn construct_variable_visit_field(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_variable_visit_field(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     Ok(if input.is_empty() {
         ASTNodeValue::None
     } else {
@@ -668,16 +698,17 @@ fn construct_variable_visit_field(mut input: Vec<ASTNodeValue>) -> Result<ASTNod
     })
 }
 
-fn construct_variable_visit_index(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_variable_visit_index(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     Ok(if input.is_empty() {
         ASTNodeValue::None
     } else {
-        pop!(input);
+        let open = token!(input);
         let exp = node!(input, Expression);
-        ASTNodeValue::VariableVisitSqbr(Box::new(exp))
+        let close = token!(input);
+        ASTNodeValue::VariableVisitSqbr(Positional::from_span(open.span.to(close.span), Box::new(exp)))
     })
 }
 
-fn construct_op(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, String> {
+fn construct_op(mut input: Vec<ASTNodeValue>) -> Result<ASTNodeValue, Positional<SyntaxError>> {
     Ok(ASTNodeValue::Operator(token!(input).image))
 }
@@ -0,0 +1,362 @@
+//! Checks the LL(1) parser against the recursive descent parser: both have to build the
+//! same AST from every example and from generated programs, and fail at the same token
+//! when the programs are broken.
+
+use crate::Parser;
+use snl_utils::ast::Positional;
+use snl_utils::error::SyntaxError;
+use snl_utils::token::Token;
+
+/// Parse with both parsers and compare the results, `source` is shown when they differ
+fn compare(source: &str) {
+    let tokens = match snl_lexer::read_tokens(source) {
+        Ok(tokens) => tokens,
+        Err(_) => return,
+    };
+    compare_tokens(source, tokens);
+}
+
+fn compare_tokens(source: &str, tokens: Vec<Token>) {
+    let rdp = snl_rdp::Parser::new(tokens.clone()).parse();
+    let ll1 = Parser::new(tokens).parse();
+    match (rdp, ll1) {
+        (Ok(rdp), Ok(ll1)) => assert_eq!(rdp, ll1, "different ASTs of\n{}", source),
+        (Err(rdp), Err(ll1)) => assert_eq!(
+            rdp.position(),
+            ll1.position(),
+            "errors at different tokens in\n{}\nrdp: {}\nll1: {}",
+            source,
+            rdp.inner(),
+            ll1.inner(),
+        ),
+        (rdp, ll1) => panic!(
+            "only one parser accepts\n{}\nrdp: {:?}\nll1: {:?}",
+            source,
+            rdp.err().map(|e| e.into_inner()),
+            ll1.err().map(|e| e.into_inner()),
+        ),
+    }
+}
+
+/// Random programs following the grammar, from a fixed seed so failures can be reproduced
+struct Generator {
+    state: u64,
+    words: Vec<String>,
+}
+
+const NAMES: [&str; 6] = ["a", "b", "c", "p", "q", "t"];
+
+impl Generator {
+    fn new(seed: u64) -> Self {
+        Self { state: seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407), words: Vec::new() }
+    }
+
+    /// Number in `0..n`
+    fn below(&mut self, n: usize) -> usize {
+        // xorshift64*
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545F4914F6CDD1D) >> 33) as usize % n
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    fn push(&mut self, word: &str) {
+        self.words.push(word.to_owned());
+    }
+
+    /// Push one of the words
+    fn pick(&mut self, words: &[&str]) {
+        let word = words[self.below(words.len())];
+        self.push(word);
+    }
+
+    fn name(&mut self) {
+        self.pick(&NAMES);
+    }
+
+    fn integer(&mut self) {
+        let value = self.below(20).to_string();
+        self.push(&value);
+    }
+
+    /// Words of a whole program
+    fn program(mut self) -> Vec<String> {
+        self.push("program");
+        self.name();
+        self.declare(2);
+        self.body(3);
+        self.push(".");
+        self.words
+    }
+
+    fn declare(&mut self, depth: usize) {
+        if self.chance(40) {
+            self.push("type");
+            for _ in 0..=self.below(2) {
+                self.name();
+                self.push("=");
+                self.type_name(true);
+                self.push(";");
+            }
+        }
+        if self.chance(60) {
+            self.push("var");
+            for _ in 0..=self.below(2) {
+                self.type_name(true);
+                self.identifiers();
+                self.push(";");
+            }
+        }
+        if depth > 0 && self.chance(40) {
+            for _ in 0..=self.below(2) {
+                self.push("procedure");
+                self.name();
+                self.push("(");
+                if self.chance(70) {
+                    for i in 0..=self.below(3) {
+                        if i > 0 {
+                            self.push(";");
+                        }
+                        if self.chance(30) {
+                            self.push("var");
+                        }
+                        self.type_name(true);
+                        self.identifiers();
+                    }
+                }
+                self.push(")");
+                self.push(";");
+                self.declare(depth - 1);
+                self.body(2);
+            }
+        }
+    }
+
+    fn type_name(&mut self, full: bool) {
+        match self.below(if full { 5 } else { 3 }) {
+            0 => self.push("integer"),
+            1 => self.push("char"),
+            2 => {
+                self.push("array");
+                self.push("[");
+                self.integer();
+                self.push("..");
+                self.integer();
+                self.push("]");
+                self.push("of");
+                self.pick(&["integer", "char"]);
+            }
+            3 => {
+                self.push("record");
+                for _ in 0..=self.below(2) {
+                    self.type_name(false);
+                    self.identifiers();
+                    self.push(";");
+                }
+                self.push("end");
+            }
+            _ => self.name(),
+        }
+    }
+
+    fn identifiers(&mut self) {
+        for i in 0..=self.below(3) {
+            if i > 0 {
+                self.push(",");
+            }
+            self.name();
+        }
+    }
+
+    fn body(&mut self, depth: usize) {
+        self.push("begin");
+        self.statements(depth);
+        self.push("end");
+    }
+
+    fn statements(&mut self, depth: usize) {
+        for i in 0..=self.below(3) {
+            if i > 0 {
+                self.push(";");
+            }
+            self.statement(depth);
+        }
+    }
+
+    fn statement(&mut self, depth: usize) {
+        match self.below(if depth > 0 { 7 } else { 5 }) {
+            0 => {
+                self.push("read");
+                self.push("(");
+                self.name();
+                self.push(")");
+            }
+            1 => {
+                self.pick(&["write", "return"]);
+                self.push("(");
+                self.expression(2);
+                self.push(")");
+            }
+            2 | 3 => {
+                self.variable(2);
+                self.push(":=");
+                self.expression(2);
+            }
+            4 => {
+                self.name();
+                self.push("(");
+                if self.chance(70) {
+                    for i in 0..=self.below(3) {
+                        if i > 0 {
+                            self.push(",");
+                        }
+                        self.expression(2);
+                    }
+                }
+                self.push(")");
+            }
+            5 => {
+                self.push("if");
+                self.relation();
+                self.push("then");
+                self.statements(depth - 1);
+                self.push("else");
+                self.statements(depth - 1);
+                self.push("fi");
+            }
+            _ => {
+                self.push("while");
+                self.relation();
+                self.push("do");
+                self.statements(depth - 1);
+                self.push("endwh");
+            }
+        }
+    }
+
+    fn relation(&mut self) {
+        self.expression(2);
+        self.pick(&["<", "="]);
+        self.expression(2);
+    }
+
+    fn expression(&mut self, depth: usize) {
+        self.term(depth);
+        while self.chance(30) {
+            self.pick(&["+", "-"]);
+            self.term(depth);
+        }
+    }
+
+    fn term(&mut self, depth: usize) {
+        self.factor(depth);
+        while self.chance(30) {
+            self.pick(&["*", "/"]);
+            self.factor(depth);
+        }
+    }
+
+    fn factor(&mut self, depth: usize) {
+        match self.below(if depth > 0 { 3 } else { 2 }) {
+            0 => self.integer(),
+            1 => self.variable(depth),
+            _ => {
+                self.push("(");
+                self.expression(depth - 1);
+                self.push(")");
+            }
+        }
+    }
+
+    fn variable(&mut self, depth: usize) {
+        self.name();
+        if self.chance(20) {
+            self.push(".");
+            self.name();
+        }
+        if depth > 0 && self.chance(20) {
+            self.push("[");
+            self.expression(depth - 1);
+            self.push("]");
+        }
+    }
+}
+
+/// Words joined by spaces, with a line break after each `;`
+fn source(words: &[String]) -> String {
+    words.iter().map(|word| if word == ";" { ";\n".to_owned() } else { format!("{} ", word) }).collect()
+}
+
+#[test]
+fn test_examples() {
+    let examples = std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/../examples")).unwrap();
+    for entry in examples {
+        let path = entry.unwrap().path();
+        let source = std::fs::read_to_string(&path).unwrap();
+        compare(&source);
+        // every error found after recovery is at the same token as well
+        let tokens = snl_lexer::read_tokens(&source).unwrap();
+        let (_, rdp) = snl_rdp::Parser::new(tokens.clone()).parse_with_errors();
+        let ll1 = Parser::new(tokens).parse_with_errors().err().unwrap_or_default();
+        let positions = |errors: &[Positional<SyntaxError>]| errors.iter().map(|e| e.position()).collect::<Vec<_>>();
+        assert_eq!(positions(&rdp), positions(&ll1), "{}", path.display());
+    }
+}
+
+#[test]
+fn test_generated_programs() {
+    for seed in 0..500 {
+        let words = Generator::new(seed).program();
+        compare(&source(&words));
+    }
+}
+
+#[test]
+fn test_broken_programs() {
+    // tokens to replace or insert, one of each kind
+    let replacements = ["program", "type", "var", "procedure", "begin", "end", "if", "fi", "while", "endwh", "a", "1", "integer", "array", "record", ":=", "=", "<", "+", "*", ";", ",", "(", ")", "[", "]", ".", ".."];
+    for seed in 0..500 {
+        let mut generator = Generator::new(seed + 1000);
+        let mut words = Generator::new(seed).program();
+        let index = generator.below(words.len());
+        let replacement = replacements[generator.below(replacements.len())].to_owned();
+        match generator.below(3) {
+            0 => {
+                words.remove(index);
+            }
+            1 => words.insert(index, replacement),
+            _ => words[index] = replacement,
+        }
+        compare(&source(&words));
+    }
+}
+
+#[test]
+fn test_final_dot() {
+    let rejected = |words: &[String]| {
+        let source = source(words);
+        compare(&source);
+        let tokens = snl_lexer::read_tokens(&source).unwrap();
+        assert!(snl_rdp::Parser::new(tokens).parse().is_err(), "accepted\n{}", source);
+    };
+    for seed in 0..100 {
+        let mut words = Generator::new(seed).program();
+        words.pop();
+        rejected(&words);
+        for replacement in &[";", "end", "..", "a"] {
+            words.push(replacement.to_string());
+            rejected(&words);
+            words.pop();
+        }
+        words.push(".".to_owned());
+        for trailing in &[&["."][..], &["garbage", "here"], &["end", "."], &["program"]] {
+            let mut words = words.clone();
+            words.extend(trailing.iter().map(|word| word.to_string()));
+            rejected(&words);
+        }
+    }
+}
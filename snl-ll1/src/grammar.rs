@@ -0,0 +1,374 @@
+use snl_utils::token::TokenType;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+/// Grammar the LL(1) parser is generated from
+pub const SNL_GRAMMAR: &str = include_str!("../../grammar/snl_ll1.bnf");
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum GrammarError {
+    #[error("line {line}: expected `::=` after {name}")]
+    ExpectedDefine { line: usize, name: String },
+    #[error("line {line}: unknown terminal {name}")]
+    UnknownTerminal { line: usize, name: String },
+    #[error("line {line}: {name} is defined more than once")]
+    DuplicateRule { line: usize, name: String },
+    #[error("rule {name} is not terminated with `;`")]
+    UnterminatedRule { name: String },
+    #[error("{name} is used but never defined")]
+    UndefinedNonTerminal { name: String },
+    #[error("grammar has no rules")]
+    Empty,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Symbol<'a> {
+    Terminal(TokenType),
+    NonTerminal(&'a str),
+}
+
+impl Display for Symbol<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Symbol::Terminal(terminal) => f.write_str(terminal.grammar_name()),
+            Symbol::NonTerminal(name) => f.write_str(name),
+        }
+    }
+}
+
+/// One alternative of a rule, an empty `rhs` derives nothing
+#[derive(Debug, Clone, PartialEq)]
+pub struct Production<'a> {
+    pub lhs: &'a str,
+    pub rhs: Vec<Symbol<'a>>,
+}
+
+impl Display for Production<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ::=", self.lhs)?;
+        if self.rhs.is_empty() {
+            return f.write_str(" ε");
+        }
+        for symbol in self.rhs.iter() {
+            write!(f, " {}", symbol)?;
+        }
+        Ok(())
+    }
+}
+
+/// Two productions of a non-terminal predicted by the same terminal
+#[derive(Debug, PartialEq)]
+pub struct Conflict {
+    pub non_terminal: String,
+    pub terminal: TokenType,
+    /// Indexes of the productions in the grammar
+    pub productions: (usize, usize),
+}
+
+/// Context-free grammar with FIRST, FOLLOW and PREDICT sets of an LL(1) parser.
+///
+/// Rules are written as `Name ::= A b | C;`, terminals are the upper-case names of tokens
+/// and the first rule defines the start symbol.
+#[derive(Debug)]
+pub struct Grammar<'a> {
+    pub productions: Vec<Production<'a>>,
+    /// Non-terminals in the order they are defined
+    non_terminals: Vec<&'a str>,
+    nullable: HashSet<&'a str>,
+    first: HashMap<&'a str, BTreeSet<TokenType>>,
+    follow: HashMap<&'a str, BTreeSet<TokenType>>,
+}
+
+impl<'a> Grammar<'a> {
+    pub fn parse(source: &'a str) -> Result<Self, GrammarError> {
+        let mut productions = Vec::new();
+        let mut non_terminals = Vec::new();
+        let mut words = Words::new(source);
+        while let Some((line, name)) = words.next() {
+            if non_terminals.contains(&name) {
+                return Err(GrammarError::DuplicateRule { line, name: name.to_owned() });
+            }
+            match words.next() {
+                Some((_, "::=")) => {}
+                _ => return Err(GrammarError::ExpectedDefine { line, name: name.to_owned() }),
+            }
+            non_terminals.push(name);
+            let mut rhs = Vec::new();
+            loop {
+                match words.next() {
+                    Some((_, ";")) => break,
+                    // the previous word names the next rule
+                    Some((_, "::=")) | None => return Err(GrammarError::UnterminatedRule { name: name.to_owned() }),
+                    Some((_, "|")) => productions.push(Production { lhs: name, rhs: std::mem::take(&mut rhs) }),
+                    Some((line, word)) if is_terminal(word) => match TokenType::from_grammar_name(word) {
+                        Some(terminal) => rhs.push(Symbol::Terminal(terminal)),
+                        None => return Err(GrammarError::UnknownTerminal { line, name: word.to_owned() }),
+                    },
+                    Some((_, word)) => rhs.push(Symbol::NonTerminal(word)),
+                }
+            }
+            productions.push(Production { lhs: name, rhs });
+        }
+        if non_terminals.is_empty() {
+            return Err(GrammarError::Empty);
+        }
+        for symbol in productions.iter().flat_map(|production| production.rhs.iter()) {
+            if let Symbol::NonTerminal(name) = symbol {
+                if !non_terminals.contains(name) {
+                    return Err(GrammarError::UndefinedNonTerminal { name: (*name).to_owned() });
+                }
+            }
+        }
+
+        let mut grammar = Grammar {
+            productions,
+            non_terminals,
+            nullable: Default::default(),
+            first: Default::default(),
+            follow: Default::default(),
+        };
+        grammar.compute_first();
+        grammar.compute_follow();
+        Ok(grammar)
+    }
+
+    /// The grammar of SNL used by the parser
+    pub fn snl() -> Grammar<'static> {
+        Grammar::parse(SNL_GRAMMAR).expect("invalid SNL grammar")
+    }
+
+    pub fn start(&self) -> &'a str {
+        self.non_terminals[0]
+    }
+
+    pub fn non_terminals(&self) -> &[&'a str] {
+        &self.non_terminals
+    }
+
+    pub fn is_nullable(&self, non_terminal: &str) -> bool {
+        self.nullable.contains(non_terminal)
+    }
+
+    pub fn first(&self, non_terminal: &str) -> &BTreeSet<TokenType> {
+        &self.first[non_terminal]
+    }
+
+    pub fn follow(&self, non_terminal: &str) -> &BTreeSet<TokenType> {
+        &self.follow[non_terminal]
+    }
+
+    /// Terminals the parser chooses the production on: FIRST of the right-hand side,
+    /// and FOLLOW of the non-terminal if the right-hand side can derive nothing
+    pub fn predict(&self, production: usize) -> BTreeSet<TokenType> {
+        let production = &self.productions[production];
+        let (mut set, nullable) = self.first_of(&production.rhs);
+        if nullable {
+            set.extend(self.follow[production.lhs].iter().copied());
+        }
+        set
+    }
+
+    /// Terminals predicting more than one production of the same non-terminal
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let mut conflicts = Vec::new();
+        // production predicted by each non-terminal and terminal
+        let mut predicted: HashMap<(&str, TokenType), usize> = HashMap::new();
+        for (i, production) in self.productions.iter().enumerate() {
+            for terminal in self.predict(i) {
+                if let Some(previous) = predicted.insert((production.lhs, terminal), i) {
+                    conflicts.push(Conflict {
+                        non_terminal: production.lhs.to_owned(),
+                        terminal,
+                        productions: (previous, i),
+                    });
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// FIRST of a sequence of symbols and whether all of them are nullable
+    fn first_of(&self, symbols: &[Symbol<'a>]) -> (BTreeSet<TokenType>, bool) {
+        let mut set = BTreeSet::new();
+        for symbol in symbols.iter() {
+            match symbol {
+                Symbol::Terminal(terminal) => {
+                    set.insert(*terminal);
+                    return (set, false);
+                }
+                Symbol::NonTerminal(name) => {
+                    set.extend(self.first[name].iter().copied());
+                    if !self.nullable.contains(name) {
+                        return (set, false);
+                    }
+                }
+            }
+        }
+        (set, true)
+    }
+
+    fn compute_first(&mut self) {
+        for name in self.non_terminals.iter() {
+            self.first.insert(name, BTreeSet::new());
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for production in self.productions.iter() {
+                let (set, nullable) = self.first_of(&production.rhs);
+                let first = self.first.get_mut(production.lhs).unwrap();
+                let len = first.len();
+                first.extend(set);
+                changed |= first.len() != len;
+                if nullable {
+                    changed |= self.nullable.insert(production.lhs);
+                }
+            }
+        }
+    }
+
+    fn compute_follow(&mut self) {
+        for name in self.non_terminals.iter() {
+            self.follow.insert(name, BTreeSet::new());
+        }
+        self.follow.get_mut(self.start()).unwrap().insert(TokenType::EOF);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for production in self.productions.iter() {
+                for (i, symbol) in production.rhs.iter().enumerate() {
+                    if let Symbol::NonTerminal(name) = symbol {
+                        let (mut set, nullable) = self.first_of(&production.rhs[i + 1..]);
+                        if nullable {
+                            set.extend(self.follow[production.lhs].iter().copied());
+                        }
+                        let follow = self.follow.get_mut(name).unwrap();
+                        let len = follow.len();
+                        follow.extend(set);
+                        changed |= follow.len() != len;
+                    }
+                }
+            }
+        }
+    }
+
+    /// FIRST, FOLLOW and PREDICT sets followed by the conflicts, one set per line
+    pub fn report(&self) -> String {
+        let names = |set: &BTreeSet<TokenType>| set.iter().map(|terminal| terminal.grammar_name()).collect::<Vec<_>>().join(", ");
+        let mut out = String::new();
+        out.push_str("FIRST\n");
+        for name in self.non_terminals.iter() {
+            let empty = if self.is_nullable(name) { ", ε" } else { "" };
+            out.push_str(&format!("    {} = {{{}{}}}\n", name, names(&self.first[name]), empty));
+        }
+        out.push_str("\nFOLLOW\n");
+        for name in self.non_terminals.iter() {
+            out.push_str(&format!("    {} = {{{}}}\n", name, names(&self.follow[name])));
+        }
+        out.push_str("\nPREDICT\n");
+        for (i, production) in self.productions.iter().enumerate() {
+            out.push_str(&format!("    {} : {{{}}}\n", production, names(&self.predict(i))));
+        }
+        let conflicts = self.conflicts();
+        if !conflicts.is_empty() {
+            out.push_str("\nCONFLICTS\n");
+            for conflict in conflicts.iter() {
+                out.push_str(&format!(
+                    "    {} on {}:\n        {}\n        {}\n",
+                    conflict.non_terminal,
+                    conflict.terminal.grammar_name(),
+                    self.productions[conflict.productions.0],
+                    self.productions[conflict.productions.1],
+                ));
+            }
+        }
+        out
+    }
+}
+
+/// Upper-case names are terminals
+fn is_terminal(word: &str) -> bool {
+    word.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Words of a BNF file with their line numbers, `::=`, `|` and `;` are words of their own
+struct Words<'a> {
+    source: &'a str,
+    offset: usize,
+    line: usize,
+}
+
+impl<'a> Words<'a> {
+    fn new(source: &'a str) -> Self {
+        Words { source, offset: 0, line: 1 }
+    }
+}
+
+impl<'a> Iterator for Words<'a> {
+    type Item = (usize, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.source[self.offset..];
+        let trimmed = rest.trim_start();
+        self.line += rest[..rest.len() - trimmed.len()].matches('\n').count();
+        self.offset += rest.len() - trimmed.len();
+        if trimmed.is_empty() {
+            return None;
+        }
+        let len = if trimmed.starts_with("::=") {
+            3
+        } else if trimmed.starts_with('|') || trimmed.starts_with(';') {
+            1
+        } else {
+            trimmed.find(|c: char| c.is_whitespace() || c == '|' || c == ';').unwrap_or(trimmed.len())
+        };
+        self.offset += len;
+        Some((self.line, &trimmed[..len]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grammar::{Conflict, Grammar, GrammarError, Symbol};
+    use snl_utils::token::TokenType;
+
+    #[test]
+    fn test_sets() {
+        let grammar = Grammar::parse("Exp ::= Term Rest;\nRest ::= ADD Term Rest |;\nTerm ::= BR_OPEN Exp BR_CLOSE | INTC;").unwrap();
+        assert_eq!(grammar.start(), "Exp");
+        assert_eq!(grammar.productions[1].rhs, vec![Symbol::Terminal(TokenType::Add), Symbol::NonTerminal("Term"), Symbol::NonTerminal("Rest")]);
+        assert!(grammar.is_nullable("Rest"));
+        assert_eq!(grammar.first("Exp").iter().copied().collect::<Vec<_>>(), vec![TokenType::Int, TokenType::BracketOpen]);
+        assert_eq!(grammar.follow("Rest").iter().copied().collect::<Vec<_>>(), vec![TokenType::BracketClose, TokenType::EOF]);
+        assert_eq!(grammar.follow("Term").iter().copied().collect::<Vec<_>>(), vec![TokenType::Add, TokenType::BracketClose, TokenType::EOF]);
+        assert_eq!(grammar.predict(2).iter().copied().collect::<Vec<_>>(), vec![TokenType::BracketClose, TokenType::EOF]);
+        assert!(grammar.conflicts().is_empty());
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let grammar = Grammar::parse("Statement ::= Name | IDENTIFIER ASSIGN;\nName ::= IDENTIFIER |;").unwrap();
+        assert_eq!(grammar.conflicts(), vec![Conflict {
+            non_terminal: "Statement".to_owned(),
+            terminal: TokenType::Identifier,
+            productions: (0, 1),
+        }]);
+        assert!(grammar.report().ends_with("CONFLICTS\n    Statement on IDENTIFIER:\n        Statement ::= Name\n        Statement ::= IDENTIFIER ASSIGN\n"));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(Grammar::parse("Start ::= Colon;\nColon ::= COLON;").unwrap_err(), GrammarError::UnknownTerminal { line: 2, name: "COLON".to_owned() });
+        assert_eq!(Grammar::parse("Start ::= Colon;").unwrap_err(), GrammarError::UndefinedNonTerminal { name: "Colon".to_owned() });
+        assert_eq!(Grammar::parse("Start ::= INTC\nOther ::= INTC;").unwrap_err(), GrammarError::UnterminatedRule { name: "Start".to_owned() });
+        assert_eq!(Grammar::parse("Start ::= INTC;\nOther INTC;").unwrap_err(), GrammarError::ExpectedDefine { line: 2, name: "Other".to_owned() });
+        assert_eq!(Grammar::parse("Start ::= INTC;\n\nStart ::= ;").unwrap_err(), GrammarError::DuplicateRule { line: 3, name: "Start".to_owned() });
+    }
+
+    #[test]
+    fn test_snl_grammar() {
+        let grammar = Grammar::snl();
+        assert_eq!(grammar.start(), "Program");
+        assert_eq!(grammar.conflicts(), vec![]);
+    }
+}
@@ -1,5 +1,12 @@
 mod predict;
 mod construct;
 mod parser;
+pub mod grammar;
+mod trace;
+mod tree;
+#[cfg(test)]
+mod differential;
 
-pub use parser::Parser;
\ No newline at end of file
+pub use parser::Parser;
+pub use trace::{trace_table, Action, Step};
+pub use tree::ParseTree;
\ No newline at end of file
@@ -1,7 +1,11 @@
 use crate::predict::{PredictTable, PredictValue};
 use crate::construct::{ConstructTable, ASTNodeValue};
+use crate::grammar::Production;
+use crate::trace::{Action, Step};
+use crate::tree::{ParseTree, TreeBuilder};
 use snl_utils::ast::{Positional, Program};
-use snl_utils::token::Token;
+use snl_utils::error::SyntaxError;
+use snl_utils::token::{Token, TokenType};
 use snl_utils::tokens::Tokens;
 
 pub struct Parser {
@@ -12,15 +16,24 @@ pub struct Parser {
 
     tokens: Tokens,
 
-    stack: Vec<PredictValue>,
+    /// Symbols with their nodes in the parse tree
+    stack: Vec<(PredictValue, usize)>,
     stack_offset: Vec<usize>,
 
     params: Vec<ASTNodeValue>,
     param_offset: Vec<usize>,
+
+    errors: Vec<Positional<SyntaxError>>,
+
+    tree: TreeBuilder,
+    /// Steps taken, only recorded if tracing
+    trace: Option<Vec<Step>>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
+        let mut tree = TreeBuilder::default();
+        let root = tree.add(PredictValue::NonTerminal("Program"));
         Self {
             predict: Default::default(),
 
@@ -29,81 +42,260 @@ impl Parser {
 
             tokens: Tokens::new(tokens),
 
-            stack: vec![PredictValue::NonTerminal("Program")],
+            stack: vec![(PredictValue::NonTerminal("Program"), root)],
             stack_offset: Default::default(),
 
             params: Default::default(),
             param_offset: Default::default(),
+
+            errors: Default::default(),
+
+            tree,
+            trace: None,
         }
     }
 
-    pub fn parse(&mut self) -> Result<Positional<Program>, String> {
+    /// Record every step of the parser, see `trace`
+    pub fn with_trace(mut self, enabled: bool) -> Self {
+        self.trace = if enabled { Some(Vec::new()) } else { None };
+        self
+    }
+
+    /// Steps taken so far, empty unless tracing
+    pub fn trace(&self) -> &[Step] {
+        self.trace.as_deref().unwrap_or(&[])
+    }
+
+    /// Concrete parse tree of what is parsed so far, it is complete once parsing succeeds
+    pub fn parse_tree(&self) -> ParseTree {
+        self.tree.build(0)
+    }
+
+    /// Parse the program, failing with the first syntax error
+    pub fn parse(&mut self) -> Result<Positional<Program>, Positional<SyntaxError>> {
+        self.parse_with_errors().map_err(|mut errors| errors.remove(0))
+    }
+
+    /// Parse the program, recovering from syntax errors to report as many of them as possible.
+    /// The AST is no longer built once an error is found.
+    pub fn parse_with_errors(&mut self) -> Result<Positional<Program>, Vec<Positional<SyntaxError>>> {
         loop {
-            if self.stack.is_empty() && self.stack_offset.is_empty() {
-                break;
-            }
-            if let Some(offset) = self.stack_offset.last() {
-                if offset == &self.stack.len() {
-                    // remove stack offset
-                    self.stack_offset.pop();
-                    // remove and get offset
-                    let start_offset = self.param_offset.pop().unwrap();
-                    // params
-                    let mut params: Vec<_> = Default::default();
-                    for _ in 0..(self.params.len() - start_offset) {
-                        params.push(self.params.pop().unwrap());
+            if self.errors.is_empty() {
+                if let Some(offset) = self.stack_offset.last() {
+                    if offset == &self.stack.len() {
+                        // remove stack offset
+                        self.stack_offset.pop();
+                        // remove and get offset
+                        let start_offset = self.param_offset.pop().unwrap();
+                        // params
+                        let mut params: Vec<_> = Default::default();
+                        for _ in 0..(self.params.len() - start_offset) {
+                            params.push(self.params.pop().unwrap());
+                        }
+                        // handle rule
+                        match self.constructor.construct(self.construct.pop().unwrap(), params) {
+                            // add new parameter
+                            Ok(result) => self.params.push(result),
+                            Err(error) => self.errors.push(error),
+                        }
+                        // continue
+                        continue;
                     }
-                    // handle rule
-                    let result = self.constructor.construct(self.construct.pop().unwrap(), params)?;
-                    // add new parameter
-                    self.params.push(result);
-                    // continue
-                    continue;
                 }
             }
-            match self.tokens.now_token() {
-                Some(current) => {
-                    match self.stack.last().unwrap().clone() {
-                        PredictValue::Terminal(terminal) => {
-                            if terminal != current.token_type {
-                                return Err(format!("Expected {:?}, got {:?}", terminal, current.token_type));
+            let (top, node) = match self.stack.last() {
+                Some(top) => *top,
+                None => {
+                    // nothing may follow the final `.`
+                    if let Some(current) = self.tokens.now_token().filter(|t| t.token_type != TokenType::EOF).cloned() {
+                        self.error(&current, vec![TokenType::EOF]);
+                    }
+                    break;
+                }
+            };
+            let current = match self.tokens.now_token() {
+                Some(current) => current.clone(),
+                None => {
+                    let expected = match top {
+                        PredictValue::Terminal(terminal) => vec![terminal],
+                        PredictValue::NonTerminal(non_terminal) => self.predict.expected(non_terminal),
+                    };
+                    let error = SyntaxError::UnexpectedEof { expected };
+                    self.errors.push(match self.tokens.consumed_since(0).last() {
+                        Some(last) => Positional::from_token(last, error),
+                        None => Positional::dump(error),
+                    });
+                    break;
+                }
+            };
+            match top {
+                PredictValue::Terminal(terminal) => {
+                    if terminal == current.token_type {
+                        self.step(Action::Match);
+                        self.stack.pop();
+                        self.tokens.move_next();
+                        self.tree.matched(node, current.clone());
+                        if self.errors.is_empty() {
+                            self.params.push(ASTNodeValue::Terminal(current));
+                        }
+                        continue;
+                    }
+                    self.error(&current, vec![terminal]);
+                    // continue as if the terminal is there
+                    self.step(Action::Pop);
+                    self.stack.pop();
+                }
+                PredictValue::NonTerminal(non_terminal) => {
+                    match self.predict.lookup(non_terminal, current.token_type) {
+                        Some(rule) => {
+                            let rule = rule.clone();
+                            if self.trace.is_some() {
+                                self.step(Action::Expand(Production { lhs: non_terminal, rhs: rule.clone() }));
                             }
+                            // remove non terminal token from the top of stack
                             self.stack.pop();
-                            self.tokens.move_next();
-                            self.params.push(ASTNodeValue::Terminal(current.clone()))
-                        }
-                        PredictValue::NonTerminal(non_terminal) => {
-                            match self.predict.lookup(non_terminal, current.token_type) {
-                                Some(rule) => {
-                                    // remove non terminal token from the top of stack
-                                    self.stack.pop();
-                                    // simpler procedure for empty rule
-                                    if rule.is_empty() {
-                                        self.params.push(ASTNodeValue::None);
-                                    } else {
-                                        // add non terminal name
-                                        self.construct.push(non_terminal);
-                                        // add stack offset
-                                        self.stack_offset.push(self.stack.len());
-                                        // add param start offset
-                                        self.param_offset.push(self.params.len());
-                                        // add values to stack
-                                        for val in rule.iter().rev() {
-                                            self.stack.push(val.clone())
-                                        }
-                                    }
+                            if self.errors.is_empty() {
+                                // simpler procedure for empty rule
+                                if rule.is_empty() {
+                                    self.params.push(ASTNodeValue::None);
+                                } else {
+                                    // add non terminal name
+                                    self.construct.push(non_terminal);
+                                    // add stack offset
+                                    self.stack_offset.push(self.stack.len());
+                                    // add param start offset
+                                    self.param_offset.push(self.params.len());
                                 }
-                                None => return Err(format!("No predict rule found for rule: {}, token: {:?}", non_terminal, current.token_type))
                             }
+                            // add values to stack
+                            let children = self.tree.expand(node, &rule);
+                            for (val, child) in rule.iter().zip(children).rev() {
+                                self.stack.push((*val, child))
+                            }
+                            continue;
+                        }
+                        None => {
+                            self.error(&current, self.predict.expected(non_terminal));
+                            self.recover(non_terminal);
                         }
                     }
                 }
-                None => return Err("Unexpected EOF".to_owned())
             }
+            if current.token_type == TokenType::EOF {
+                break;
+            }
+        }
+        if !self.errors.is_empty() {
+            return Err(std::mem::take(&mut self.errors));
         }
         Ok(match self.params.pop().unwrap() {
             ASTNodeValue::Program(p) => p,
             _ => unreachable!()
         })
     }
-}
\ No newline at end of file
+
+    /// Record the step about to be taken if tracing
+    fn step(&mut self, action: Action) {
+        if let Some(trace) = self.trace.as_mut() {
+            trace.push(Step {
+                stack: self.stack.iter().map(|(symbol, _)| *symbol).collect(),
+                token: self.tokens.now().unwrap_or(TokenType::EOF),
+                action,
+            });
+        }
+    }
+
+    /// Report `found` where one of `expected` should be, once for each token
+    fn error(&mut self, found: &Token, expected: Vec<TokenType>) {
+        self.step(Action::Error);
+        let position = found.position();
+        if self.errors.last().map(|error| error.position()) == Some(position) {
+            return;
+        }
+        self.errors.push(Positional::from_token(found, SyntaxError::unexpected(&expected, found.token_type)));
+    }
+
+    /// Panic mode: skip tokens until one the non-terminal on top of the stack can start with,
+    /// or one which can follow it, in which case the non-terminal is dropped
+    fn recover(&mut self, non_terminal: &'static str) {
+        while let Some(token) = self.tokens.now() {
+            if token == TokenType::EOF || self.predict.lookup(non_terminal, token).is_some() {
+                return;
+            }
+            if self.predict.follow(non_terminal).contains(&token) {
+                self.step(Action::Pop);
+                self.stack.pop();
+                return;
+            }
+            self.step(Action::Skip);
+            self.tokens.move_next();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Parser;
+    use snl_utils::ast::Statement;
+    use snl_utils::error::SyntaxError;
+    use snl_utils::token::TokenType;
+
+    fn parse(source: &str) -> Result<(), ((u32, u32), SyntaxError)> {
+        let mut parser = Parser::new(snl_lexer::read_tokens(source).unwrap());
+        parser.parse().map(|_| ()).map_err(|e| (e.position(), e.into_inner()))
+    }
+
+    #[test]
+    fn test_positioned_errors() {
+        assert!(parse(include_str!("../../examples/program1.snl")).is_ok());
+        assert_eq!(parse("program p\nbegin\n  a := ;\nend."), Err(((3, 8), SyntaxError::UnexpectedToken {
+            expected: vec![TokenType::Identifier, TokenType::Int, TokenType::BracketOpen],
+            found: TokenType::Semicolon,
+        })));
+        assert_eq!(parse("program p\nbegin\n  a := 4294967296\nend."), Err(((3, 8), SyntaxError::IntegerOverflow("4294967296".to_owned()))));
+        assert_eq!(parse("program p\nbegin\n  read(a b)\nend."), Err(((3, 10), SyntaxError::UnexpectedToken {
+            expected: vec![TokenType::BracketClose],
+            found: TokenType::Identifier,
+        })));
+    }
+
+    #[test]
+    fn test_multiple_errors() {
+        let mut parser = Parser::new(snl_lexer::read_tokens(r#"program p
+var integer a, b;
+begin
+  read(a);
+  a := (a + 1;
+  if a < b then
+    b = 1
+  else
+    write(b)
+  fi;
+  while a < do
+    a := a - 1
+  endwh;
+  write(a
+end."#).unwrap());
+        let errors = parser.parse_with_errors().unwrap_err();
+        let positions: Vec<_> = errors.iter().map(|e| e.position()).collect();
+        assert_eq!(positions, vec![(5, 14), (7, 7), (11, 13), (15, 1)]);
+        assert_eq!(errors[1].inner(), &SyntaxError::UnexpectedToken {
+            expected: vec![TokenType::Assign, TokenType::BracketOpen, TokenType::SquareBracketOpen, TokenType::Dot],
+            found: TokenType::Equal,
+        });
+        // the end of file is reported once
+        let errors = Parser::new(snl_lexer::read_tokens("program p\nbegin\n  write(1").unwrap()).parse_with_errors().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0].inner(), SyntaxError::UnexpectedEof { .. }));
+    }
+
+    #[test]
+    fn test_left_associative() {
+        let mut parser = Parser::new(snl_lexer::read_tokens("program p\nbegin\n  write(10 - 4 - 3 + 8 / 4 / 2)\nend.").unwrap());
+        let program = parser.parse().unwrap();
+        match &program.body[0] {
+            Statement::Output(exp) => assert_eq!(exp.constant(), Some(4)),
+            s => panic!("unexpected statement {:?}", s),
+        }
+    }
+}
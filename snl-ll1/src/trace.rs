@@ -0,0 +1,89 @@
+use crate::grammar::Production;
+use crate::predict::PredictValue;
+use snl_utils::token::TokenType;
+use std::fmt::{Display, Formatter};
+
+/// What the parser did with the symbol on top of the stack and the current token
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Non-terminal replaced by the production predicted by the token
+    Expand(Production<'static>),
+    /// Terminal matched the token, both are removed
+    Match,
+    /// Token is not expected, recovery follows
+    Error,
+    /// Token skipped by error recovery
+    Skip,
+    /// Symbol removed by error recovery
+    Pop,
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::Expand(production) => write!(f, "{}", production),
+            Action::Match => f.write_str("match"),
+            Action::Error => f.write_str("error"),
+            Action::Skip => f.write_str("skip"),
+            Action::Pop => f.write_str("pop"),
+        }
+    }
+}
+
+/// State of the parser before an action
+#[derive(Debug, Clone)]
+pub struct Step {
+    /// Symbols from the bottom of the stack to the top
+    pub stack: Vec<PredictValue>,
+    pub token: TokenType,
+    pub action: Action,
+}
+
+/// Steps as a table with a numbered row for each step
+pub fn trace_table(steps: &[Step]) -> String {
+    let rows: Vec<_> = steps
+        .iter()
+        .map(|step| {
+            let stack: Vec<_> = step.stack.iter().map(|symbol| symbol.to_string()).collect();
+            (stack.join(" "), step.token.grammar_name(), step.action.to_string())
+        })
+        .collect();
+    let stack_width = rows.iter().map(|(stack, _, _)| stack.len()).max().unwrap_or(0).max("stack".len());
+    let token_width = rows.iter().map(|(_, token, _)| token.len()).max().unwrap_or(0).max("input".len());
+    let number_width = steps.len().to_string().len().max("step".len());
+
+    let mut out = format!("{:>n$}  {:<s$}  {:<t$}  action\n", "step", "stack", "input", n = number_width, s = stack_width, t = token_width);
+    for (i, (stack, token, action)) in rows.iter().enumerate() {
+        out.push_str(&format!("{:>n$}  {:<s$}  {:<t$}  {}\n", i + 1, stack, token, action, n = number_width, s = stack_width, t = token_width));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{trace_table, Action, Parser};
+
+    #[test]
+    fn test_trace() {
+        let tokens = snl_lexer::read_tokens("program p\nbegin\n  write(1)\nend.").unwrap();
+        let count = tokens.len();
+        let mut parser = Parser::new(tokens).with_trace(true);
+        parser.parse().unwrap();
+        let steps = parser.trace();
+        // every token but the end of file is matched
+        assert_eq!(steps.iter().filter(|step| step.action == Action::Match).count(), count - 1);
+        let table = trace_table(steps);
+        let lines: Vec<_> = table.lines().take(4).collect();
+        assert_eq!(lines, vec![
+            "step  stack                                                         input       action",
+            "   1  Program                                                       PROGRAM     Program ::= ProgramHead DeclarePart ProgramBody DOT",
+            "   2  DOT ProgramBody DeclarePart ProgramHead                       PROGRAM     ProgramHead ::= PROGRAM ProgramName",
+            "   3  DOT ProgramBody DeclarePart ProgramName PROGRAM               PROGRAM     match",
+        ]);
+        assert!(table.contains("  BEGIN       TypeDec ::= ε\n"));
+
+        let mut parser = Parser::new(snl_lexer::read_tokens("program p\nbegin\n  write(1)\nend.").unwrap());
+        parser.parse().unwrap();
+        assert!(parser.trace().is_empty());
+    }
+}
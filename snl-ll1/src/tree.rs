@@ -0,0 +1,126 @@
+use crate::predict::PredictValue;
+use serde::Serialize;
+use snl_utils::token::Token;
+use std::fmt::Write;
+
+/// Concrete parse tree, with a node for every symbol the parser expands or matches
+#[derive(Debug, Serialize)]
+pub struct ParseTree {
+    /// Name of the non-terminal, or of the terminal in the grammar
+    pub symbol: String,
+    /// Text of the token a terminal matched, `None` for non-terminals and for
+    /// terminals which are missing from the source
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<(u32, u32)>,
+    /// Empty for terminals and for non-terminals expanded to nothing
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ParseTree>,
+}
+
+impl ParseTree {
+    /// Graphviz DOT of the tree, terminals are drawn as boxes below their image
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "digraph cst {{").unwrap();
+        writeln!(out, "    node [fontname=\"monospace\"];").unwrap();
+        self.write_dot(&mut out, &mut 0);
+        writeln!(out, "}}").unwrap();
+        out
+    }
+
+    /// Write the node and its children, returns the name of the node
+    fn write_dot(&self, out: &mut String, count: &mut usize) -> String {
+        let name = format!("n{}", count);
+        *count += 1;
+        match &self.image {
+            Some(image) => writeln!(out, "    {} [label=\"{}\\n{}\", shape=box];", name, self.symbol, escape(image)).unwrap(),
+            None => writeln!(out, "    {} [label=\"{}\"];", name, self.symbol).unwrap(),
+        }
+        for child in self.children.iter() {
+            let child = child.write_dot(out, count);
+            writeln!(out, "    {} -> {};", name, child).unwrap();
+        }
+        name
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Nodes of the tree while it is built, children are added when a non-terminal is expanded
+#[derive(Default)]
+pub(crate) struct TreeBuilder {
+    nodes: Vec<Node>,
+}
+
+struct Node {
+    symbol: PredictValue,
+    token: Option<Token>,
+    children: Vec<usize>,
+}
+
+impl TreeBuilder {
+    pub fn add(&mut self, symbol: PredictValue) -> usize {
+        self.nodes.push(Node { symbol, token: None, children: Vec::new() });
+        self.nodes.len() - 1
+    }
+
+    /// Add nodes for the symbols a non-terminal is expanded to, returns their indexes
+    pub fn expand(&mut self, node: usize, rule: &[PredictValue]) -> Vec<usize> {
+        let children: Vec<_> = rule.iter().map(|symbol| self.add(*symbol)).collect();
+        self.nodes[node].children = children.clone();
+        children
+    }
+
+    pub fn matched(&mut self, node: usize, token: Token) {
+        self.nodes[node].token = Some(token);
+    }
+
+    pub fn build(&self, node: usize) -> ParseTree {
+        let node = &self.nodes[node];
+        ParseTree {
+            symbol: node.symbol.to_string(),
+            image: node.token.as_ref().map(|token| token.image.clone()),
+            position: node.token.as_ref().map(|token| token.position()),
+            children: node.children.iter().map(|child| self.build(*child)).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ParseTree, Parser};
+
+    fn leaves<'a>(tree: &'a ParseTree, images: &mut Vec<&'a str>) {
+        if let Some(image) = &tree.image {
+            images.push(image);
+        }
+        for child in tree.children.iter() {
+            leaves(child, images);
+        }
+    }
+
+    #[test]
+    fn test_parse_tree() {
+        let source = "program p\nvar integer a;\nbegin\n  a := a + 1\nend.";
+        let tokens = snl_lexer::read_tokens(source).unwrap();
+        let images: Vec<_> = tokens.iter().map(|token| token.image.clone()).collect();
+        let mut parser = Parser::new(tokens);
+        parser.parse().unwrap();
+        let tree = parser.parse_tree();
+        assert_eq!(tree.symbol, "Program");
+        let children: Vec<_> = tree.children.iter().map(|child| child.symbol.as_str()).collect();
+        assert_eq!(children, vec!["ProgramHead", "DeclarePart", "ProgramBody", "DOT"]);
+        // the leaves are the tokens in order
+        let mut found = Vec::new();
+        leaves(&tree, &mut found);
+        assert_eq!(found, images[..images.len() - 1].iter().map(String::as_str).collect::<Vec<_>>());
+
+        let dot = tree.to_dot();
+        assert!(dot.starts_with("digraph cst {\n    node [fontname=\"monospace\"];\n    n0 [label=\"Program\"];\n"));
+        assert!(dot.contains(" [label=\"INTC\\n1\", shape=box];\n"));
+    }
+}
@@ -0,0 +1,291 @@
+; program nested
+
+%frame.outer.inner = type { ptr, i64 }
+%frame.outer.swap = type { ptr, ptr, ptr, i64 }
+%frame.outer = type { ptr, i64, i64 }
+%frame.show = type { ptr, { i64, [3 x i8] } }
+%frame.program = type { ptr, { i64, [3 x i8] }, { i64, [3 x i8] }, [3 x i64], i64, i8 }
+
+define internal void @snl.outer.inner(ptr %up, i64 %a.1) {
+entry:
+  %frame = alloca %frame.outer.inner
+  store %frame.outer.inner zeroinitializer, ptr %frame
+  %p.0 = getelementptr %frame.outer.inner, ptr %frame, i32 0, i32 0
+  store ptr %up, ptr %p.0
+  %p.1 = getelementptr %frame.outer.inner, ptr %frame, i32 0, i32 1
+  store i64 %a.1, ptr %p.1
+  %t0 = getelementptr %frame.outer.inner, ptr %frame, i32 0, i32 0
+  %t1 = load ptr, ptr %t0
+  %t2 = getelementptr %frame.outer, ptr %t1, i32 0, i32 0
+  %t3 = load ptr, ptr %t2
+  %t4 = getelementptr %frame.program, ptr %t3, i32 0, i32 3
+  %t5 = getelementptr %frame.outer.inner, ptr %frame, i32 0, i32 1
+  %t6 = load i64, ptr %t5
+  %t7 = sub i64 %t6, 2
+  %t8 = getelementptr [3 x i64], ptr %t4, i64 0, i64 %t7
+  %t9 = getelementptr %frame.outer.inner, ptr %frame, i32 0, i32 0
+  %t10 = load ptr, ptr %t9
+  %t11 = getelementptr %frame.outer, ptr %t10, i32 0, i32 1
+  %t12 = load i64, ptr %t11
+  %t13 = getelementptr %frame.outer.inner, ptr %frame, i32 0, i32 0
+  %t14 = load ptr, ptr %t13
+  %t15 = getelementptr %frame.outer, ptr %t14, i32 0, i32 2
+  %t16 = load i64, ptr %t15
+  %t17 = add i64 %t12, %t16
+  %t18 = getelementptr %frame.outer.inner, ptr %frame, i32 0, i32 1
+  %t19 = load i64, ptr %t18
+  %t20 = add i64 %t17, %t19
+  store i64 %t20, ptr %t8
+  ret void
+}
+
+define internal void @snl.outer.swap(ptr %up, ptr %a.1, ptr %a.2) {
+entry:
+  %frame = alloca %frame.outer.swap
+  store %frame.outer.swap zeroinitializer, ptr %frame
+  %p.0 = getelementptr %frame.outer.swap, ptr %frame, i32 0, i32 0
+  store ptr %up, ptr %p.0
+  %p.1 = getelementptr %frame.outer.swap, ptr %frame, i32 0, i32 1
+  store ptr %a.1, ptr %p.1
+  %p.2 = getelementptr %frame.outer.swap, ptr %frame, i32 0, i32 2
+  store ptr %a.2, ptr %p.2
+  %t0 = getelementptr %frame.outer.swap, ptr %frame, i32 0, i32 3
+  %t1 = getelementptr %frame.outer.swap, ptr %frame, i32 0, i32 1
+  %t2 = load ptr, ptr %t1
+  %t3 = load i64, ptr %t2
+  store i64 %t3, ptr %t0
+  %t4 = getelementptr %frame.outer.swap, ptr %frame, i32 0, i32 1
+  %t5 = load ptr, ptr %t4
+  %t6 = getelementptr %frame.outer.swap, ptr %frame, i32 0, i32 2
+  %t7 = load ptr, ptr %t6
+  %t8 = load i64, ptr %t7
+  store i64 %t8, ptr %t5
+  %t9 = getelementptr %frame.outer.swap, ptr %frame, i32 0, i32 2
+  %t10 = load ptr, ptr %t9
+  %t11 = getelementptr %frame.outer.swap, ptr %frame, i32 0, i32 3
+  %t12 = load i64, ptr %t11
+  store i64 %t12, ptr %t10
+  ret void
+}
+
+define internal void @snl.outer(ptr %up, i64 %a.1) {
+entry:
+  %frame = alloca %frame.outer
+  store %frame.outer zeroinitializer, ptr %frame
+  %p.0 = getelementptr %frame.outer, ptr %frame, i32 0, i32 0
+  store ptr %up, ptr %p.0
+  %p.1 = getelementptr %frame.outer, ptr %frame, i32 0, i32 1
+  store i64 %a.1, ptr %p.1
+  %t0 = getelementptr %frame.outer, ptr %frame, i32 0, i32 2
+  store i64 100, ptr %t0
+  call void @snl.outer.inner(ptr %frame, i64 2)
+  call void @snl.outer.inner(ptr %frame, i64 4)
+  %t1 = getelementptr %frame.outer, ptr %frame, i32 0, i32 0
+  %t2 = load ptr, ptr %t1
+  %t3 = getelementptr %frame.program, ptr %t2, i32 0, i32 3
+  %t4 = sub i64 2, 2
+  %t5 = getelementptr [3 x i64], ptr %t3, i64 0, i64 %t4
+  %t6 = getelementptr %frame.outer, ptr %frame, i32 0, i32 0
+  %t7 = load ptr, ptr %t6
+  %t8 = getelementptr %frame.program, ptr %t7, i32 0, i32 3
+  %t9 = sub i64 4, 2
+  %t10 = getelementptr [3 x i64], ptr %t8, i64 0, i64 %t9
+  call void @snl.outer.swap(ptr %frame, ptr %t5, ptr %t10)
+  ret void
+}
+
+define internal void @snl.show(ptr %up, { i64, [3 x i8] } %a.1) {
+entry:
+  %frame = alloca %frame.show
+  store %frame.show zeroinitializer, ptr %frame
+  %p.0 = getelementptr %frame.show, ptr %frame, i32 0, i32 0
+  store ptr %up, ptr %p.0
+  %p.1 = getelementptr %frame.show, ptr %frame, i32 0, i32 1
+  store { i64, [3 x i8] } %a.1, ptr %p.1
+  %t0 = getelementptr %frame.show, ptr %frame, i32 0, i32 1
+  %t1 = getelementptr { i64, [3 x i8] }, ptr %t0, i32 0, i32 0
+  %t2 = load i64, ptr %t1
+  call void @snl.write_integer(i64 %t2)
+  %t3 = getelementptr %frame.show, ptr %frame, i32 0, i32 1
+  %t4 = getelementptr { i64, [3 x i8] }, ptr %t3, i32 0, i32 1
+  %t5 = sub i64 2, 1
+  %t6 = getelementptr [3 x i8], ptr %t4, i64 0, i64 %t5
+  %t8 = load i8, ptr %t6
+  %t7 = zext i8 %t8 to i64
+  call void @snl.write_char(i64 %t7)
+  %t9 = getelementptr %frame.show, ptr %frame, i32 0, i32 1
+  %t10 = getelementptr { i64, [3 x i8] }, ptr %t9, i32 0, i32 0
+  %t11 = load i64, ptr %t10
+  %t12 = call i64 @snl.divide(i64 %t11, i64 5, i32 33, i32 23)
+  %t13 = sub i64 0, %t12
+  call void @snl.write_integer(i64 %t13)
+  %t14 = getelementptr %frame.show, ptr %frame, i32 0, i32 1
+  %t15 = getelementptr { i64, [3 x i8] }, ptr %t14, i32 0, i32 0
+  store i64 0, ptr %t15
+  ret void
+}
+
+define internal void @snl.program() {
+entry:
+  %frame = alloca %frame.program
+  store %frame.program zeroinitializer, ptr %frame
+  %t0 = getelementptr %frame.program, ptr %frame, i32 0, i32 4
+  store i64 7, ptr %t0
+  call void @snl.outer(ptr %frame, i64 10)
+  %t1 = getelementptr %frame.program, ptr %frame, i32 0, i32 5
+  %t2 = call i64 @snl.read_char(i32 39, i32 8)
+  %t3 = trunc i64 %t2 to i8
+  store i8 %t3, ptr %t1
+  %t4 = getelementptr %frame.program, ptr %frame, i32 0, i32 1
+  %t5 = getelementptr { i64, [3 x i8] }, ptr %t4, i32 0, i32 1
+  %t6 = sub i64 2, 1
+  %t7 = getelementptr [3 x i8], ptr %t5, i64 0, i64 %t6
+  %t8 = getelementptr %frame.program, ptr %frame, i32 0, i32 5
+  %t10 = load i8, ptr %t8
+  %t9 = zext i8 %t10 to i64
+  %t11 = trunc i64 %t9 to i8
+  store i8 %t11, ptr %t7
+  %t12 = getelementptr %frame.program, ptr %frame, i32 0, i32 1
+  %t13 = getelementptr { i64, [3 x i8] }, ptr %t12, i32 0, i32 0
+  %t14 = getelementptr %frame.program, ptr %frame, i32 0, i32 3
+  %t15 = sub i64 4, 2
+  %t16 = getelementptr [3 x i64], ptr %t14, i64 0, i64 %t15
+  %t17 = load i64, ptr %t16
+  store i64 %t17, ptr %t13
+  %t18 = getelementptr %frame.program, ptr %frame, i32 0, i32 2
+  %t19 = getelementptr %frame.program, ptr %frame, i32 0, i32 1
+  %t20 = load { i64, [3 x i8] }, ptr %t19
+  store { i64, [3 x i8] } %t20, ptr %t18
+  %t21 = getelementptr %frame.program, ptr %frame, i32 0, i32 2
+  %t22 = load { i64, [3 x i8] }, ptr %t21
+  call void @snl.show(ptr %frame, { i64, [3 x i8] } %t22)
+  %t23 = getelementptr %frame.program, ptr %frame, i32 0, i32 2
+  %t24 = getelementptr { i64, [3 x i8] }, ptr %t23, i32 0, i32 0
+  %t25 = load i64, ptr %t24
+  call void @snl.write_integer(i64 %t25)
+  %t26 = getelementptr %frame.program, ptr %frame, i32 0, i32 3
+  %t27 = sub i64 4, 2
+  %t28 = getelementptr [3 x i64], ptr %t26, i64 0, i64 %t27
+  %t29 = load i64, ptr %t28
+  call void @snl.write_integer(i64 %t29)
+  br label %L0
+L0:
+  %t30 = getelementptr %frame.program, ptr %frame, i32 0, i32 4
+  %t31 = load i64, ptr %t30
+  %t32 = icmp slt i64 %t31, 9
+  br i1 %t32, label %L1, label %L2
+L1:
+  %t33 = getelementptr %frame.program, ptr %frame, i32 0, i32 4
+  %t34 = getelementptr %frame.program, ptr %frame, i32 0, i32 4
+  %t35 = load i64, ptr %t34
+  %t36 = add i64 %t35, 1
+  store i64 %t36, ptr %t33
+  br label %L0
+L2:
+  %t37 = getelementptr %frame.program, ptr %frame, i32 0, i32 4
+  %t38 = load i64, ptr %t37
+  %t39 = icmp eq i64 %t38, 9
+  br i1 %t39, label %L3, label %L4
+L3:
+  %t40 = getelementptr %frame.program, ptr %frame, i32 0, i32 4
+  %t41 = load i64, ptr %t40
+  call void @snl.write_integer(i64 %t41)
+  br label %L5
+L4:
+  ret void
+L5:
+  %t42 = getelementptr %frame.program, ptr %frame, i32 0, i32 4
+  %t43 = load i64, ptr %t42
+  %t44 = getelementptr %frame.program, ptr %frame, i32 0, i32 4
+  %t45 = load i64, ptr %t44
+  %t46 = sub i64 %t45, 9
+  %t47 = call i64 @snl.divide(i64 %t43, i64 %t46, i32 54, i32 18)
+  call void @snl.write_integer(i64 %t47)
+  ret void
+}
+
+define i32 @main() {
+  call void @snl.program()
+  ret i32 0
+}
+
+@.format.integer.in = private unnamed_addr constant [6 x i8] c" %lld\00"
+@.format.char.in = private unnamed_addr constant [4 x i8] c" %c\00"
+@.format.integer.out = private unnamed_addr constant [6 x i8] c"%lld\0A\00"
+@.format.char.out = private unnamed_addr constant [4 x i8] c"%c\0A\00"
+@.format.error = private unnamed_addr constant [41 x i8] c"Runtime error at line %d, column %d:\09%s\0A\00"
+@.message.divide = private unnamed_addr constant [17 x i8] c"Division by zero\00"
+@.message.end = private unnamed_addr constant [24 x i8] c"Unexpected end of input\00"
+@.message.invalid = private unnamed_addr constant [33 x i8] c"Invalid input for type 'integer'\00"
+
+declare i32 @printf(ptr, ...)
+declare i32 @scanf(ptr, ...)
+declare i32 @dprintf(i32, ptr, ...)
+declare i32 @fflush(ptr)
+declare void @exit(i32)
+
+define internal void @snl.error(i32 %line, i32 %column, ptr %message) noreturn {
+  call i32 @fflush(ptr null)
+  call i32 (i32, ptr, ...) @dprintf(i32 2, ptr @.format.error, i32 %line, i32 %column, ptr %message)
+  call void @exit(i32 1)
+  unreachable
+}
+
+define internal i64 @snl.read_integer(i32 %line, i32 %column) {
+  %value = alloca i64
+  %count = call i32 (ptr, ...) @scanf(ptr @.format.integer.in, ptr %value)
+  %ok = icmp eq i32 %count, 1
+  br i1 %ok, label %done, label %fail
+done:
+  %result = load i64, ptr %value
+  ret i64 %result
+fail:
+  %end = icmp eq i32 %count, -1
+  %message = select i1 %end, ptr @.message.end, ptr @.message.invalid
+  call void @snl.error(i32 %line, i32 %column, ptr %message)
+  unreachable
+}
+
+define internal i64 @snl.read_char(i32 %line, i32 %column) {
+  %value = alloca i8
+  %count = call i32 (ptr, ...) @scanf(ptr @.format.char.in, ptr %value)
+  %ok = icmp eq i32 %count, 1
+  br i1 %ok, label %done, label %fail
+done:
+  %char = load i8, ptr %value
+  %result = zext i8 %char to i64
+  ret i64 %result
+fail:
+  call void @snl.error(i32 %line, i32 %column, ptr @.message.end)
+  unreachable
+}
+
+define internal void @snl.write_integer(i64 %value) {
+  call i32 (ptr, ...) @printf(ptr @.format.integer.out, i64 %value)
+  ret void
+}
+
+define internal void @snl.write_char(i64 %value) {
+  %char = trunc i64 %value to i8
+  %promoted = zext i8 %char to i32
+  call i32 (ptr, ...) @printf(ptr @.format.char.out, i32 %promoted)
+  ret void
+}
+
+; wraps on overflow like the interpreter
+define internal i64 @snl.divide(i64 %a, i64 %b, i32 %line, i32 %column) {
+  %zero = icmp eq i64 %b, 0
+  br i1 %zero, label %fail, label %check
+check:
+  %negate = icmp eq i64 %b, -1
+  br i1 %negate, label %negative, label %divide
+negative:
+  %negated = sub i64 0, %a
+  ret i64 %negated
+divide:
+  %result = sdiv i64 %a, %b
+  ret i64 %result
+fail:
+  call void @snl.error(i32 %line, i32 %column, ptr @.message.divide)
+  unreachable
+}
@@ -0,0 +1,107 @@
+; program p
+
+%frame.program = type { ptr, i64, i8 }
+
+define internal void @snl.program() {
+entry:
+  %frame = alloca %frame.program
+  store %frame.program zeroinitializer, ptr %frame
+  %t0 = getelementptr %frame.program, ptr %frame, i32 0, i32 1
+  %t1 = call i64 @snl.read_integer(i32 6, i32 10)
+  store i64 %t1, ptr %t0
+  %t2 = getelementptr %frame.program, ptr %frame, i32 0, i32 1
+  %t3 = getelementptr %frame.program, ptr %frame, i32 0, i32 1
+  %t4 = load i64, ptr %t3
+  %t5 = add i64 %t4, 10
+  store i64 %t5, ptr %t2
+  %t6 = getelementptr %frame.program, ptr %frame, i32 0, i32 1
+  %t7 = load i64, ptr %t6
+  call void @snl.write_integer(i64 %t7)
+  ret void
+}
+
+define i32 @main() {
+  call void @snl.program()
+  ret i32 0
+}
+
+@.format.integer.in = private unnamed_addr constant [6 x i8] c" %lld\00"
+@.format.char.in = private unnamed_addr constant [4 x i8] c" %c\00"
+@.format.integer.out = private unnamed_addr constant [6 x i8] c"%lld\0A\00"
+@.format.char.out = private unnamed_addr constant [4 x i8] c"%c\0A\00"
+@.format.error = private unnamed_addr constant [41 x i8] c"Runtime error at line %d, column %d:\09%s\0A\00"
+@.message.divide = private unnamed_addr constant [17 x i8] c"Division by zero\00"
+@.message.end = private unnamed_addr constant [24 x i8] c"Unexpected end of input\00"
+@.message.invalid = private unnamed_addr constant [33 x i8] c"Invalid input for type 'integer'\00"
+
+declare i32 @printf(ptr, ...)
+declare i32 @scanf(ptr, ...)
+declare i32 @dprintf(i32, ptr, ...)
+declare i32 @fflush(ptr)
+declare void @exit(i32)
+
+define internal void @snl.error(i32 %line, i32 %column, ptr %message) noreturn {
+  call i32 @fflush(ptr null)
+  call i32 (i32, ptr, ...) @dprintf(i32 2, ptr @.format.error, i32 %line, i32 %column, ptr %message)
+  call void @exit(i32 1)
+  unreachable
+}
+
+define internal i64 @snl.read_integer(i32 %line, i32 %column) {
+  %value = alloca i64
+  %count = call i32 (ptr, ...) @scanf(ptr @.format.integer.in, ptr %value)
+  %ok = icmp eq i32 %count, 1
+  br i1 %ok, label %done, label %fail
+done:
+  %result = load i64, ptr %value
+  ret i64 %result
+fail:
+  %end = icmp eq i32 %count, -1
+  %message = select i1 %end, ptr @.message.end, ptr @.message.invalid
+  call void @snl.error(i32 %line, i32 %column, ptr %message)
+  unreachable
+}
+
+define internal i64 @snl.read_char(i32 %line, i32 %column) {
+  %value = alloca i8
+  %count = call i32 (ptr, ...) @scanf(ptr @.format.char.in, ptr %value)
+  %ok = icmp eq i32 %count, 1
+  br i1 %ok, label %done, label %fail
+done:
+  %char = load i8, ptr %value
+  %result = zext i8 %char to i64
+  ret i64 %result
+fail:
+  call void @snl.error(i32 %line, i32 %column, ptr @.message.end)
+  unreachable
+}
+
+define internal void @snl.write_integer(i64 %value) {
+  call i32 (ptr, ...) @printf(ptr @.format.integer.out, i64 %value)
+  ret void
+}
+
+define internal void @snl.write_char(i64 %value) {
+  %char = trunc i64 %value to i8
+  %promoted = zext i8 %char to i32
+  call i32 (ptr, ...) @printf(ptr @.format.char.out, i32 %promoted)
+  ret void
+}
+
+; wraps on overflow like the interpreter
+define internal i64 @snl.divide(i64 %a, i64 %b, i32 %line, i32 %column) {
+  %zero = icmp eq i64 %b, 0
+  br i1 %zero, label %fail, label %check
+check:
+  %negate = icmp eq i64 %b, -1
+  br i1 %negate, label %negative, label %divide
+negative:
+  %negated = sub i64 0, %a
+  ret i64 %negated
+divide:
+  %result = sdiv i64 %a, %b
+  ret i64 %result
+fail:
+  call void @snl.error(i32 %line, i32 %column, ptr @.message.divide)
+  unreachable
+}
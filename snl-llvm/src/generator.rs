@@ -0,0 +1,556 @@
+use crate::runtime::{BOUNDS_CHECK, RUNTIME};
+use snl_ir::scope::{self, Binding, Result, Scope};
+use snl_ir::{ScalarType, Type};
+use snl_utils::ast::*;
+use snl_utils::options::CodegenOptions;
+
+/// Field `index` of the frame of a procedure at `level`
+struct Variable {
+    level: usize,
+    index: usize,
+    is_var: bool,
+    ty: Type,
+}
+
+/// Procedure whose own frame is at `level`
+struct Procedure {
+    level: usize,
+    name: String,
+    params: Vec<(bool, Type)>,
+}
+
+struct Generator {
+    scope: Scope<Variable, Procedure>,
+    types: String,
+    functions: String,
+    /// Body of the function being generated
+    body: String,
+    temps: usize,
+    labels: usize,
+    /// Whether the current basic block already ends with a terminator
+    terminated: bool,
+    /// Frame type of every enclosing block, indexed by level
+    frames: Vec<String>,
+    /// Names of the enclosing procedures
+    path: Vec<String>,
+    options: CodegenOptions,
+}
+
+/// Generate a textual LLVM IR module from a semantically checked program.
+///
+/// Nested procedures are closure converted: every block allocates its variables as one
+/// `%frame.<path>` struct, whose first field points to the frame of the lexically enclosing
+/// block. Procedures become top level functions receiving that pointer as their first
+/// argument, `var` params are passed as pointers. Values are computed as `i64`, chars are
+/// stored as `i8`. The output uses opaque pointers and links against libc.
+pub fn generate(program: &Positional<Program>, options: CodegenOptions) -> Result<String> {
+    let mut generator = Generator {
+        scope: Default::default(),
+        types: Default::default(),
+        functions: Default::default(),
+        body: Default::default(),
+        temps: 0,
+        labels: 0,
+        terminated: false,
+        frames: Default::default(),
+        path: Default::default(),
+        options,
+    };
+    generator.generate_block(
+        "program",
+        "define internal void @snl.program()".to_owned(),
+        vec![],
+        String::new(),
+        &program.declare,
+        &program.body,
+    )?;
+
+    let mut module = format!(
+        "; program {}\n\n{}\n{}define i32 @main() {{\n  call void @snl.program()\n  ret i32 0\n}}\n\n{}",
+        program.name, generator.types, generator.functions, RUNTIME,
+    );
+    if options.bounds_check {
+        module.push_str(BOUNDS_CHECK);
+    }
+    Ok(module)
+}
+
+fn scalar_type(ty: ScalarType) -> &'static str {
+    match ty {
+        ScalarType::Integer => "i64",
+        ScalarType::Char => "i8",
+    }
+}
+
+fn llvm_type(ty: &Type) -> String {
+    match ty {
+        Type::Scalar(ty) => scalar_type(*ty).to_owned(),
+        Type::Array { base, .. } => format!("[{} x {}]", ty.size(), scalar_type(*base)),
+        Type::Record(fields) => {
+            let fields: Vec<_> = fields.iter().map(|(_, ty)| llvm_type(ty)).collect();
+            format!("{{ {} }}", fields.join(", "))
+        }
+    }
+}
+
+/// Global name, quoted unless it only has characters allowed in a bare identifier
+fn global(name: &str) -> String {
+    if name.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_') {
+        format!("@{}", name)
+    } else {
+        format!("@\"{}\"", name)
+    }
+}
+
+impl Generator {
+    fn line<S: AsRef<str>>(&mut self, line: S) {
+        self.body.push_str("  ");
+        self.body.push_str(line.as_ref());
+        self.body.push('\n');
+    }
+
+    fn temp(&mut self) -> String {
+        self.temps += 1;
+        format!("%t{}", self.temps - 1)
+    }
+
+    fn new_label(&mut self) -> String {
+        self.labels += 1;
+        format!("L{}", self.labels - 1)
+    }
+
+    /// Start a basic block, falling through from the previous one
+    fn label(&mut self, label: &str) {
+        if !self.terminated {
+            self.line(format!("br label %{}", label));
+        }
+        self.body.push_str(&format!("{}:\n", label));
+        self.terminated = false;
+    }
+
+    fn terminate<S: AsRef<str>>(&mut self, line: S) {
+        self.line(line);
+        self.terminated = true;
+    }
+
+    /// Generate the frame type and function of a block.
+    ///
+    /// `fields` holds the types of the static link and params, locals are appended by declarations.
+    /// `setup` is placed right after the frame is allocated.
+    fn generate_block(&mut self, name: &str, signature: String, mut fields: Vec<String>, setup: String,
+                      declare: &ProgramDeclare, body: &StatementList) -> Result<()> {
+        let level = self.frames.len();
+        let frame = format!("%frame.{}", name);
+        self.frames.push(frame.clone());
+        if fields.is_empty() {
+            // the main program has no enclosing frame, its static link stays null
+            fields.push("ptr".to_owned());
+        }
+
+        for t in declare.type_declare.iter() {
+            let ty = self.scope.resolve_type(&t.base)?;
+            self.scope.insert(t.name().to_owned(), Binding::Type(ty));
+        }
+
+        for v in declare.variable_declare.iter() {
+            let ty = self.scope.resolve_type(&v.type_name)?;
+            for id in v.identifiers.iter() {
+                self.scope.insert(id.inner().clone(), Binding::Variable(Variable { level, index: fields.len(), is_var: false, ty: ty.clone() }));
+                fields.push(llvm_type(&ty));
+            }
+        }
+
+        for p in declare.procedure_declare.iter() {
+            self.generate_procedure(p)?;
+        }
+
+        self.types.push_str(&format!("{} = type {{ {} }}\n", frame, fields.join(", ")));
+
+        // locals and the fields of structured values all start at zero
+        self.line(format!("%frame = alloca {}", frame));
+        self.line(format!("store {} zeroinitializer, ptr %frame", frame));
+        self.body.push_str(&setup);
+        self.generate_statement_list(body)?;
+        if !self.terminated {
+            self.line("ret void");
+        }
+
+        let body = std::mem::take(&mut self.body);
+        self.functions.push_str(&format!("{} {{\nentry:\n{}}}\n\n", signature, body));
+        self.frames.pop();
+        Ok(())
+    }
+
+    fn generate_procedure(&mut self, p: &Positional<ProcedureDeclare>) -> Result<()> {
+        self.path.push(p.name().to_owned());
+        let name = self.path.join(".");
+        let level = self.frames.len();
+
+        let mut arguments = vec!["ptr %up".to_owned()];
+        let mut fields = vec!["ptr".to_owned()];
+        let mut params = Vec::new();
+        let mut variables = Vec::new();
+        for param in p.params.iter() {
+            let ty = self.scope.resolve_type(&param.definition.type_name)?;
+            let param_type = if param.is_var { "ptr".to_owned() } else { llvm_type(&ty) };
+            for id in param.definition.identifiers.iter() {
+                arguments.push(format!("{} %a.{}", param_type, fields.len()));
+                variables.push((id.inner().clone(), fields.len(), param.is_var, ty.clone()));
+                fields.push(param_type.clone());
+                params.push((param.is_var, ty.clone()));
+            }
+        }
+        self.scope.insert(p.name().to_owned(), Binding::Procedure(Procedure { level, name: name.clone(), params }));
+
+        self.scope.step_in();
+        let saved = (std::mem::take(&mut self.body), self.temps, self.labels, self.terminated);
+        self.temps = 0;
+        self.labels = 0;
+        self.terminated = false;
+
+        // the frame is allocated by generate_block, the static link and params are stored right after it
+        let frame = format!("%frame.{}", name);
+        let mut stores = String::new();
+        for (i, field) in fields.iter().enumerate() {
+            let value = if i == 0 { "%up".to_owned() } else { format!("%a.{}", i) };
+            stores.push_str(&format!("  %p.{0} = getelementptr {1}, ptr %frame, i32 0, i32 {0}\n", i, frame));
+            stores.push_str(&format!("  store {} {}, ptr %p.{}\n", field, value, i));
+        }
+        for (id, index, is_var, ty) in variables {
+            self.scope.insert(id, Binding::Variable(Variable { level, index, is_var, ty }));
+        }
+
+        let signature = format!("define internal void {}({})", global(&format!("snl.{}", name)), arguments.join(", "));
+        self.generate_block(&name, signature, fields, stores, &p.declare, &p.body)?;
+
+        let (body, temps, labels, terminated) = saved;
+        self.body = body;
+        self.temps = temps;
+        self.labels = labels;
+        self.terminated = terminated;
+        self.path.pop();
+        self.scope.step_out();
+        Ok(())
+    }
+
+    fn generate_statement_list(&mut self, list: &StatementList) -> Result<()> {
+        for statement in list.iter() {
+            if self.terminated {
+                // code after a return is unreachable, but still needs a block
+                let label = self.new_label();
+                self.label(&label);
+            }
+            self.generate_statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn generate_statement(&mut self, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::Conditional(con) => {
+                let (then_label, else_label, end_label) = (self.new_label(), self.new_label(), self.new_label());
+                let condition = self.generate_condition(&con.condition)?;
+                self.terminate(format!("br i1 {}, label %{}, label %{}", condition, then_label, else_label));
+                self.label(&then_label);
+                self.generate_statement_list(&con.body)?;
+                if !self.terminated {
+                    self.terminate(format!("br label %{}", end_label));
+                }
+                self.label(&else_label);
+                self.generate_statement_list(&con.else_body)?;
+                self.label(&end_label);
+            }
+            Statement::Loop(lo) => {
+                let (cond_label, body_label, end_label) = (self.new_label(), self.new_label(), self.new_label());
+                self.label(&cond_label);
+                let condition = self.generate_condition(&lo.condition)?;
+                self.terminate(format!("br i1 {}, label %{}, label %{}", condition, body_label, end_label));
+                self.label(&body_label);
+                self.generate_statement_list(&lo.body)?;
+                if !self.terminated {
+                    self.terminate(format!("br label %{}", cond_label));
+                }
+                self.label(&end_label);
+            }
+            Statement::Input(input) => {
+                let repr = scope::input_variable(input);
+                let (pointer, ty) = self.generate_access(&repr)?;
+                let function = match scope::scalar(&ty, input)? {
+                    ScalarType::Integer => "@snl.read_integer",
+                    ScalarType::Char => "@snl.read_char",
+                };
+                let (line, column) = input.position();
+                let value = self.temp();
+                self.line(format!("{} = call i64 {}(i32 {}, i32 {})", value, function, line, column));
+                self.store(&pointer, &ty, value);
+            }
+            Statement::Output(output) => {
+                let (value, ty) = self.generate_expression(output)?;
+                match ty {
+                    ScalarType::Integer => self.line(format!("call void @snl.write_integer(i64 {})", value)),
+                    ScalarType::Char => self.line(format!("call void @snl.write_char(i64 {})", value)),
+                }
+            }
+            Statement::Return(ret) => {
+                // the value has no receiver
+                self.generate_expression(ret)?;
+                self.terminate("ret void");
+            }
+            Statement::Assign(assign) => {
+                let (pointer, ty) = self.generate_access(&assign.variable)?;
+                if ty.scalar().is_some() {
+                    let (value, _) = self.generate_expression(&assign.value)?;
+                    self.store(&pointer, &ty, value);
+                } else {
+                    let (src, _) = self.generate_access(scope::structured_source(assign)?)?;
+                    let value = self.temp();
+                    let llvm_type = llvm_type(&ty);
+                    self.line(format!("{} = load {}, ptr {}", value, llvm_type, src));
+                    self.line(format!("store {} {}, ptr {}", llvm_type, value, pointer));
+                }
+            }
+            Statement::Call(call) => self.generate_call(call)?,
+            Statement::Error(error) => return Err(scope::syntax_error(error)),
+        }
+        Ok(())
+    }
+
+    /// Store an `i64` value into a scalar, truncating it for chars
+    fn store(&mut self, pointer: &str, ty: &Type, value: String) {
+        match ty.scalar() {
+            Some(ScalarType::Char) => {
+                let char = self.temp();
+                self.line(format!("{} = trunc i64 {} to i8", char, value));
+                self.line(format!("store i8 {}, ptr {}", char, pointer));
+            }
+            _ => self.line(format!("store i64 {}, ptr {}", value, pointer)),
+        }
+    }
+
+    fn generate_call(&mut self, call: &Positional<CallStatement>) -> Result<()> {
+        let Procedure { level, name, params } = self.scope.procedure(call)?;
+        let (level, name, params) = (*level, name.clone(), params.clone());
+
+        // the environment of the callee is the frame of its parent, at `level - 1`
+        let env = self.frame(level - 1);
+        let mut arguments = vec![format!("ptr {}", env)];
+        for ((is_var, ty), exp) in params.iter().zip(call.params.iter()) {
+            if *is_var || ty.scalar().is_none() {
+                let (pointer, _) = self.generate_access(scope::referenced_argument(exp, *is_var, call.name())?)?;
+                if *is_var {
+                    arguments.push(format!("ptr {}", pointer));
+                } else {
+                    let value = self.temp();
+                    let llvm_type = llvm_type(ty);
+                    self.line(format!("{} = load {}, ptr {}", value, llvm_type, pointer));
+                    arguments.push(format!("{} {}", llvm_type, value));
+                }
+            } else {
+                let (value, _) = self.generate_expression(exp)?;
+                if ty.scalar() == Some(ScalarType::Char) {
+                    let char = self.temp();
+                    self.line(format!("{} = trunc i64 {} to i8", char, value));
+                    arguments.push(format!("i8 {}", char));
+                } else {
+                    arguments.push(format!("i64 {}", value));
+                }
+            }
+        }
+        self.line(format!("call void {}({})", global(&format!("snl.{}", name)), arguments.join(", ")));
+        Ok(())
+    }
+
+    /// Compare two expressions into an `i1`
+    fn generate_condition(&mut self, rel: &RelationExpression) -> Result<String> {
+        let (left, _) = self.generate_expression(&rel.left)?;
+        let (right, _) = self.generate_expression(&rel.right)?;
+        let condition = self.temp();
+        let predicate = match rel.op.as_str() {
+            "<" => "slt",
+            "=" => "eq",
+            _ => unreachable!(),
+        };
+        self.line(format!("{} = icmp {} i64 {}, {}", condition, predicate, left, right));
+        Ok(condition)
+    }
+
+    fn generate_expression(&mut self, exp: &Expression) -> Result<(String, ScalarType)> {
+        match exp {
+            Expression::Binary(binary) => {
+                let left = self.generate_expression(&binary.left)?;
+                let right = self.generate_expression(&binary.right)?;
+                Ok(self.binary(binary.op, left, right, binary.right.position()))
+            }
+            Expression::Factor(factor) => self.generate_factor(factor),
+        }
+    }
+
+    fn binary(&mut self, op: BinaryOperator, (left, left_type): (String, ScalarType), (right, right_type): (String, ScalarType),
+              (line, column): (u32, u32)) -> (String, ScalarType) {
+        let value = self.temp();
+        match op {
+            BinaryOperator::Add => self.line(format!("{} = add i64 {}, {}", value, left, right)),
+            BinaryOperator::Sub => self.line(format!("{} = sub i64 {}, {}", value, left, right)),
+            BinaryOperator::Mul => self.line(format!("{} = mul i64 {}, {}", value, left, right)),
+            BinaryOperator::Div => self.line(format!("{} = call i64 @snl.divide(i64 {}, i64 {}, i32 {}, i32 {})", value, left, right, line, column)),
+        }
+        let ty = if left_type == ScalarType::Char && right_type == ScalarType::Char {
+            ScalarType::Char
+        } else {
+            ScalarType::Integer
+        };
+        (value, ty)
+    }
+
+    fn generate_factor(&mut self, factor: &Positional<ExpressionFactor>) -> Result<(String, ScalarType)> {
+        match factor.inner() {
+            ExpressionFactor::Bracket(exp) => self.generate_expression(exp),
+            ExpressionFactor::Constant(c) => Ok((c.to_string(), ScalarType::Integer)),
+            ExpressionFactor::Variable(repr) => {
+                let (pointer, ty) = self.generate_access(repr)?;
+                let ty = scope::scalar(&ty, &repr.base)?;
+                let value = self.temp();
+                match ty {
+                    ScalarType::Integer => self.line(format!("{} = load i64, ptr {}", value, pointer)),
+                    ScalarType::Char => {
+                        let char = self.temp();
+                        self.line(format!("{} = load i8, ptr {}", char, pointer));
+                        self.line(format!("{} = zext i8 {} to i64", value, char));
+                    }
+                }
+                Ok((value, ty))
+            }
+        }
+    }
+
+    /// Pointer to the frame of the enclosing block at `level`, following static links
+    fn frame(&mut self, level: usize) -> String {
+        let mut frame = "%frame".to_owned();
+        for current in (level + 1..self.frames.len()).rev() {
+            let link = self.temp();
+            self.line(format!("{} = getelementptr {}, ptr {}, i32 0, i32 0", link, self.frames[current], frame));
+            frame = self.temp();
+            self.line(format!("{} = load ptr, ptr {}", frame, link));
+        }
+        frame
+    }
+
+    /// Pointer to a variable represent, with field and index offsets applied
+    fn generate_access(&mut self, repr: &VariableRepresent) -> Result<(String, Type)> {
+        let Variable { level, index, is_var, ty } = self.scope.variable(&repr.base)?;
+        let (level, index, is_var, mut ty) = (*level, *index, *is_var, ty.clone());
+        let frame = self.frame(level);
+        let mut pointer = self.temp();
+        self.line(format!("{} = getelementptr {}, ptr {}, i32 0, i32 {}", pointer, self.frames[level], frame, index));
+        if is_var {
+            // the field of a var parameter holds the address of the argument
+            let address = self.temp();
+            self.line(format!("{} = load ptr, ptr {}", address, pointer));
+            pointer = address;
+        }
+
+        if let Some(visit) = &repr.visit {
+            if let Some(field) = &visit.dot {
+                let field_type = scope::field(&ty, field)?.1.clone();
+                // struct members are numbered by declaration, not by cell offset
+                let index = match &ty {
+                    Type::Record(fields) => fields.iter().position(|(name, _)| name == field.inner()).unwrap(),
+                    _ => unreachable!(),
+                };
+                let field_pointer = self.temp();
+                self.line(format!("{} = getelementptr {}, ptr {}, i32 0, i32 {}", field_pointer, llvm_type(&ty), pointer, index));
+                pointer = field_pointer;
+                ty = field_type;
+            }
+
+            // array indexes are rebased to start from zero
+            if let Some(index) = &visit.sqbr {
+                let (base, lower_bound, upper_bound) = scope::element(&ty, repr, index)?;
+                let checked = self.options.bounds_check && index.constant().is_none();
+                let (line, column) = index.position();
+                let (mut index, _) = self.generate_expression(index)?;
+                if checked {
+                    let checked = self.temp();
+                    self.line(format!(
+                        "{} = call i64 @snl.check_index(i64 {}, i64 {}, i64 {}, i32 {}, i32 {})",
+                        checked, index, lower_bound, upper_bound, line, column,
+                    ));
+                    index = checked;
+                }
+                if lower_bound != 0 {
+                    let rebased = self.temp();
+                    self.line(format!("{} = sub i64 {}, {}", rebased, index, lower_bound));
+                    index = rebased;
+                }
+                let element = self.temp();
+                self.line(format!("{} = getelementptr {}, ptr {}, i64 0, i64 {}", element, llvm_type(&ty), pointer, index));
+                pointer = element;
+                ty = Type::Scalar(base);
+            }
+        }
+        Ok((pointer, ty))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generate;
+    use snl_rdp::Parser;
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    fn llvm(source: &str) -> String {
+        let tokens = snl_lexer::read_tokens(source).unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        generate(&ast, Default::default()).unwrap()
+    }
+
+    /// Compare with a file under `golden/`, set `SNL_BLESS` to rewrite it instead
+    fn golden(source: &str, name: &str) {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("golden").join(name);
+        let output = llvm(source);
+        if std::env::var_os("SNL_BLESS").is_some() {
+            std::fs::write(&path, &output).unwrap();
+        }
+        assert_eq!(output, std::fs::read_to_string(&path).unwrap(), "output differs from {}", name);
+    }
+
+    #[test]
+    fn test_golden() {
+        golden(include_str!("../../examples/program1.snl"), "program1.ll");
+        golden(include_str!("../golden/nested.snl"), "nested.ll");
+    }
+
+    /// Run with the LLVM interpreter, skipped when it is not installed
+    #[test]
+    fn test_run() {
+        let path = std::env::temp_dir().join(format!("snl-llvm-{}.ll", std::process::id()));
+        std::fs::write(&path, llvm(include_str!("../golden/nested.snl"))).unwrap();
+        // LLVM before 15 needs opaque pointers enabled explicitly, later versions dropped the flag
+        let mut output = None;
+        for args in [&["-opaque-pointers"][..], &[][..]].iter() {
+            let mut child = match Command::new("lli")
+                .args(args.iter())
+                .arg(&path)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn() {
+                Ok(child) => child,
+                Err(_) => break,
+            };
+            child.stdin.take().unwrap().write_all(b" x").unwrap();
+            let result = child.wait_with_output().unwrap();
+            if !String::from_utf8_lossy(&result.stderr).contains("Unknown command line argument") {
+                output = Some(result);
+                break;
+            }
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        if let Some(output) = output {
+            assert_eq!(String::from_utf8(output.stdout).unwrap(), "112\nx\n-22\n112\n112\n9\n");
+            assert_eq!(String::from_utf8(output.stderr).unwrap(), "Runtime error at line 54, column 18:\tDivision by zero\n");
+        }
+    }
+}
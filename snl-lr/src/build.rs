@@ -0,0 +1,321 @@
+use crate::grammar::Grammar;
+use snl_utils::ast::*;
+use snl_utils::error::SyntaxError;
+use snl_utils::span::Span;
+use snl_utils::token::{Token, TokenType};
+use std::str::FromStr;
+
+/// Node on the value stack of the parser. Nodes of generated non-terminals are
+/// merged into their parents, so children follow the EBNF rules of `grammar/snl.bnf`.
+#[derive(Debug)]
+pub(crate) enum Tree {
+    Token(Token),
+    Node { non_terminal: usize, children: Vec<Tree> },
+}
+
+impl Tree {
+    fn children(&self) -> &[Tree] {
+        match self {
+            Tree::Token(_) => &[],
+            Tree::Node { children, .. } => children,
+        }
+    }
+
+    fn token(&self) -> &Token {
+        match self {
+            Tree::Token(token) => token,
+            Tree::Node { .. } => panic!("expected a token, got {:?}", self),
+        }
+    }
+
+    fn is(&self, token_type: TokenType) -> bool {
+        matches!(self, Tree::Token(token) if token.token_type == token_type)
+    }
+
+    fn first_token(&self) -> Option<&Token> {
+        match self {
+            Tree::Token(token) => Some(token),
+            Tree::Node { children, .. } => children.iter().find_map(Tree::first_token),
+        }
+    }
+
+    fn last_token(&self) -> Option<&Token> {
+        match self {
+            Tree::Token(token) => Some(token),
+            Tree::Node { children, .. } => children.iter().rev().find_map(Tree::last_token),
+        }
+    }
+}
+
+/// Span from the first to the last token of the trees
+fn span(trees: &[Tree]) -> Span {
+    let first = trees.iter().find_map(Tree::first_token).unwrap();
+    let last = trees.iter().rev().find_map(Tree::last_token).unwrap();
+    first.span.to(last.span)
+}
+
+/// Integer literal, an error is reported at the literal if it does not fit in `T`
+fn integer<T: FromStr>(token: &Token) -> Result<T> {
+    T::from_str(&token.image).map_err(|_| Positional::from_token(token, SyntaxError::IntegerOverflow(token.image.clone())))
+}
+
+/// Builds the AST from the parse tree, with the same spans as the recursive descent parser
+pub(crate) struct Builder<'a> {
+    grammar: &'a Grammar,
+}
+
+type Result<T> = std::result::Result<T, Positional<SyntaxError>>;
+
+impl<'a> Builder<'a> {
+    pub fn new(grammar: &'a Grammar) -> Self {
+        Self { grammar }
+    }
+
+    fn name(&self, tree: &Tree) -> &str {
+        match tree {
+            Tree::Token(token) => token.token_type.grammar_name(),
+            Tree::Node { non_terminal, .. } => self.grammar.name(*non_terminal),
+        }
+    }
+
+    /// Nodes among the children of the tree
+    fn nodes<'t>(&self, tree: &'t Tree) -> impl Iterator<Item = &'t Tree> {
+        tree.children().iter().filter(|child| matches!(child, Tree::Node { .. }))
+    }
+
+    pub fn program(&self, tree: &Tree) -> Result<Positional<Program>> {
+        let c = tree.children();
+        let head = c[0].children();
+        let name = head[1].children()[0].token().image.clone();
+        Ok(Positional::from_span(span(c), Program {
+            name,
+            declare: self.declare_part(&c[1])?,
+            body: self.program_body(&c[2])?,
+        }))
+    }
+
+    fn declare_part(&self, tree: &Tree) -> Result<ProgramDeclare> {
+        let c = tree.children();
+        let mut declare = ProgramDeclare {
+            type_declare: Vec::new(),
+            variable_declare: Vec::new(),
+            procedure_declare: Vec::new(),
+        };
+        // TypeDeclaration ::= TYPE ( TypeId EQ TypeName SEMICOLON )+
+        if let Some(types) = c[0].children().first() {
+            for declaration in types.children()[1].children().chunks(4) {
+                let name = declaration[0].children()[0].token();
+                declare.type_declare.push(Positional::from_token(name, TypeDeclare {
+                    base: self.type_name(&declaration[2])?,
+                    name: name.image.clone(),
+                }));
+            }
+        }
+        // VarDeclaration ::= VAR ( TypeName IdentifierList SEMICOLON )+
+        if let Some(vars) = c[1].children().first() {
+            for declaration in vars.children()[1].children().chunks(3) {
+                declare.variable_declare.push(Positional::from_span(span(&declaration[..2]), TypedIdentifiers {
+                    type_name: self.type_name(&declaration[0])?,
+                    identifiers: self.identifier_list(&declaration[1]),
+                }));
+            }
+        }
+        if let Some(procedures) = c[2].children().first() {
+            declare.procedure_declare = self.procedures(procedures)?;
+        }
+        Ok(declare)
+    }
+
+    /// `( PROCEDURE ProcName BR_OPEN ParamList? BR_CLOSE SEMICOLON ProcDecPart ProcBody )+`
+    fn procedures(&self, tree: &Tree) -> Result<PositionalVec<ProcedureDeclare>> {
+        let mut result = Vec::new();
+        let mut children = tree.children().iter();
+        // PROCEDURE is taken by the loop
+        while children.next().is_some() {
+            let name = children.next().unwrap().children()[0].token();
+            let mut rest = children.by_ref().skip(1);
+            let mut params = Vec::new();
+            let mut next = rest.next().unwrap();
+            if !next.is(TokenType::BracketClose) {
+                for param in self.nodes(next) {
+                    params.push(self.param(param)?);
+                }
+                next = rest.next().unwrap();
+            }
+            debug_assert!(next.is(TokenType::BracketClose));
+            rest.next();
+            let declare = self.declare_part(&rest.next().unwrap().children()[0])?;
+            let body = self.program_body(&rest.next().unwrap().children()[0])?;
+            result.push(Positional::from_token(name, ProcedureDeclare {
+                name: name.image.clone(),
+                params,
+                declare: Box::new(declare),
+                body,
+            }));
+        }
+        Ok(result)
+    }
+
+    /// `VAR? TypeName IdentifierList`
+    fn param(&self, tree: &Tree) -> Result<Positional<Param>> {
+        let c = tree.children();
+        let is_var = c[0].is(TokenType::Var);
+        let c = if is_var { &c[1..] } else { c };
+        Ok(Positional::from_span(span(c), Param {
+            is_var,
+            definition: TypedIdentifiers {
+                type_name: self.type_name(&c[0])?,
+                identifiers: self.identifier_list(&c[1]),
+            },
+        }))
+    }
+
+    /// Any of `TypeName`, `FieldDecType` and the types they are made of
+    fn type_name(&self, tree: &Tree) -> Result<Positional<SNLType>> {
+        let c = tree.children();
+        let inner = match self.name(tree) {
+            "BaseType" => match c[0].token().token_type {
+                TokenType::Integer => SNLType::Integer,
+                _ => SNLType::Char,
+            },
+            "ArrayType" => SNLType::Array(SNLTypeArray {
+                base: match c[7].children()[0].token().token_type {
+                    TokenType::Integer => SNLBaseType::Integer,
+                    _ => SNLBaseType::Char,
+                },
+                lower_bound: integer(c[2].children()[0].token())?,
+                upper_bound: integer(c[4].children()[0].token())?,
+            }),
+            // RecordType ::= RECORD ( FieldDecType IdentifierList SEMICOLON )+ END
+            "RecordType" => {
+                let mut fields = Vec::new();
+                for field in c[1].children().chunks(3) {
+                    fields.push(TypedIdentifiers {
+                        type_name: self.type_name(&field[0])?,
+                        identifiers: self.identifier_list(&field[1]),
+                    });
+                }
+                SNLType::Record(fields)
+            }
+            _ => match &c[0] {
+                Tree::Token(name) => SNLType::Others(name.image.clone()),
+                node => return self.type_name(node),
+            },
+        };
+        Ok(Positional::from_span(span(c), inner))
+    }
+
+    fn identifier_list(&self, tree: &Tree) -> PositionalVec<String> {
+        tree.children()
+            .iter()
+            .filter(|child| child.is(TokenType::Identifier))
+            .map(|child| Positional::from_token_image(child.token()))
+            .collect()
+    }
+
+    /// `BEGIN StatementList END`
+    fn program_body(&self, tree: &Tree) -> Result<StatementList> {
+        self.statement_list(&tree.children()[1])
+    }
+
+    fn statement_list(&self, tree: &Tree) -> Result<StatementList> {
+        self.nodes(tree).map(|statement| self.statement(statement)).collect()
+    }
+
+    fn statement(&self, tree: &Tree) -> Result<Statement> {
+        let c = tree.children();
+        if let Tree::Token(base) = &c[0] {
+            // IDENTIFIER AssCall
+            let rest = &c[1].children()[0];
+            let r = rest.children();
+            return Ok(match self.name(rest) {
+                "AssignmentRest" => Statement::Assign(AssignStatement {
+                    variable: VariableRepresent {
+                        base: Positional::from_token_image(base),
+                        visit: self.variable_visit(&r[0])?,
+                    },
+                    value: self.exp(&r[2])?,
+                }),
+                _ => Statement::Call(Positional::from_span(base.span.to(r[r.len() - 1].token().span), CallStatement {
+                    name: base.image.clone(),
+                    params: self.nodes(rest).map(|exp| self.exp(exp)).collect::<Result<_>>()?,
+                })),
+            });
+        }
+        let s = c[0].children();
+        Ok(match self.name(&c[0]) {
+            "ConditionalStatement" => Statement::Conditional(ConditionalStatement {
+                condition: self.rel_exp(&s[1])?,
+                body: self.statement_list(&s[3])?,
+                else_body: self.statement_list(&s[5])?,
+            }),
+            "LoopStatement" => Statement::Loop(LoopStatement {
+                condition: self.rel_exp(&s[1])?,
+                body: self.statement_list(&s[3])?,
+            }),
+            "InputStatement" => Statement::Input(Positional::from_token_image(s[2].token())),
+            "OutputStatement" => Statement::Output(self.exp(&s[2])?),
+            _ => Statement::Return(self.exp(&s[2])?),
+        })
+    }
+
+    /// `Exp CmdOp Exp`
+    fn rel_exp(&self, tree: &Tree) -> Result<RelationExpression> {
+        let c = tree.children();
+        Ok(RelationExpression {
+            left: self.exp(&c[0])?,
+            op: c[1].children()[0].token().image.clone(),
+            right: self.exp(&c[2])?,
+        })
+    }
+
+    /// `Term ( AddOp Term )*` and `Factor ( MultOp Factor )*`, associating to the left
+    fn exp(&self, tree: &Tree) -> Result<Expression> {
+        let c = tree.children();
+        let operand = |tree: &Tree| match self.name(tree) {
+            "Factor" => Ok(Expression::Factor(self.factor(tree)?)),
+            _ => self.exp(tree),
+        };
+        let mut left = operand(&c[0])?;
+        for pair in c[1..].chunks(2) {
+            let op = BinaryOperator::from_str(&pair[0].children()[0].token().image).unwrap();
+            left = Expression::binary(left, op, operand(&pair[1])?);
+        }
+        Ok(left)
+    }
+
+    fn factor(&self, tree: &Tree) -> Result<Positional<ExpressionFactor>> {
+        let c = tree.children();
+        let inner = match &c[0] {
+            Tree::Token(token) if token.token_type == TokenType::BracketOpen => ExpressionFactor::Bracket(Box::new(self.exp(&c[1])?)),
+            Tree::Token(token) => ExpressionFactor::Constant(integer(token)?),
+            // Variable ::= IDENTIFIER VariableVisit
+            variable => {
+                let v = variable.children();
+                ExpressionFactor::Variable(VariableRepresent {
+                    base: Positional::from_token_image(v[0].token()),
+                    visit: self.variable_visit(&v[1])?,
+                })
+            }
+        };
+        Ok(Positional::from_span(span(c), inner))
+    }
+
+    /// `( DOT IDENTIFIER )? ( SQBR_OPEN Exp SQBR_CLOSE )?`
+    fn variable_visit(&self, tree: &Tree) -> Result<Option<VariableVisit>> {
+        let c = tree.children();
+        if c.is_empty() {
+            return Ok(None);
+        }
+        let (dot, rest) = if c[0].is(TokenType::Dot) {
+            (Some(Positional::from_token_image(c[1].token())), &c[2..])
+        } else {
+            (None, c)
+        };
+        let sqbr = match rest.get(1) {
+            Some(index) => Some(Box::new(self.exp(index)?)),
+            None => None,
+        };
+        Ok(Some(VariableVisit { dot, sqbr }))
+    }
+}
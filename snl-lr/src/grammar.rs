@@ -0,0 +1,429 @@
+use snl_utils::token::TokenType;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// Grammar the LR parser is generated from
+pub const SNL_GRAMMAR: &str = include_str!("../../grammar/snl.bnf");
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum GrammarError {
+    #[error("line {line}: expected `::=` after {name}")]
+    ExpectedDefine { line: usize, name: String },
+    #[error("line {line}: unknown terminal {name}")]
+    UnknownTerminal { line: usize, name: String },
+    #[error("line {line}: unexpected `{word}`")]
+    UnexpectedWord { line: usize, word: String },
+    #[error("line {line}: {name} is defined more than once")]
+    DuplicateRule { line: usize, name: String },
+    #[error("rule {name} is not terminated with `;`")]
+    UnterminatedRule { name: String },
+    #[error("{name} is used but never defined")]
+    UndefinedNonTerminal { name: String },
+    #[error("grammar has no syntax rules")]
+    Empty,
+}
+
+/// Terminal, or non-terminal by its index in `Grammar::non_terminals`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Symbol {
+    Terminal(TokenType),
+    NonTerminal(usize),
+}
+
+#[derive(Debug)]
+pub struct NonTerminal {
+    pub name: String,
+    /// Added for a group, a repetition or an optional part of a rule, or for the start,
+    /// nodes of generated non-terminals are merged into their parents
+    pub generated: bool,
+}
+
+/// One alternative of a rule, an empty `rhs` derives nothing
+#[derive(Debug, Clone, PartialEq)]
+pub struct Production {
+    pub lhs: usize,
+    pub rhs: Vec<Symbol>,
+}
+
+/// Context-free grammar read from EBNF, with the FIRST and FOLLOW sets of an LR parser.
+///
+/// Rules are written as `Name ::= A ( b | C )* d? | ;`. Rules with CamelCase names are
+/// syntax rules, the others describe tokens and are skipped. Upper-case names in syntax rules
+/// are terminals and the first syntax rule defines the start symbol.
+///
+/// Groups, repetitions and optional parts get generated non-terminals, repetitions are
+/// left recursive so the stack of the parser does not grow with them. Production 0 is
+/// `Start' ::= Start`, which is reduced with `EOF` to accept.
+#[derive(Debug)]
+pub struct Grammar {
+    pub non_terminals: Vec<NonTerminal>,
+    pub productions: Vec<Production>,
+    nullable: Vec<bool>,
+    first: Vec<BTreeSet<TokenType>>,
+    follow: Vec<BTreeSet<TokenType>>,
+}
+
+/// Right-hand side of a rule before it is desugared
+enum Expr<'a> {
+    Name(usize, &'a str),
+    Group(Vec<Vec<Expr<'a>>>),
+    Repeat(Box<Expr<'a>>, &'a str),
+}
+
+impl Grammar {
+    pub fn parse(source: &str) -> Result<Self, GrammarError> {
+        let mut rules: Vec<(&str, Vec<Vec<Expr>>)> = Vec::new();
+        let mut words = Words::new(source).peekable();
+        while let Some((line, name)) = words.next() {
+            match words.next() {
+                Some((_, "::=")) => {}
+                _ => return Err(GrammarError::ExpectedDefine { line, name: name.to_owned() }),
+            }
+            if !is_non_terminal(name) {
+                // lexical rule
+                loop {
+                    match words.next() {
+                        Some((_, ";")) => break,
+                        Some((_, "::=")) | None => return Err(GrammarError::UnterminatedRule { name: name.to_owned() }),
+                        Some(_) => {}
+                    }
+                }
+                continue;
+            }
+            if rules.iter().any(|(rule, _)| *rule == name) {
+                return Err(GrammarError::DuplicateRule { line, name: name.to_owned() });
+            }
+            let alternatives = parse_alternatives(&mut words, name)?;
+            match words.next() {
+                Some((_, ";")) => {}
+                Some((line, word)) if word == ")" => return Err(GrammarError::UnexpectedWord { line, word: word.to_owned() }),
+                _ => return Err(GrammarError::UnterminatedRule { name: name.to_owned() }),
+            }
+            rules.push((name, alternatives));
+        }
+        if rules.is_empty() {
+            return Err(GrammarError::Empty);
+        }
+
+        let mut grammar = Grammar {
+            non_terminals: vec![NonTerminal { name: format!("{}'", rules[0].0), generated: true }],
+            productions: vec![Production { lhs: 0, rhs: vec![Symbol::NonTerminal(1)] }],
+            nullable: Default::default(),
+            first: Default::default(),
+            follow: Default::default(),
+        };
+        for (name, _) in rules.iter() {
+            grammar.non_terminals.push(NonTerminal { name: (*name).to_owned(), generated: false });
+        }
+        for (i, (_, alternatives)) in rules.iter().enumerate() {
+            let mut helpers = 0;
+            for alternative in alternatives.iter() {
+                let rhs = grammar.lower_sequence(alternative, i + 1, &mut helpers)?;
+                grammar.productions.push(Production { lhs: i + 1, rhs });
+            }
+        }
+        grammar.compute_first();
+        grammar.compute_follow();
+        Ok(grammar)
+    }
+
+    /// The grammar of SNL used by the parser
+    pub fn snl() -> Grammar {
+        Grammar::parse(SNL_GRAMMAR).expect("invalid SNL grammar")
+    }
+
+    pub fn name(&self, non_terminal: usize) -> &str {
+        &self.non_terminals[non_terminal].name
+    }
+
+    /// Index of the non-terminal named `name`
+    pub fn non_terminal(&self, name: &str) -> Option<usize> {
+        self.non_terminals.iter().position(|non_terminal| non_terminal.name == name)
+    }
+
+    pub fn symbol_name(&self, symbol: Symbol) -> &str {
+        match symbol {
+            Symbol::Terminal(terminal) => terminal.grammar_name(),
+            Symbol::NonTerminal(non_terminal) => self.name(non_terminal),
+        }
+    }
+
+    /// The production written as `Lhs ::= A b`, `ε` stands for an empty right-hand side
+    pub fn production_string(&self, production: usize) -> String {
+        let production = &self.productions[production];
+        let mut out = format!("{} ::=", self.name(production.lhs));
+        if production.rhs.is_empty() {
+            out.push_str(" ε");
+        }
+        for symbol in production.rhs.iter() {
+            write!(out, " {}", self.symbol_name(*symbol)).unwrap();
+        }
+        out
+    }
+
+    /// Indexes of the productions of a non-terminal
+    pub fn productions_of(&self, non_terminal: usize) -> impl Iterator<Item = usize> + '_ {
+        self.productions.iter().enumerate().filter(move |(_, production)| production.lhs == non_terminal).map(|(i, _)| i)
+    }
+
+    pub fn is_nullable(&self, non_terminal: usize) -> bool {
+        self.nullable[non_terminal]
+    }
+
+    pub fn first(&self, non_terminal: usize) -> &BTreeSet<TokenType> {
+        &self.first[non_terminal]
+    }
+
+    pub fn follow(&self, non_terminal: usize) -> &BTreeSet<TokenType> {
+        &self.follow[non_terminal]
+    }
+
+    /// FIRST of a sequence of symbols and whether all of them are nullable
+    pub fn first_of(&self, symbols: &[Symbol]) -> (BTreeSet<TokenType>, bool) {
+        let mut set = BTreeSet::new();
+        for symbol in symbols.iter() {
+            match *symbol {
+                Symbol::Terminal(terminal) => {
+                    set.insert(terminal);
+                    return (set, false);
+                }
+                Symbol::NonTerminal(non_terminal) => {
+                    set.extend(self.first[non_terminal].iter().copied());
+                    if !self.nullable[non_terminal] {
+                        return (set, false);
+                    }
+                }
+            }
+        }
+        (set, true)
+    }
+
+    /// Symbols of a sequence, adding non-terminals for what it contains
+    fn lower_sequence(&mut self, sequence: &[Expr], lhs: usize, helpers: &mut usize) -> Result<Vec<Symbol>, GrammarError> {
+        let mut symbols = Vec::new();
+        for expr in sequence.iter() {
+            symbols.extend(self.lower(expr, lhs, helpers)?);
+        }
+        Ok(symbols)
+    }
+
+    fn lower(&mut self, expr: &Expr, lhs: usize, helpers: &mut usize) -> Result<Vec<Symbol>, GrammarError> {
+        Ok(match expr {
+            Expr::Name(_, name) if is_non_terminal(name) => match self.non_terminal(name) {
+                Some(non_terminal) if !self.non_terminals[non_terminal].generated => vec![Symbol::NonTerminal(non_terminal)],
+                _ => return Err(GrammarError::UndefinedNonTerminal { name: (*name).to_owned() }),
+            },
+            Expr::Name(line, name) => match TokenType::from_grammar_name(name) {
+                Some(terminal) if terminal != TokenType::EOF => vec![Symbol::Terminal(terminal)],
+                _ => return Err(GrammarError::UnknownTerminal { line: *line, name: (*name).to_owned() }),
+            },
+            Expr::Group(alternatives) if alternatives.len() == 1 => self.lower_sequence(&alternatives[0], lhs, helpers)?,
+            Expr::Group(alternatives) => {
+                let helper = self.add_helper(lhs, helpers);
+                for alternative in alternatives.iter() {
+                    let rhs = self.lower_sequence(alternative, lhs, helpers)?;
+                    self.productions.push(Production { lhs: helper, rhs });
+                }
+                vec![Symbol::NonTerminal(helper)]
+            }
+            Expr::Repeat(inner, op) => {
+                let body = self.lower(inner, lhs, helpers)?;
+                let helper = self.add_helper(lhs, helpers);
+                let (first, second) = match *op {
+                    // H ::= H X | ε
+                    "*" => ([&[Symbol::NonTerminal(helper)][..], &body].concat(), vec![]),
+                    // H ::= H X | X
+                    "+" => ([&[Symbol::NonTerminal(helper)][..], &body].concat(), body),
+                    // H ::= X | ε
+                    _ => (body, vec![]),
+                };
+                self.productions.push(Production { lhs: helper, rhs: first });
+                self.productions.push(Production { lhs: helper, rhs: second });
+                vec![Symbol::NonTerminal(helper)]
+            }
+        })
+    }
+
+    /// Non-terminal named after the rule it is generated for, e.g. `Exp#1`
+    fn add_helper(&mut self, lhs: usize, helpers: &mut usize) -> usize {
+        *helpers += 1;
+        let name = format!("{}#{}", self.non_terminals[lhs].name, helpers);
+        self.non_terminals.push(NonTerminal { name, generated: true });
+        self.non_terminals.len() - 1
+    }
+
+    fn compute_first(&mut self) {
+        self.nullable = vec![false; self.non_terminals.len()];
+        self.first = vec![BTreeSet::new(); self.non_terminals.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for i in 0..self.productions.len() {
+                let (set, nullable) = self.first_of(&self.productions[i].rhs);
+                let lhs = self.productions[i].lhs;
+                let len = self.first[lhs].len();
+                self.first[lhs].extend(set);
+                changed |= self.first[lhs].len() != len;
+                if nullable && !self.nullable[lhs] {
+                    self.nullable[lhs] = true;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    fn compute_follow(&mut self) {
+        self.follow = vec![BTreeSet::new(); self.non_terminals.len()];
+        self.follow[0].insert(TokenType::EOF);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for production in self.productions.iter() {
+                for (i, symbol) in production.rhs.iter().enumerate() {
+                    if let Symbol::NonTerminal(non_terminal) = *symbol {
+                        let (mut set, nullable) = self.first_of(&production.rhs[i + 1..]);
+                        if nullable {
+                            set.extend(self.follow[production.lhs].iter().copied());
+                        }
+                        let len = self.follow[non_terminal].len();
+                        self.follow[non_terminal].extend(set);
+                        changed |= self.follow[non_terminal].len() != len;
+                    }
+                }
+            }
+        }
+    }
+}
+
+type PeekableWords<'a> = std::iter::Peekable<Words<'a>>;
+
+/// `a b | c d` up to a `)` or `;`, which is left to the caller
+fn parse_alternatives<'a>(words: &mut PeekableWords<'a>, name: &str) -> Result<Vec<Vec<Expr<'a>>>, GrammarError> {
+    let mut alternatives = vec![Vec::new()];
+    loop {
+        let (line, word) = match words.peek() {
+            Some(&(_, ")")) | Some(&(_, ";")) => return Ok(alternatives),
+            Some(&(_, "::=")) | None => return Err(GrammarError::UnterminatedRule { name: name.to_owned() }),
+            Some(&word) => word,
+        };
+        words.next();
+        let expr = match word {
+            "|" => {
+                alternatives.push(Vec::new());
+                continue;
+            }
+            "(" => {
+                let group = parse_alternatives(words, name)?;
+                match words.next() {
+                    Some((_, ")")) => Expr::Group(group),
+                    Some((line, word)) => return Err(GrammarError::UnexpectedWord { line, word: word.to_owned() }),
+                    None => return Err(GrammarError::UnterminatedRule { name: name.to_owned() }),
+                }
+            }
+            "*" | "+" | "?" | ")" => return Err(GrammarError::UnexpectedWord { line, word: word.to_owned() }),
+            _ if word.starts_with('"') => return Err(GrammarError::UnexpectedWord { line, word: word.to_owned() }),
+            _ => Expr::Name(line, word),
+        };
+        let mut expr = expr;
+        while let Some(&(_, op @ ("*" | "+" | "?"))) = words.peek() {
+            words.next();
+            expr = Expr::Repeat(Box::new(expr), op);
+        }
+        alternatives.last_mut().unwrap().push(expr);
+    }
+}
+
+/// CamelCase names are non-terminals
+fn is_non_terminal(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_uppercase()) && word.contains(|c: char| c.is_ascii_lowercase())
+}
+
+/// Words of an EBNF file with their line numbers, skipping `//` comments.
+/// Punctuation is a word of its own and string literals are single words.
+struct Words<'a> {
+    source: &'a str,
+    offset: usize,
+    line: usize,
+}
+
+impl<'a> Words<'a> {
+    fn new(source: &'a str) -> Self {
+        Words { source, offset: 0, line: 1 }
+    }
+
+    fn skip(&mut self, len: usize) {
+        self.line += self.source[self.offset..self.offset + len].matches('\n').count();
+        self.offset += len;
+    }
+}
+
+impl<'a> Iterator for Words<'a> {
+    type Item = (usize, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let rest = &self.source[self.offset..];
+            let trimmed = rest.trim_start();
+            self.skip(rest.len() - trimmed.len());
+            if trimmed.starts_with("//") {
+                self.skip(trimmed.find('\n').unwrap_or(trimmed.len()));
+                continue;
+            }
+            if trimmed.is_empty() {
+                return None;
+            }
+            let len = if trimmed.starts_with("::=") {
+                3
+            } else if trimmed.starts_with(|c| "|;()*+?".contains(c)) {
+                1
+            } else if let Some(literal) = trimmed.strip_prefix('"') {
+                literal.find('"').map_or(trimmed.len(), |end| end + 2)
+            } else {
+                trimmed.find(|c: char| c.is_whitespace() || "|;()*+?\"".contains(c)).unwrap_or(trimmed.len())
+            };
+            let line = self.line;
+            let word = &trimmed[..len];
+            self.skip(len);
+            return Some((line, word));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grammar::{Grammar, GrammarError, Symbol};
+    use snl_utils::token::TokenType;
+
+    #[test]
+    fn test_desugar() {
+        let grammar = Grammar::parse("// list\nList ::= BR_OPEN ( INTC ( COMMA INTC )* )? BR_CLOSE;\nINTC ::= \"(\" digit+;").unwrap();
+        let productions: Vec<_> = (0..grammar.productions.len()).map(|i| grammar.production_string(i)).collect();
+        assert_eq!(productions, vec![
+            "List' ::= List",
+            "List#1 ::= List#1 COMMA INTC",
+            "List#1 ::= ε",
+            "List#2 ::= INTC List#1",
+            "List#2 ::= ε",
+            "List ::= BR_OPEN List#2 BR_CLOSE",
+        ]);
+        let list = grammar.non_terminal("List").unwrap();
+        assert!(!grammar.non_terminals[list].generated);
+        assert!(grammar.is_nullable(grammar.non_terminal("List#2").unwrap()));
+        assert_eq!(grammar.first(list).iter().copied().collect::<Vec<_>>(), vec![TokenType::BracketOpen]);
+        assert_eq!(
+            grammar.follow(grammar.non_terminal("List#1").unwrap()).iter().copied().collect::<Vec<_>>(),
+            vec![TokenType::Comma, TokenType::BracketClose],
+        );
+        assert_eq!(grammar.productions[0].rhs, vec![Symbol::NonTerminal(list)]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(Grammar::parse("Start ::= Colon;\nColon ::= COLON;").unwrap_err(), GrammarError::UnknownTerminal { line: 2, name: "COLON".to_owned() });
+        assert_eq!(Grammar::parse("Start ::= Colon;").unwrap_err(), GrammarError::UndefinedNonTerminal { name: "Colon".to_owned() });
+        assert_eq!(Grammar::parse("Start ::= ( INTC;").unwrap_err(), GrammarError::UnexpectedWord { line: 1, word: ";".to_owned() });
+        assert_eq!(Grammar::parse("Start ::= INTC\nOther ::= INTC;").unwrap_err(), GrammarError::UnterminatedRule { name: "Start".to_owned() });
+        assert_eq!(Grammar::parse("Start ::= INTC;\n\nStart ::= ;").unwrap_err(), GrammarError::DuplicateRule { line: 3, name: "Start".to_owned() });
+        assert_eq!(Grammar::parse("INTC ::= digit+;").unwrap_err(), GrammarError::Empty);
+    }
+}
@@ -0,0 +1,7 @@
+pub use parser::Parser;
+pub use table::{Action, Conflict, Method, Table};
+
+mod build;
+pub mod grammar;
+mod parser;
+mod table;
@@ -0,0 +1,134 @@
+use crate::build::{Builder, Tree};
+use crate::grammar::Grammar;
+use crate::table::{Action, Method, Table};
+use snl_utils::ast::{Positional, Program};
+use snl_utils::error::SyntaxError;
+use snl_utils::token::{Token, TokenType};
+
+pub struct Parser {
+    tokens: Vec<Token>,
+    grammar: Grammar,
+    table: Table,
+}
+
+impl Parser {
+    /// Parser driven by the LALR(1) tables of the SNL grammar
+    pub fn new(tokens: Vec<Token>) -> Self {
+        let grammar = Grammar::snl();
+        let table = Table::new(&grammar, Method::Lalr);
+        Self { tokens, grammar, table }
+    }
+
+    /// Build the tables with another method, the SNL grammar is SLR(1) as well
+    pub fn with_method(mut self, method: Method) -> Self {
+        if self.table.method != method {
+            self.table = Table::new(&self.grammar, method);
+        }
+        self
+    }
+
+    /// Parse the program, failing with the first syntax error.
+    /// Nothing may follow the final `.` of the program.
+    pub fn parse(&self) -> Result<Positional<Program>, Positional<SyntaxError>> {
+        let (grammar, table) = (&self.grammar, &self.table);
+        let mut states = vec![0];
+        let mut values: Vec<Tree> = Vec::new();
+        let mut position = 0;
+        loop {
+            let token = self.tokens.get(position);
+            let lookahead = token.map_or(TokenType::EOF, |token| token.token_type);
+            let state = *states.last().unwrap();
+            let action = match table.action(state, lookahead) {
+                Some(action) => action,
+                None => {
+                    let error = SyntaxError::unexpected(&table.expected(state), lookahead);
+                    return Err(match token.or_else(|| self.tokens.last()) {
+                        Some(found) => Positional::from_token(found, error),
+                        None => Positional::dump(error),
+                    });
+                }
+            };
+            match action {
+                Action::Shift(target) => {
+                    states.push(target);
+                    values.push(Tree::Token(token.unwrap().clone()));
+                    position += 1;
+                }
+                Action::Reduce(production) => {
+                    let production = &grammar.productions[production];
+                    let start = values.len() - production.rhs.len();
+                    states.truncate(states.len() - production.rhs.len());
+                    let mut children = Vec::with_capacity(production.rhs.len());
+                    for value in values.drain(start..) {
+                        match value {
+                            Tree::Node { non_terminal, children: merged } if grammar.non_terminals[non_terminal].generated => {
+                                children.extend(merged)
+                            }
+                            value => children.push(value),
+                        }
+                    }
+                    values.push(Tree::Node { non_terminal: production.lhs, children });
+                    states.push(table.goto(*states.last().unwrap(), production.lhs).unwrap());
+                }
+                Action::Accept => break,
+            }
+        }
+        Builder::new(grammar).program(&values[0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Method, Parser};
+    use snl_utils::error::SyntaxError;
+    use snl_utils::token::TokenType;
+
+    fn parse_json(parser: &str, source: &str) -> String {
+        let tokens = snl_lexer::read_tokens(source).unwrap();
+        match parser {
+            "rdp" => serde_json::to_string(&snl_rdp::Parser::new(tokens).parse().unwrap()).unwrap(),
+            "slr" => serde_json::to_string(&Parser::new(tokens).with_method(Method::Slr).parse().unwrap()).unwrap(),
+            _ => serde_json::to_string(&Parser::new(tokens).parse().unwrap()).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_same_ast_as_rdp() {
+        for source in &[
+            include_str!("../../examples/program1.snl"),
+            include_str!("../../examples/bubble.snl"),
+            "program p\ntype t = record integer a, b; array [1..3] of char c; end;\n     u = t;\nvar t r; u s;\n    array [0..9] of integer n;\nbegin\n  r.c[2] := n[(r.a + 1) * 2 - 3 / s.b];\n  if r.a < 2 then read(s) else return(1) fi;\n  while n[0] = 1 do write(n[0]) endwh\nend.",
+        ] {
+            let expected = parse_json("rdp", source);
+            assert_eq!(parse_json("lalr", source), expected);
+            assert_eq!(parse_json("slr", source), expected);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let parse = |source: &str| Parser::new(snl_lexer::read_tokens(source).unwrap()).parse().unwrap_err();
+        let error = parse("program p\nbegin\n  a := ;\n  b := \nend.");
+        assert_eq!(error.position(), (3, 8));
+        assert_eq!(error.inner(), &SyntaxError::UnexpectedToken {
+            expected: vec![TokenType::Identifier, TokenType::Int, TokenType::BracketOpen],
+            found: TokenType::Semicolon,
+        });
+
+        let error = parse("program p\nbegin\n  write(4294967296)\nend.");
+        assert_eq!(error.position(), (3, 9));
+        // the error spans the whole literal
+        assert_eq!((error.span.end.line, error.span.end.column), (3, 19));
+        assert_eq!(error.inner(), &SyntaxError::IntegerOverflow("4294967296".to_owned()));
+
+        let error = parse("program p\nbegin\n  write(1)");
+        assert!(matches!(error.inner(), SyntaxError::UnexpectedEof { expected } if expected.contains(&TokenType::End)));
+
+        let error = parse("program p\nbegin\n  write(1)\nend");
+        assert!(matches!(error.inner(), SyntaxError::UnexpectedEof { expected } if expected.contains(&TokenType::Dot)));
+
+        let error = parse("program p\nbegin\n  write(1)\nend. garbage");
+        assert_eq!(error.position(), (4, 6));
+        assert_eq!(error.inner(), &SyntaxError::UnexpectedToken { expected: vec![TokenType::EOF], found: TokenType::Identifier });
+    }
+}
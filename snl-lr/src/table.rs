@@ -0,0 +1,366 @@
+use crate::grammar::{Grammar, Symbol};
+use snl_utils::token::TokenType;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{Display, Formatter, Write};
+
+/// How the lookaheads of reductions are chosen
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Method {
+    /// Reduce on FOLLOW of the non-terminal
+    Slr,
+    /// Reduce on the lookaheads of the LR(1) items merged into the state
+    Lalr,
+}
+
+impl Display for Method {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Method::Slr => "SLR(1)",
+            Method::Lalr => "LALR(1)",
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Push the token and go to the state
+    Shift(usize),
+    /// Replace the right-hand side of the production on the stack with its left-hand side
+    Reduce(usize),
+    Accept,
+}
+
+/// Two actions of a state on the same terminal, the first one is kept in the table
+#[derive(Debug, PartialEq)]
+pub struct Conflict {
+    pub state: usize,
+    pub terminal: TokenType,
+    pub actions: (Action, Action),
+}
+
+impl Conflict {
+    pub fn is_shift_reduce(&self) -> bool {
+        matches!(self.actions, (Action::Shift(_), _) | (_, Action::Shift(_)))
+    }
+}
+
+/// Production with a dot before the symbol at `dot`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Item {
+    pub production: usize,
+    pub dot: usize,
+}
+
+/// LR(1) item, `None` is the placeholder lookahead used to find propagated lookaheads
+type LookaheadItem = (Item, Option<TokenType>);
+
+/// ACTION and GOTO tables over the canonical collection of LR(0) item sets
+#[derive(Debug)]
+pub struct Table {
+    pub method: Method,
+    /// Kernel items of each state
+    states: Vec<Vec<Item>>,
+    transitions: Vec<BTreeMap<Symbol, usize>>,
+    action: Vec<BTreeMap<TokenType, Action>>,
+    conflicts: Vec<Conflict>,
+}
+
+impl Table {
+    /// Tables of the grammar, conflicts are resolved in favour of shifting and then of the
+    /// production defined first, and are kept to be reported
+    pub fn new(grammar: &Grammar, method: Method) -> Self {
+        let mut table = Table {
+            method,
+            states: vec![vec![Item { production: 0, dot: 0 }]],
+            transitions: Vec::new(),
+            action: Vec::new(),
+            conflicts: Vec::new(),
+        };
+        table.build_states(grammar);
+
+        let reductions = match method {
+            Method::Slr => table.slr_reductions(grammar),
+            Method::Lalr => table.lalr_reductions(grammar),
+        };
+        for (state, reductions) in reductions.into_iter().enumerate() {
+            let shifts: Vec<_> = table.transitions[state]
+                .iter()
+                .filter_map(|(symbol, target)| match symbol {
+                    Symbol::Terminal(terminal) => Some((*terminal, Action::Shift(*target))),
+                    Symbol::NonTerminal(_) => None,
+                })
+                .collect();
+            let mut row = BTreeMap::new();
+            for (terminal, action) in shifts.into_iter().chain(reductions) {
+                match row.get(&terminal) {
+                    None => {
+                        row.insert(terminal, action);
+                    }
+                    Some(kept) if *kept != action => {
+                        let kept = *kept;
+                        let actions = match (kept, action) {
+                            (Action::Reduce(first), Action::Reduce(second)) if second < first => {
+                                row.insert(terminal, action);
+                                (action, kept)
+                            }
+                            _ => (kept, action),
+                        };
+                        table.conflicts.push(Conflict { state, terminal, actions });
+                    }
+                    Some(_) => {}
+                }
+            }
+            table.action.push(row);
+        }
+        table
+    }
+
+    pub fn action(&self, state: usize, terminal: TokenType) -> Option<Action> {
+        self.action[state].get(&terminal).copied()
+    }
+
+    pub fn goto(&self, state: usize, non_terminal: usize) -> Option<usize> {
+        self.transitions[state].get(&Symbol::NonTerminal(non_terminal)).copied()
+    }
+
+    /// Terminals with an action in the state
+    pub fn expected(&self, state: usize) -> Vec<TokenType> {
+        self.action[state].keys().copied().collect()
+    }
+
+    pub fn states(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+
+    /// Closure of a set of LR(0) items
+    fn closure(grammar: &Grammar, kernel: &[Item]) -> BTreeSet<Item> {
+        let mut items: BTreeSet<Item> = kernel.iter().copied().collect();
+        let mut pending = kernel.to_vec();
+        while let Some(item) = pending.pop() {
+            if let Some(Symbol::NonTerminal(non_terminal)) = grammar.productions[item.production].rhs.get(item.dot) {
+                for production in grammar.productions_of(*non_terminal) {
+                    let item = Item { production, dot: 0 };
+                    if items.insert(item) {
+                        pending.push(item);
+                    }
+                }
+            }
+        }
+        items
+    }
+
+    /// Closure of a set of LR(1) items
+    fn closure_lookahead(grammar: &Grammar, kernel: &[LookaheadItem]) -> BTreeSet<LookaheadItem> {
+        let mut items: BTreeSet<LookaheadItem> = kernel.iter().copied().collect();
+        let mut pending = kernel.to_vec();
+        while let Some((item, lookahead)) = pending.pop() {
+            let rhs = &grammar.productions[item.production].rhs;
+            if let Some(Symbol::NonTerminal(non_terminal)) = rhs.get(item.dot) {
+                let (first, nullable) = grammar.first_of(&rhs[item.dot + 1..]);
+                let mut lookaheads: Vec<_> = first.into_iter().map(Some).collect();
+                if nullable {
+                    lookaheads.push(lookahead);
+                }
+                for production in grammar.productions_of(*non_terminal) {
+                    for lookahead in lookaheads.iter() {
+                        let item = (Item { production, dot: 0 }, *lookahead);
+                        if items.insert(item) {
+                            pending.push(item);
+                        }
+                    }
+                }
+            }
+        }
+        items
+    }
+
+    /// Canonical collection of LR(0) item sets and the transitions between them
+    fn build_states(&mut self, grammar: &Grammar) {
+        let mut index: HashMap<Vec<Item>, usize> = HashMap::new();
+        index.insert(self.states[0].clone(), 0);
+        let mut state = 0;
+        while state < self.states.len() {
+            let mut kernels: BTreeMap<Symbol, Vec<Item>> = BTreeMap::new();
+            for item in Self::closure(grammar, &self.states[state]) {
+                if let Some(symbol) = grammar.productions[item.production].rhs.get(item.dot) {
+                    kernels.entry(*symbol).or_default().push(Item { dot: item.dot + 1, ..item });
+                }
+            }
+            let mut transitions = BTreeMap::new();
+            for (symbol, kernel) in kernels {
+                let target = match index.get(&kernel) {
+                    Some(target) => *target,
+                    None => {
+                        self.states.push(kernel.clone());
+                        index.insert(kernel, self.states.len() - 1);
+                        self.states.len() - 1
+                    }
+                };
+                transitions.insert(symbol, target);
+            }
+            self.transitions.push(transitions);
+            state += 1;
+        }
+    }
+
+    /// Reductions of the completed items among items with the terminals they are reduced on
+    fn reductions(grammar: &Grammar, items: impl Iterator<Item = (Item, TokenType)>) -> Vec<(TokenType, Action)> {
+        items
+            .filter(|(item, _)| item.dot == grammar.productions[item.production].rhs.len())
+            .map(|(item, terminal)| match item.production {
+                0 => (terminal, Action::Accept),
+                production => (terminal, Action::Reduce(production)),
+            })
+            .collect()
+    }
+
+    fn slr_reductions(&self, grammar: &Grammar) -> Vec<Vec<(TokenType, Action)>> {
+        (0..self.states.len())
+            .map(|state| {
+                let items = Self::closure(grammar, &self.states[state]).into_iter().flat_map(|item| {
+                    let lhs = grammar.productions[item.production].lhs;
+                    grammar.follow(lhs).iter().map(move |terminal| (item, *terminal))
+                });
+                Self::reductions(grammar, items)
+            })
+            .collect()
+    }
+
+    /// Lookaheads of the kernel items found by propagating them between states,
+    /// then those of the other items from the closure of the kernel
+    fn lalr_reductions(&self, grammar: &Grammar) -> Vec<Vec<(TokenType, Action)>> {
+        let mut lookaheads: HashMap<(usize, Item), BTreeSet<TokenType>> = HashMap::new();
+        let mut propagate: HashMap<(usize, Item), Vec<(usize, Item)>> = HashMap::new();
+        lookaheads.entry((0, self.states[0][0])).or_default().insert(TokenType::EOF);
+        for (state, kernel) in self.states.iter().enumerate() {
+            for item in kernel.iter() {
+                for (closed, lookahead) in Self::closure_lookahead(grammar, &[(*item, None)]) {
+                    let symbol = match grammar.productions[closed.production].rhs.get(closed.dot) {
+                        Some(symbol) => symbol,
+                        None => continue,
+                    };
+                    let target = (self.transitions[state][symbol], Item { dot: closed.dot + 1, ..closed });
+                    match lookahead {
+                        Some(terminal) => {
+                            lookaheads.entry(target).or_default().insert(terminal);
+                        }
+                        None => propagate.entry((state, *item)).or_default().push(target),
+                    }
+                }
+            }
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (from, targets) in propagate.iter() {
+                let set = match lookaheads.get(from) {
+                    Some(set) => set.clone(),
+                    None => continue,
+                };
+                for target in targets.iter() {
+                    let target = lookaheads.entry(*target).or_default();
+                    let len = target.len();
+                    target.extend(set.iter().copied());
+                    changed |= target.len() != len;
+                }
+            }
+        }
+
+        (0..self.states.len())
+            .map(|state| {
+                let kernel: Vec<_> = self.states[state]
+                    .iter()
+                    .flat_map(|item| lookaheads.get(&(state, *item)).into_iter().flatten().map(move |terminal| (*item, Some(*terminal))))
+                    .collect();
+                let items = Self::closure_lookahead(grammar, &kernel).into_iter().filter_map(|(item, terminal)| Some((item, terminal?)));
+                Self::reductions(grammar, items)
+            })
+            .collect()
+    }
+
+    /// Number of states and the conflicts with the items of their states
+    pub fn report(&self, grammar: &Grammar) -> String {
+        let mut out = format!("{} states by {}\n", self.states.len(), self.method);
+        if self.conflicts.is_empty() {
+            return out;
+        }
+        out.push_str("\nCONFLICTS\n");
+        for conflict in self.conflicts.iter() {
+            let kind = if conflict.is_shift_reduce() { "shift/reduce" } else { "reduce/reduce" };
+            writeln!(out, "    {} in state {} on {}:", kind, conflict.state, conflict.terminal.grammar_name()).unwrap();
+            for action in [conflict.actions.0, conflict.actions.1].iter() {
+                match action {
+                    Action::Shift(target) => writeln!(out, "        shift to state {}", target).unwrap(),
+                    Action::Reduce(production) => writeln!(out, "        reduce {}", grammar.production_string(*production)).unwrap(),
+                    Action::Accept => writeln!(out, "        accept").unwrap(),
+                }
+            }
+            for item in self.states[conflict.state].iter() {
+                writeln!(out, "        {}", item_string(grammar, *item)).unwrap();
+            }
+        }
+        out
+    }
+}
+
+/// The item written as `Lhs ::= A . b`
+fn item_string(grammar: &Grammar, item: Item) -> String {
+    let production = &grammar.productions[item.production];
+    let mut out = format!("{} ::=", grammar.name(production.lhs));
+    for (i, symbol) in production.rhs.iter().enumerate() {
+        if i == item.dot {
+            out.push_str(" .");
+        }
+        write!(out, " {}", grammar.symbol_name(*symbol)).unwrap();
+    }
+    if item.dot == production.rhs.len() {
+        out.push_str(" .");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grammar::Grammar;
+    use crate::table::{Action, Method, Table};
+    use snl_utils::token::TokenType;
+
+    #[test]
+    fn test_snl_tables() {
+        let grammar = Grammar::snl();
+        for method in [Method::Slr, Method::Lalr].iter() {
+            let table = Table::new(&grammar, *method);
+            assert_eq!(table.conflicts(), &[]);
+            assert_eq!(table.action(0, TokenType::Program), Some(Action::Shift(1)));
+        }
+    }
+
+    #[test]
+    fn test_lalr_is_stronger() {
+        // the classic grammar which is LALR(1) but not SLR(1)
+        let grammar = Grammar::parse("Start ::= Left EQ Right | Right;\nLeft ::= MUL Right | IDENTIFIER;\nRight ::= Left;").unwrap();
+        let slr = Table::new(&grammar, Method::Slr);
+        assert_eq!(slr.conflicts().len(), 1);
+        assert!(slr.conflicts()[0].is_shift_reduce());
+        assert_eq!(slr.conflicts()[0].terminal, TokenType::Equal);
+        assert!(slr.report(&grammar).contains("    shift/reduce in state 4 on EQ:\n        shift to state 8\n        reduce Right ::= Left\n"));
+        let lalr = Table::new(&grammar, Method::Lalr);
+        assert_eq!(lalr.conflicts(), &[]);
+        assert_eq!(lalr.states(), slr.states());
+    }
+
+    #[test]
+    fn test_reduce_reduce() {
+        let grammar = Grammar::parse("Start ::= Alpha | Beta;\nAlpha ::= INTC;\nBeta ::= INTC;").unwrap();
+        let table = Table::new(&grammar, Method::Lalr);
+        assert_eq!(table.conflicts().len(), 1);
+        let conflict = &table.conflicts()[0];
+        assert!(!conflict.is_shift_reduce());
+        assert_eq!(conflict.actions, (Action::Reduce(3), Action::Reduce(4)));
+        assert_eq!(table.action(conflict.state, TokenType::EOF), Some(Action::Reduce(3)));
+    }
+}
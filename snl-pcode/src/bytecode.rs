@@ -0,0 +1,139 @@
+use crate::error::Error;
+use crate::instruction::Instruction;
+use std::fmt::{Display, Formatter};
+
+const MAGIC: &[u8; 4] = b"SNLB";
+const VERSION: u16 = 1;
+
+/// Compiled program, serialised as a `.snlb` file:
+///
+/// ```text
+/// magic     "SNLB"
+/// version   u16
+/// name      u32 length, UTF-8 bytes
+/// constants u32 count, i64 each
+/// code      u32 count, opcode followed by its operands each
+/// lines     u32 count, (u32 pc, u32 line, u32 column) each
+/// ```
+///
+/// All integers are little endian.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bytecode {
+    pub name: String,
+    pub constants: Vec<i64>,
+    pub code: Vec<Instruction>,
+    /// Source position of the instructions starting at `pc`, sorted by `pc`
+    pub lines: Vec<(u32, u32, u32)>,
+}
+
+impl Bytecode {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+
+        out.extend_from_slice(&(self.name.len() as u32).to_le_bytes());
+        out.extend_from_slice(self.name.as_bytes());
+
+        out.extend_from_slice(&(self.constants.len() as u32).to_le_bytes());
+        for constant in self.constants.iter() {
+            out.extend_from_slice(&constant.to_le_bytes());
+        }
+
+        out.extend_from_slice(&(self.code.len() as u32).to_le_bytes());
+        for instruction in self.code.iter() {
+            instruction.encode(&mut out);
+        }
+
+        out.extend_from_slice(&(self.lines.len() as u32).to_le_bytes());
+        for (pc, line, column) in self.lines.iter() {
+            out.extend_from_slice(&pc.to_le_bytes());
+            out.extend_from_slice(&line.to_le_bytes());
+            out.extend_from_slice(&column.to_le_bytes());
+        }
+        out
+    }
+
+    pub fn from_bytes(mut input: &[u8]) -> Result<Self, Error> {
+        let input = &mut input;
+        if take(input, 4)? != MAGIC {
+            return Err(Error::InvalidMagic);
+        }
+        let version = take(input, 2)?;
+        let version = u16::from_le_bytes([version[0], version[1]]);
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+
+        let length = take_u32(input)? as usize;
+        let name = String::from_utf8(take(input, length)?.to_vec())
+            .map_err(|_| Error::Malformed("program name is not UTF-8"))?;
+
+        let count = take_u32(input)?;
+        let mut constants = Vec::new();
+        for _ in 0..count {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(take(input, 8)?);
+            constants.push(i64::from_le_bytes(bytes));
+        }
+
+        let count = take_u32(input)?;
+        let mut code = Vec::new();
+        for _ in 0..count {
+            code.push(Instruction::decode(input)?);
+        }
+
+        let count = take_u32(input)?;
+        let mut lines = Vec::new();
+        for _ in 0..count {
+            lines.push((take_u32(input)?, take_u32(input)?, take_u32(input)?));
+        }
+
+        if !input.is_empty() {
+            return Err(Error::Malformed("trailing bytes after line table"));
+        }
+        Ok(Bytecode { name, constants, code, lines })
+    }
+
+    /// Source position of the instruction at `pc`
+    pub fn position(&self, pc: usize) -> (u32, u32) {
+        match self.lines.iter().rev().find(|(start, _, _)| *start as usize <= pc) {
+            Some((_, line, column)) => (*line, *column),
+            None => (0, 0),
+        }
+    }
+}
+
+fn take<'a>(input: &mut &'a [u8], n: usize) -> Result<&'a [u8], Error> {
+    if input.len() < n {
+        return Err(Error::UnexpectedEndOfFile);
+    }
+    let (bytes, rest) = input.split_at(n);
+    *input = rest;
+    Ok(bytes)
+}
+
+fn take_u32(input: &mut &[u8]) -> Result<u32, Error> {
+    let bytes = take(input, 4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Disassembly listing
+impl Display for Bytecode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "program {}", self.name)?;
+        writeln!(f, "constants:")?;
+        for (i, constant) in self.constants.iter().enumerate() {
+            writeln!(f, "    #{}\t{}", i, constant)?;
+        }
+        writeln!(f, "code:")?;
+        let mut lines = self.lines.iter().peekable();
+        for (pc, instruction) in self.code.iter().enumerate() {
+            while let Some((_, line, column)) = lines.next_if(|(start, _, _)| *start as usize == pc) {
+                writeln!(f, "    ; line {}, column {}", line, column)?;
+            }
+            writeln!(f, "    {:>4}\t{}", pc, instruction)?;
+        }
+        Ok(())
+    }
+}
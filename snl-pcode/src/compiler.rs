@@ -0,0 +1,538 @@
+use crate::bytecode::Bytecode;
+use crate::instruction::Instruction;
+use snl_ir::scope::{self, Binding, Result, Scope};
+use snl_ir::{ScalarType, Type};
+use snl_utils::ast::*;
+use snl_utils::options::CodegenOptions;
+use std::collections::HashMap;
+
+/// Cells between the frame base and the first local: static link, dynamic link, return address
+const FRAME_HEADER: i32 = 3;
+
+/// Variable in the frame of a procedure at `level`
+struct Variable {
+    level: usize,
+    offset: i32,
+    is_var: bool,
+    ty: Type,
+}
+
+/// Procedure whose own frame is at `level`, code starts at `entry`
+struct Procedure {
+    level: usize,
+    entry: u32,
+    params: Vec<(bool, Type)>,
+}
+
+/// Where the value of a variable represent lives
+enum Access {
+    /// Frame cell addressed directly by instructions
+    Direct { depth: u32, offset: i32 },
+    /// Cell whose address is on top of the stack
+    Stack,
+}
+
+struct Compiler {
+    scope: Scope<Variable, Procedure>,
+    code: Vec<Instruction>,
+    constants: Vec<i64>,
+    constant_index: HashMap<i64, u32>,
+    lines: Vec<(u32, u32, u32)>,
+    options: CodegenOptions,
+    /// Nesting level of the block being compiled, 0 for the main program
+    level: usize,
+    /// Instruction leaving the block being compiled
+    leave: Instruction,
+}
+
+/// Compile a semantically checked program into stack machine code
+pub fn compile(program: &Positional<Program>, options: CodegenOptions) -> Result<Bytecode> {
+    let mut compiler = Compiler {
+        scope: Default::default(),
+        code: Default::default(),
+        constants: Default::default(),
+        constant_index: Default::default(),
+        lines: Default::default(),
+        options,
+        level: 0,
+        leave: Instruction::Halt,
+    };
+    compiler.compile_block(&program.declare, &program.body)?;
+    Ok(Bytecode {
+        name: program.name.clone(),
+        constants: compiler.constants,
+        code: compiler.code,
+        lines: compiler.lines,
+    })
+}
+
+impl Compiler {
+    fn pc(&self) -> u32 {
+        self.code.len() as u32
+    }
+
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.code.push(instruction);
+        self.code.len() - 1
+    }
+
+    /// Point a previously emitted jump to `target`
+    fn patch(&mut self, at: usize, target: u32) {
+        match &mut self.code[at] {
+            Instruction::Jump(t) | Instruction::JumpIfFalse(t) => *t = target,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Index of a value in the constant pool
+    fn pool(&mut self, value: i64) -> u32 {
+        let constants = &mut self.constants;
+        *self.constant_index.entry(value).or_insert_with(|| {
+            constants.push(value);
+            constants.len() as u32 - 1
+        })
+    }
+
+    fn constant(&mut self, value: i64) {
+        let index = self.pool(value);
+        self.emit(Instruction::Const(index));
+    }
+
+    /// Attribute following instructions to a source position
+    fn mark(&mut self, (line, column): (u32, u32)) {
+        let pc = self.pc();
+        if let Some(last) = self.lines.last_mut() {
+            if last.0 == pc {
+                *last = (pc, line, column);
+                return;
+            }
+        }
+        self.lines.push((pc, line, column));
+    }
+
+    fn compile_block(&mut self, declare: &ProgramDeclare, body: &StatementList) -> Result<()> {
+        for t in declare.type_declare.iter() {
+            let ty = self.scope.resolve_type(&t.base)?;
+            self.scope.insert(t.name().to_owned(), Binding::Type(ty));
+        }
+
+        let mut locals = 0;
+        for v in declare.variable_declare.iter() {
+            let ty = self.scope.resolve_type(&v.type_name)?;
+            for name in v.identifiers.iter() {
+                let size = ty.size() as i32;
+                self.scope.insert(name.inner().clone(), Binding::Variable(Variable {
+                    level: self.level,
+                    offset: FRAME_HEADER + locals,
+                    is_var: false,
+                    ty: ty.clone(),
+                }));
+                locals += size;
+            }
+        }
+
+        // nested procedures are placed before the body, the entry jumps over them
+        let jump = if declare.procedure_declare.is_empty() {
+            None
+        } else {
+            Some(self.emit(Instruction::Jump(0)))
+        };
+        for p in declare.procedure_declare.iter() {
+            self.compile_procedure(p)?;
+        }
+        if let Some(jump) = jump {
+            let pc = self.pc();
+            self.patch(jump, pc);
+        }
+
+        self.emit(Instruction::Enter(locals as u32));
+        self.compile_statement_list(body)?;
+        let leave = self.leave;
+        self.emit(leave);
+        Ok(())
+    }
+
+    fn compile_procedure(&mut self, p: &Positional<ProcedureDeclare>) -> Result<()> {
+        let mut params = Vec::new();
+        for param in p.params.iter() {
+            let ty = self.scope.resolve_type(&param.definition.type_name)?;
+            for name in param.definition.identifiers.iter() {
+                params.push((param.is_var, ty.clone(), name));
+            }
+        }
+        let params_size: i32 = params.iter()
+            .map(|(is_var, ty, _)| if *is_var { 1 } else { ty.size() as i32 })
+            .sum();
+
+        let entry = self.pc();
+        self.scope.insert(p.name().to_owned(), Binding::Procedure(Procedure {
+            level: self.level + 1,
+            entry,
+            params: params.iter().map(|(is_var, ty, _)| (*is_var, ty.clone())).collect(),
+        }));
+
+        self.scope.step_in();
+        self.level += 1;
+        let leave = std::mem::replace(&mut self.leave, Instruction::Return(params_size as u32));
+
+        // params are pushed by the caller right below the frame header
+        let mut offset = -params_size;
+        for (is_var, ty, name) in params {
+            let size = if is_var { 1 } else { ty.size() as i32 };
+            self.scope.insert(name.inner().clone(), Binding::Variable(Variable { level: self.level, offset, is_var, ty }));
+            offset += size;
+        }
+        self.mark(p.position());
+        self.compile_block(&p.declare, &p.body)?;
+
+        self.leave = leave;
+        self.level -= 1;
+        self.scope.step_out();
+        Ok(())
+    }
+
+    fn compile_statement_list(&mut self, list: &StatementList) -> Result<()> {
+        for statement in list.iter() {
+            self.mark(statement.position());
+            self.compile_statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn compile_statement(&mut self, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::Conditional(con) => {
+                let to_else = self.compile_condition(&con.condition)?;
+                self.compile_statement_list(&con.body)?;
+                let to_end = self.emit(Instruction::Jump(0));
+                let pc = self.pc();
+                self.patch(to_else, pc);
+                self.compile_statement_list(&con.else_body)?;
+                let pc = self.pc();
+                self.patch(to_end, pc);
+            }
+            Statement::Loop(lo) => {
+                let start = self.pc();
+                let to_end = self.compile_condition(&lo.condition)?;
+                self.compile_statement_list(&lo.body)?;
+                self.emit(Instruction::Jump(start));
+                let pc = self.pc();
+                self.patch(to_end, pc);
+            }
+            Statement::Input(input) => {
+                let repr = scope::input_variable(input);
+                let (access, ty) = self.compile_access(&repr)?;
+                match scope::scalar(&ty, input)? {
+                    ScalarType::Integer => self.emit(Instruction::ReadInt),
+                    ScalarType::Char => self.emit(Instruction::ReadChar),
+                };
+                self.store(access);
+            }
+            Statement::Output(output) => {
+                match self.compile_expression(output)? {
+                    ScalarType::Integer => self.emit(Instruction::WriteInt),
+                    ScalarType::Char => self.emit(Instruction::WriteChar),
+                };
+            }
+            Statement::Return(ret) => {
+                // the value is dropped together with the frame
+                self.compile_expression(ret)?;
+                let leave = self.leave;
+                self.emit(leave);
+            }
+            Statement::Assign(assign) => {
+                let (access, ty) = self.compile_access(&assign.variable)?;
+                if ty.scalar().is_some() {
+                    self.compile_expression(&assign.value)?;
+                    self.store(access);
+                } else {
+                    self.address(access);
+                    let (src, _) = self.compile_access(scope::structured_source(assign)?)?;
+                    self.address(src);
+                    self.emit(Instruction::CopyBlock(ty.size() as u32));
+                }
+            }
+            Statement::Call(call) => self.compile_call(call)?,
+            Statement::Error(error) => return Err(scope::syntax_error(error)),
+        }
+        Ok(())
+    }
+
+    fn compile_call(&mut self, call: &Positional<CallStatement>) -> Result<()> {
+        let Procedure { level, entry, params } = self.scope.procedure(call)?;
+        let (level, entry, params) = (*level, *entry, params.clone());
+
+        for ((is_var, ty), exp) in params.iter().zip(call.params.iter()) {
+            if *is_var || ty.scalar().is_none() {
+                let (access, _) = self.compile_access(scope::referenced_argument(exp, *is_var, call.name())?)?;
+                self.address(access);
+                if !*is_var {
+                    self.emit(Instruction::PushBlock(ty.size() as u32));
+                }
+            } else {
+                self.compile_expression(exp)?;
+            }
+        }
+        // the static link of the callee is the frame of its parent, at `level - 1`
+        self.emit(Instruction::Call { depth: (self.level + 1 - level) as u32, target: entry });
+        Ok(())
+    }
+
+    /// Emit a conditional jump taken when the relation does not hold, returns it for patching
+    fn compile_condition(&mut self, rel: &RelationExpression) -> Result<usize> {
+        self.compile_expression(&rel.left)?;
+        self.compile_expression(&rel.right)?;
+        match rel.op.as_str() {
+            "<" => self.emit(Instruction::Lt),
+            "=" => self.emit(Instruction::Eq),
+            _ => unreachable!(),
+        };
+        Ok(self.emit(Instruction::JumpIfFalse(0)))
+    }
+
+    fn compile_expression(&mut self, exp: &Expression) -> Result<ScalarType> {
+        match exp {
+            Expression::Binary(binary) => {
+                let left = self.compile_expression(&binary.left)?;
+                let right = self.compile_expression(&binary.right)?;
+                self.compile_operator(binary.op, binary.right.position());
+                Ok(result_type(left, right))
+            }
+            Expression::Factor(factor) => self.compile_factor(factor),
+        }
+    }
+
+    fn compile_operator(&mut self, op: BinaryOperator, position: (u32, u32)) {
+        match op {
+            BinaryOperator::Add => self.emit(Instruction::Add),
+            BinaryOperator::Sub => self.emit(Instruction::Sub),
+            BinaryOperator::Mul => self.emit(Instruction::Mul),
+            BinaryOperator::Div => {
+                // division by zero is reported at the divisor
+                self.mark(position);
+                self.emit(Instruction::Div)
+            }
+        };
+    }
+
+    fn compile_factor(&mut self, factor: &Positional<ExpressionFactor>) -> Result<ScalarType> {
+        match factor.inner() {
+            ExpressionFactor::Bracket(exp) => self.compile_expression(exp),
+            ExpressionFactor::Constant(c) => {
+                self.constant(*c as i64);
+                Ok(ScalarType::Integer)
+            }
+            ExpressionFactor::Variable(repr) => {
+                let (access, ty) = self.compile_access(repr)?;
+                let ty = scope::scalar(&ty, &repr.base)?;
+                self.load(access);
+                Ok(ty)
+            }
+        }
+    }
+
+    /// Resolve a variable represent, leaving its address on the stack unless accessed directly
+    fn compile_access(&mut self, repr: &VariableRepresent) -> Result<(Access, Type)> {
+        let Variable { level, offset, is_var, ty } = self.scope.variable(&repr.base)?;
+        let (depth, offset, is_var, mut ty) = ((self.level - level) as u32, *offset, *is_var, ty.clone());
+        let mut access = if is_var {
+            // the cell of a var parameter holds the address of the argument
+            self.emit(Instruction::Load { depth, offset });
+            Access::Stack
+        } else {
+            Access::Direct { depth, offset }
+        };
+
+        if let Some(visit) = &repr.visit {
+            if let Some(field) = &visit.dot {
+                let (offset, field_type) = scope::field(&ty, field)?;
+                let field_type = field_type.clone();
+                self.address(access);
+                if offset != 0 {
+                    self.constant(offset as i64);
+                    self.emit(Instruction::Add);
+                }
+                access = Access::Stack;
+                ty = field_type;
+            }
+
+            if let Some(index) = &visit.sqbr {
+                let (base, lower_bound, upper_bound) = scope::element(&ty, repr, index)?;
+                self.address(access);
+                self.compile_expression(index)?;
+                if self.options.bounds_check && index.constant().is_none() {
+                    let lower = self.pool(lower_bound as i64);
+                    let upper = self.pool(upper_bound as i64);
+                    // out of bound indexes are reported at the index
+                    self.mark(index.position());
+                    self.emit(Instruction::Check { lower, upper });
+                }
+                if lower_bound != 0 {
+                    self.constant(lower_bound as i64);
+                    self.emit(Instruction::Sub);
+                }
+                self.emit(Instruction::Add);
+                access = Access::Stack;
+                ty = Type::Scalar(base);
+            }
+        }
+        Ok((access, ty))
+    }
+
+    /// Make sure the address of the access is on the stack
+    fn address(&mut self, access: Access) {
+        if let Access::Direct { depth, offset } = access {
+            self.emit(Instruction::Address { depth, offset });
+        }
+    }
+
+    fn load(&mut self, access: Access) {
+        match access {
+            Access::Direct { depth, offset } => self.emit(Instruction::Load { depth, offset }),
+            Access::Stack => self.emit(Instruction::LoadIndirect),
+        };
+    }
+
+    /// Store the value on top of the stack, an indirect address must be right below it
+    fn store(&mut self, access: Access) {
+        match access {
+            Access::Direct { depth, offset } => self.emit(Instruction::Store { depth, offset }),
+            Access::Stack => self.emit(Instruction::StoreIndirect),
+        };
+    }
+}
+
+fn result_type(left: ScalarType, right: ScalarType) -> ScalarType {
+    if left == ScalarType::Char && right == ScalarType::Char {
+        ScalarType::Char
+    } else {
+        ScalarType::Integer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{compile, Bytecode, Error, Instruction, Vm};
+    use snl_rdp::Parser;
+    use snl_utils::error::RuntimeError;
+    use snl_utils::options::CodegenOptions;
+
+    fn run(source: &str, input: &str) -> Result<String, Error> {
+        let tokens = snl_lexer::read_tokens(source).unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let bytecode = compile(&ast, Default::default()).unwrap();
+        // always execute what was read back from the file format
+        let bytecode = Bytecode::from_bytes(&bytecode.to_bytes()).unwrap();
+        let mut output = Vec::new();
+        Vm::new(&bytecode, input.as_bytes(), &mut output).run().map_err(|e| e.into_inner())?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_read_write() {
+        assert_eq!(run(include_str!("../../examples/program1.snl"), "5").unwrap(), "15\n");
+    }
+
+    #[test]
+    fn test_static_link() {
+        let output = run(r#"program nested
+type pair = record
+  integer first;
+  array [1..3] of char tag;
+end;
+var pair p, q;
+    array [2..4] of integer a;
+    integer offset;
+    char c;
+procedure outer(integer base);
+var integer offset;
+procedure inner(integer k);
+begin
+  a[k] := base + offset + k
+end
+procedure swap(var integer x, y);
+var integer t;
+begin
+  t := x;
+  x := y;
+  y := t
+end
+begin
+  offset := 100;
+  inner(2);
+  inner(4);
+  swap(a[2], a[4])
+end
+procedure show(pair r);
+begin
+  write(r.first);
+  write(r.tag[2])
+end
+begin
+  offset := 7;
+  outer(10);
+  read(c);
+  p.tag[2] := c;
+  p.first := a[4];
+  q := p;
+  show(q);
+  write(a[4]);
+  write(offset)
+end."#, "x").unwrap();
+        assert_eq!(output, "112\nx\n112\n7\n");
+    }
+
+    #[test]
+    fn test_runtime_error() {
+        let source = r#"program recursion
+var integer n;
+procedure down(integer k);
+begin
+  write(10 / k);
+  down(k - 1)
+end
+begin
+  read(n);
+  down(n)
+end."#;
+        let tokens = snl_lexer::read_tokens(source).unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let bytecode = compile(&ast, Default::default()).unwrap();
+        let mut output = Vec::new();
+        let error = Vm::new(&bytecode, "2".as_bytes(), &mut output).run().unwrap_err();
+        assert!(matches!(error.inner(), Error::Runtime(RuntimeError::DivideByZero)));
+        assert_eq!(error.position(), (5, 14));
+        assert_eq!(String::from_utf8(output).unwrap(), "5\n10\n");
+
+        let error = Vm::new(&bytecode, "-1".as_bytes(), Vec::new()).with_max_stack(1000).run().unwrap_err();
+        assert!(matches!(error.inner(), Error::Runtime(RuntimeError::StackOverflow(1000))));
+    }
+
+    #[test]
+    fn test_bounds_check() {
+        let source = r#"program bounds
+var array [1..3] of integer a;
+    integer i;
+begin
+  read(i);
+  a[i] := 1;
+  write(a[3])
+end."#;
+        let tokens = snl_lexer::read_tokens(source).unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let unchecked = compile(&ast, Default::default()).unwrap();
+        assert!(!unchecked.code.iter().any(|i| matches!(i, Instruction::Check { .. })));
+
+        let bytecode = compile(&ast, CodegenOptions { bounds_check: true }).unwrap();
+        // the constant index is not checked
+        assert_eq!(bytecode.code.iter().filter(|i| matches!(i, Instruction::Check { .. })).count(), 1);
+        let error = Vm::new(&bytecode, "4".as_bytes(), Vec::new()).run().unwrap_err();
+        assert!(matches!(error.inner(), Error::Runtime(RuntimeError::ArrayIndexOutbound(4, 1, 3))));
+        assert_eq!(error.position(), (6, 5));
+
+        let bytecode = Bytecode::from_bytes(&bytecode.to_bytes()).unwrap();
+        let mut output = Vec::new();
+        Vm::new(&bytecode, "1".as_bytes(), &mut output).run().unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "0\n");
+    }
+}
@@ -0,0 +1,43 @@
+use snl_utils::diagnostic::Diagnose;
+use snl_utils::error::RuntimeError;
+
+/// Failure of loading or running bytecode
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// File does not start with the bytecode magic
+    #[error("Not a SNL bytecode file")]
+    InvalidMagic,
+    /// File was written by an incompatible version
+    #[error("Unsupported bytecode version {0}")]
+    UnsupportedVersion(u16),
+    /// Unknown instruction opcode
+    #[error("Invalid opcode {0:#04x}")]
+    InvalidOpcode(u8),
+    /// File ended in the middle of a section
+    #[error("Unexpected end of bytecode file")]
+    UnexpectedEndOfFile,
+    /// Malformed content, e.g. a name which is not UTF-8
+    #[error("Malformed bytecode: {0}")]
+    Malformed(&'static str),
+
+    /// Jump, call or memory access outside of the program
+    #[error("Invalid address {0}")]
+    InvalidAddress(i64),
+    /// Failure of the program itself, as reported by the interpreter
+    #[error(transparent)]
+    Runtime(#[from] RuntimeError),
+}
+
+impl Diagnose for Error {
+    fn code(&self) -> &'static str {
+        match self {
+            Error::InvalidMagic => "E0601",
+            Error::UnsupportedVersion(_) => "E0602",
+            Error::InvalidOpcode(_) => "E0603",
+            Error::UnexpectedEndOfFile => "E0604",
+            Error::Malformed(_) => "E0605",
+            Error::InvalidAddress(_) => "E0606",
+            Error::Runtime(error) => error.code(),
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use crate::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// Instruction of the stack machine.
+///
+/// A frame is laid out as `[params..][static link][dynamic link][return address][locals..]`,
+/// the frame base points to the static link, so params have negative offsets and
+/// locals start at offset 3. `depth` counts static links followed from the current frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    /// Push an entry of the constant pool
+    Const(u32),
+    /// Push the address of a frame cell
+    Address { depth: u32, offset: i32 },
+    /// Push the content of a frame cell
+    Load { depth: u32, offset: i32 },
+    /// Pop a value into a frame cell
+    Store { depth: u32, offset: i32 },
+    /// `[addr] -> [*addr]`
+    LoadIndirect,
+    /// `[addr, value] -> []`, stores value at addr
+    StoreIndirect,
+    /// `[addr] -> [cells..]`, pushes `n` cells starting at addr
+    PushBlock(u32),
+    /// `[dst, src] -> []`, copies `n` cells from src to dst
+    CopyBlock(u32),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    /// `[index] -> [index]`, stops unless the index is between the constants
+    /// at pool entries `lower` and `upper`
+    Check { lower: u32, upper: u32 },
+    /// `[a, b] -> [a < b]`
+    Lt,
+    /// `[a, b] -> [a = b]`
+    Eq,
+    Jump(u32),
+    /// Pop a value and jump if it is zero
+    JumpIfFalse(u32),
+    /// Push static link, dynamic link and return address, then jump to target
+    Call { depth: u32, target: u32 },
+    /// Reserve `n` zeroed cells for locals
+    Enter(u32),
+    /// Leave the frame and drop `n` cells of params
+    Return(u32),
+    ReadInt,
+    ReadChar,
+    WriteInt,
+    WriteChar,
+    Halt,
+}
+
+impl Instruction {
+    fn opcode(&self) -> u8 {
+        match self {
+            Instruction::Const(_) => 0x01,
+            Instruction::Address { .. } => 0x02,
+            Instruction::Load { .. } => 0x03,
+            Instruction::Store { .. } => 0x04,
+            Instruction::LoadIndirect => 0x05,
+            Instruction::StoreIndirect => 0x06,
+            Instruction::PushBlock(_) => 0x07,
+            Instruction::CopyBlock(_) => 0x08,
+            Instruction::Add => 0x10,
+            Instruction::Sub => 0x11,
+            Instruction::Mul => 0x12,
+            Instruction::Div => 0x13,
+            Instruction::Lt => 0x14,
+            Instruction::Eq => 0x15,
+            Instruction::Check { .. } => 0x16,
+            Instruction::Jump(_) => 0x20,
+            Instruction::JumpIfFalse(_) => 0x21,
+            Instruction::Call { .. } => 0x22,
+            Instruction::Enter(_) => 0x23,
+            Instruction::Return(_) => 0x24,
+            Instruction::ReadInt => 0x30,
+            Instruction::ReadChar => 0x31,
+            Instruction::WriteInt => 0x32,
+            Instruction::WriteChar => 0x33,
+            Instruction::Halt => 0xff,
+        }
+    }
+
+    /// Append the opcode and little endian operands
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        out.push(self.opcode());
+        match *self {
+            Instruction::Const(n)
+            | Instruction::PushBlock(n)
+            | Instruction::CopyBlock(n)
+            | Instruction::Jump(n)
+            | Instruction::JumpIfFalse(n)
+            | Instruction::Enter(n)
+            | Instruction::Return(n) => out.extend_from_slice(&n.to_le_bytes()),
+            Instruction::Address { depth, offset }
+            | Instruction::Load { depth, offset }
+            | Instruction::Store { depth, offset } => {
+                out.extend_from_slice(&depth.to_le_bytes());
+                out.extend_from_slice(&offset.to_le_bytes());
+            }
+            Instruction::Call { depth, target } => {
+                out.extend_from_slice(&depth.to_le_bytes());
+                out.extend_from_slice(&target.to_le_bytes());
+            }
+            Instruction::Check { lower, upper } => {
+                out.extend_from_slice(&lower.to_le_bytes());
+                out.extend_from_slice(&upper.to_le_bytes());
+            }
+            _ => {}
+        }
+    }
+
+    /// Decode one instruction, advancing `input` past it
+    pub fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+        fn u32(input: &mut &[u8]) -> Result<u32, Error> {
+            if input.len() < 4 {
+                return Err(Error::UnexpectedEndOfFile);
+            }
+            let (bytes, rest) = input.split_at(4);
+            *input = rest;
+            Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        }
+
+        let (&opcode, rest) = input.split_first().ok_or(Error::UnexpectedEndOfFile)?;
+        *input = rest;
+        Ok(match opcode {
+            0x01 => Instruction::Const(u32(input)?),
+            0x02 => Instruction::Address { depth: u32(input)?, offset: u32(input)? as i32 },
+            0x03 => Instruction::Load { depth: u32(input)?, offset: u32(input)? as i32 },
+            0x04 => Instruction::Store { depth: u32(input)?, offset: u32(input)? as i32 },
+            0x05 => Instruction::LoadIndirect,
+            0x06 => Instruction::StoreIndirect,
+            0x07 => Instruction::PushBlock(u32(input)?),
+            0x08 => Instruction::CopyBlock(u32(input)?),
+            0x10 => Instruction::Add,
+            0x11 => Instruction::Sub,
+            0x12 => Instruction::Mul,
+            0x13 => Instruction::Div,
+            0x14 => Instruction::Lt,
+            0x15 => Instruction::Eq,
+            0x16 => Instruction::Check { lower: u32(input)?, upper: u32(input)? },
+            0x20 => Instruction::Jump(u32(input)?),
+            0x21 => Instruction::JumpIfFalse(u32(input)?),
+            0x22 => Instruction::Call { depth: u32(input)?, target: u32(input)? },
+            0x23 => Instruction::Enter(u32(input)?),
+            0x24 => Instruction::Return(u32(input)?),
+            0x30 => Instruction::ReadInt,
+            0x31 => Instruction::ReadChar,
+            0x32 => Instruction::WriteInt,
+            0x33 => Instruction::WriteChar,
+            0xff => Instruction::Halt,
+            op => return Err(Error::InvalidOpcode(op)),
+        })
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instruction::Const(n) => write!(f, "const #{}", n),
+            Instruction::Address { depth, offset } => write!(f, "addr {}, {}", depth, offset),
+            Instruction::Load { depth, offset } => write!(f, "load {}, {}", depth, offset),
+            Instruction::Store { depth, offset } => write!(f, "store {}, {}", depth, offset),
+            Instruction::LoadIndirect => write!(f, "loadi"),
+            Instruction::StoreIndirect => write!(f, "storei"),
+            Instruction::PushBlock(n) => write!(f, "pushb {}", n),
+            Instruction::CopyBlock(n) => write!(f, "copyb {}", n),
+            Instruction::Add => write!(f, "add"),
+            Instruction::Sub => write!(f, "sub"),
+            Instruction::Mul => write!(f, "mul"),
+            Instruction::Div => write!(f, "div"),
+            Instruction::Lt => write!(f, "lt"),
+            Instruction::Eq => write!(f, "eq"),
+            Instruction::Check { lower, upper } => write!(f, "check #{}, #{}", lower, upper),
+            Instruction::Jump(target) => write!(f, "jmp {}", target),
+            Instruction::JumpIfFalse(target) => write!(f, "jpf {}", target),
+            Instruction::Call { depth, target } => write!(f, "call {}, {}", depth, target),
+            Instruction::Enter(n) => write!(f, "enter {}", n),
+            Instruction::Return(n) => write!(f, "ret {}", n),
+            Instruction::ReadInt => write!(f, "readi"),
+            Instruction::ReadChar => write!(f, "readc"),
+            Instruction::WriteInt => write!(f, "writei"),
+            Instruction::WriteChar => write!(f, "writec"),
+            Instruction::Halt => write!(f, "halt"),
+        }
+    }
+}
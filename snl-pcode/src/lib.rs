@@ -0,0 +1,11 @@
+pub use bytecode::Bytecode;
+pub use compiler::compile;
+pub use error::Error;
+pub use instruction::Instruction;
+pub use vm::Vm;
+
+mod bytecode;
+mod compiler;
+mod error;
+mod instruction;
+mod vm;
@@ -0,0 +1,230 @@
+use crate::bytecode::Bytecode;
+use crate::error::Error;
+use snl_utils::error::RuntimeError;
+use crate::instruction::Instruction;
+use snl_utils::ast::Positional;
+use snl_utils::input::{read_char, read_integer};
+use std::io::{BufRead, Write};
+
+/// Default limit of the stack, in cells
+const MAX_STACK: usize = 1 << 20;
+
+/// Stack machine executing compiled bytecode
+pub struct Vm<'b, R: BufRead, W: Write> {
+    bytecode: &'b Bytecode,
+    input: R,
+    output: W,
+    stack: Vec<i64>,
+    max_stack: usize,
+    pc: usize,
+    /// Base of the current frame, pointing to its static link
+    bp: usize,
+}
+
+impl<'b, R: BufRead, W: Write> Vm<'b, R, W> {
+    pub fn new(bytecode: &'b Bytecode, input: R, output: W) -> Self {
+        Vm {
+            bytecode,
+            input,
+            output,
+            stack: Vec::new(),
+            max_stack: MAX_STACK,
+            pc: 0,
+            bp: 0,
+        }
+    }
+
+    /// Limit the number of cells on the stack
+    pub fn with_max_stack(mut self, max_stack: usize) -> Self {
+        self.max_stack = max_stack;
+        self
+    }
+
+    pub fn run(&mut self) -> Result<(), Positional<Error>> {
+        // the main program frame has an empty header
+        self.stack.clear();
+        self.stack.extend_from_slice(&[0, 0, 0]);
+        self.pc = 0;
+        self.bp = 0;
+
+        loop {
+            match self.step() {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(e) => return Err(Positional::from_position(self.bytecode.position(self.pc), e)),
+            }
+        }
+        self.output.flush().map_err(|e| Positional::from_position((0, 0), RuntimeError::Io(e).into()))
+    }
+
+    /// Execute one instruction, returns false after halt
+    fn step(&mut self) -> Result<bool, Error> {
+        let instruction = *self.bytecode.code.get(self.pc).ok_or(Error::InvalidAddress(self.pc as i64))?;
+        let mut next = self.pc + 1;
+        match instruction {
+            Instruction::Const(index) => {
+                let value = self.constant(index)?;
+                self.push(value)?;
+            }
+            Instruction::Address { depth, offset } => {
+                let address = self.base(depth)? as i64 + offset as i64;
+                self.push(address)?;
+            }
+            Instruction::Load { depth, offset } => {
+                let address = self.base(depth)? as i64 + offset as i64;
+                let value = self.stack[self.address(address)?];
+                self.push(value)?;
+            }
+            Instruction::Store { depth, offset } => {
+                let value = self.pop()?;
+                let address = self.base(depth)? as i64 + offset as i64;
+                let address = self.address(address)?;
+                self.stack[address] = value;
+            }
+            Instruction::LoadIndirect => {
+                let address = self.pop()?;
+                let value = self.stack[self.address(address)?];
+                self.push(value)?;
+            }
+            Instruction::StoreIndirect => {
+                let value = self.pop()?;
+                let address = self.pop()?;
+                let address = self.address(address)?;
+                self.stack[address] = value;
+            }
+            Instruction::PushBlock(n) => {
+                let address = self.pop()?;
+                let start = self.address(address)?;
+                self.block(start, n)?;
+                for i in start..start + n as usize {
+                    self.push(self.stack[i])?;
+                }
+            }
+            Instruction::CopyBlock(n) => {
+                let src = self.pop()?;
+                let dst = self.pop()?;
+                let (src, dst) = (self.address(src)?, self.address(dst)?);
+                self.block(src, n)?;
+                self.block(dst, n)?;
+                self.stack.copy_within(src..src + n as usize, dst);
+            }
+            Instruction::Add => self.binary(|a, b| Ok(a.wrapping_add(b)))?,
+            Instruction::Sub => self.binary(|a, b| Ok(a.wrapping_sub(b)))?,
+            Instruction::Mul => self.binary(|a, b| Ok(a.wrapping_mul(b)))?,
+            Instruction::Div => self.binary(|a, b| {
+                if b == 0 {
+                    Err(RuntimeError::DivideByZero.into())
+                } else {
+                    Ok(a.wrapping_div(b))
+                }
+            })?,
+            Instruction::Check { lower, upper } => {
+                let lower = self.constant(lower)?;
+                let upper = self.constant(upper)?;
+                let index = *self.stack.last().ok_or(Error::Malformed("stack underflow"))?;
+                if index < lower || index > upper {
+                    return Err(RuntimeError::ArrayIndexOutbound(index, lower, upper).into());
+                }
+            }
+            Instruction::Lt => self.binary(|a, b| Ok((a < b) as i64))?,
+            Instruction::Eq => self.binary(|a, b| Ok((a == b) as i64))?,
+            Instruction::Jump(target) => next = target as usize,
+            Instruction::JumpIfFalse(target) => {
+                if self.pop()? == 0 {
+                    next = target as usize;
+                }
+            }
+            Instruction::Call { depth, target } => {
+                let static_link = self.base(depth)? as i64;
+                self.push(static_link)?;
+                self.push(self.bp as i64)?;
+                self.push(next as i64)?;
+                self.bp = self.stack.len() - 3;
+                next = target as usize;
+            }
+            Instruction::Enter(n) => {
+                if self.stack.len() + n as usize > self.max_stack {
+                    return Err(RuntimeError::StackOverflow(self.max_stack).into());
+                }
+                self.stack.resize(self.stack.len() + n as usize, 0);
+            }
+            Instruction::Return(n) => {
+                let bp = self.bp;
+                let dynamic_link = self.stack[self.address(bp as i64 + 1)?];
+                let return_address = self.stack[self.address(bp as i64 + 2)?];
+                self.stack.truncate(bp.checked_sub(n as usize).ok_or(Error::Malformed("stack underflow"))?);
+                self.bp = self.address(dynamic_link)?;
+                next = return_address as usize;
+            }
+            Instruction::ReadInt => {
+                let value = read_integer(&mut self.input).map_err(|e| RuntimeError::from_input(e, "integer"))?;
+                self.push(value)?;
+            }
+            Instruction::ReadChar => {
+                let value = read_char(&mut self.input).map_err(|e| RuntimeError::from_input(e, "char"))?;
+                self.push(value as i64)?;
+            }
+            Instruction::WriteInt => {
+                let value = self.pop()?;
+                writeln!(self.output, "{}", value).map_err(RuntimeError::from)?;
+            }
+            Instruction::WriteChar => {
+                let value = self.pop()?;
+                let c = std::char::from_u32(value as u32).unwrap_or(std::char::REPLACEMENT_CHARACTER);
+                writeln!(self.output, "{}", c).map_err(RuntimeError::from)?;
+            }
+            Instruction::Halt => return Ok(false),
+        }
+        self.pc = next;
+        Ok(true)
+    }
+
+    fn constant(&self, index: u32) -> Result<i64, Error> {
+        self.bytecode.constants.get(index as usize).copied().ok_or(Error::Malformed("constant index out of pool"))
+    }
+
+    fn push(&mut self, value: i64) -> Result<(), Error> {
+        if self.stack.len() >= self.max_stack {
+            return Err(RuntimeError::StackOverflow(self.max_stack).into());
+        }
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<i64, Error> {
+        self.stack.pop().ok_or(Error::Malformed("stack underflow"))
+    }
+
+    fn binary<F>(&mut self, f: F) -> Result<(), Error>
+        where F: Fn(i64, i64) -> Result<i64, Error> {
+        let right = self.pop()?;
+        let left = self.pop()?;
+        self.push(f(left, right)?)
+    }
+
+    /// Check that an address points into the stack
+    fn address(&self, address: i64) -> Result<usize, Error> {
+        if address < 0 || address as usize >= self.stack.len() {
+            Err(Error::InvalidAddress(address))
+        } else {
+            Ok(address as usize)
+        }
+    }
+
+    /// Check that `n` cells starting at `start` are on the stack
+    fn block(&self, start: usize, n: u32) -> Result<(), Error> {
+        if n > 0 {
+            self.address((start + n as usize - 1) as i64)?;
+        }
+        Ok(())
+    }
+
+    /// Frame base reached by following `depth` static links
+    fn base(&self, depth: u32) -> Result<usize, Error> {
+        let mut base = self.bp;
+        for _ in 0..depth {
+            base = self.address(self.stack[base])?;
+        }
+        Ok(base)
+    }
+}
@@ -1,197 +1,386 @@
 use snl_utils::token::{Token, TokenType};
 use snl_utils::tokens::Tokens;
 use snl_utils::ast::*;
+use snl_utils::error::SyntaxError;
+use snl_utils::span::Span;
+use std::cell::RefCell;
 use std::str::FromStr;
 
+/// Tokens a broken statement is skipped to before parsing goes on
+const STATEMENT_SYNC: [TokenType; 7] = [
+    TokenType::Semicolon,
+    TokenType::End,
+    TokenType::Else,
+    TokenType::Fi,
+    TokenType::EndWhile,
+    TokenType::Begin,
+    TokenType::Procedure,
+];
+
+/// Tokens a broken declaration is skipped to before parsing goes on
+const DECLARE_SYNC: [TokenType; 5] = [
+    TokenType::Semicolon,
+    TokenType::Type,
+    TokenType::Var,
+    TokenType::Procedure,
+    TokenType::Begin,
+];
+
+/// Tokens a statement starts with
+const STATEMENT_START: [TokenType; 6] = [
+    TokenType::If,
+    TokenType::While,
+    TokenType::Read,
+    TokenType::Write,
+    TokenType::Return,
+    TokenType::Identifier,
+];
+
+/// Tokens which may follow the program head
+const HEAD_SYNC: [TokenType; 4] = [
+    TokenType::Type,
+    TokenType::Var,
+    TokenType::Procedure,
+    TokenType::Begin,
+];
+
 pub struct Parser {
     inner: Tokens,
+    errors: RefCell<Vec<Positional<SyntaxError>>>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
         Self {
             inner: Tokens::new(tokens),
+            errors: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Parse the whole program, failing with the first syntax error found.
+    /// Use `parse_with_errors` to get all of them.
+    pub fn parse(&self) -> Result<Positional<Program>, Positional<SyntaxError>> {
+        let (program, errors) = self.parse_with_errors();
+        match errors.into_iter().next() {
+            None => Ok(program),
+            Some(error) => Err(error),
+        }
+    }
+
+    /// Parse the whole program with panic-mode recovery.
+    ///
+    /// Statements that fail to parse are kept as `Statement::Error` in the returned AST,
+    /// and every syntax error is returned in source order.
+    pub fn parse_with_errors(&self) -> (Positional<Program>, Vec<Positional<SyntaxError>>) {
+        let program = self.parse_program();
+        (program, self.errors.replace(Vec::new()))
+    }
+
+    /// Record a syntax error at the current token.
+    /// Errors at the same token as the last one are consequences of it and dropped.
+    fn report(&self, error: SyntaxError) {
+        let token = self.inner.current_token();
+        let mut errors = self.errors.borrow_mut();
+        if errors.last().map(|e| e.position()) != Some(token.position()) {
+            errors.push(Positional::from_token(token, error));
+        }
+    }
+
+    /// Skip tokens until one of `sync` or the end of file
+    fn skip_until(&self, sync: &[TokenType]) {
+        while self.inner.current() != TokenType::EOF && !sync.contains(&self.inner.current()) {
+            self.inner.move_next();
+        }
+    }
+
+    /// Report the error and skip to the next synchronising token
+    fn recover(&self, error: SyntaxError, sync: &[TokenType]) {
+        self.report(error);
+        self.skip_until(sync);
+    }
+
+    /// Report the error of a statement starting at token `start` and skip to the next
+    /// synchronising token outside of the `if` and `while` opened by the broken statement.
+    fn recover_statement(&self, error: SyntaxError, start: usize) {
+        self.report(error);
+        let mut depth = 0;
+        for token in self.inner.consumed_since(start) {
+            match token.token_type {
+                TokenType::If | TokenType::While => depth += 1,
+                TokenType::Fi | TokenType::EndWhile => depth -= 1,
+                _ => {}
+            }
+        }
+        loop {
+            match self.inner.current() {
+                TokenType::EOF => break,
+                // never part of a statement
+                TokenType::End | TokenType::Begin | TokenType::Procedure => break,
+                TokenType::If | TokenType::While => depth += 1,
+                TokenType::Fi | TokenType::EndWhile if depth > 0 => depth -= 1,
+                t if depth <= 0 && STATEMENT_SYNC.contains(&t) => break,
+                _ => {}
+            }
+            self.inner.move_next();
         }
     }
 
-    pub fn parse(&self) -> Result<Positional<Program>, String> {
-        self.parse_program()
+    /// Skip the `;` a declaration was recovered to, returns whether there is one
+    fn recovered_to_semicolon(&self) -> bool {
+        let found = TokenType::Semicolon == self.inner.current();
+        if found {
+            self.inner.move_next();
+        }
+        found
+    }
+
+    /// `inner` spanning from `start` to the last token taken
+    fn spanned<T>(&self, start: Span, inner: T) -> Positional<T> {
+        let end = self.inner.previous_token().span;
+        if end.end.offset < start.start.offset {
+            // nothing is taken after `start`
+            Positional::from_span(start, inner)
+        } else {
+            Positional::from_span(start.to(end), inner)
+        }
     }
 
-    fn parse_program(&self) -> Result<Positional<Program>, String> {
-        let name = self.parse_program_head()?;
-        let declare = self.parse_declare_part()?;
-        let body = self.parse_program_body()?;
-        Ok(Positional::from_position(name.position(), Program {
+    /// Take an integer literal, an error is reported at the literal if it does not fit in `T`
+    fn take_integer<T: FromStr>(&self) -> Result<T, SyntaxError> {
+        let token = self.inner.current_token();
+        if TokenType::Int != token.token_type {
+            return Err(SyntaxError::unexpected(&[TokenType::Int], token.token_type));
+        }
+        let value = T::from_str(&token.image).map_err(|_| SyntaxError::IntegerOverflow(token.image.clone()))?;
+        self.inner.move_next();
+        Ok(value)
+    }
+
+    fn parse_program(&self) -> Positional<Program> {
+        let start = self.inner.current_token().span;
+        let name = match self.parse_program_head() {
+            Ok(name) => name,
+            Err(e) => {
+                self.recover(e, &HEAD_SYNC);
+                Positional::from_span(start, String::new())
+            }
+        };
+        let declare = self.parse_declare_part();
+        let body = self.parse_program_body();
+        // nothing may follow the final `.`
+        if let Err(e) = self.inner.take(TokenType::Dot) {
+            self.report(e);
+        } else if TokenType::EOF != self.inner.current() {
+            self.report(SyntaxError::unexpected(&[TokenType::EOF], self.inner.current()));
+        }
+        self.spanned(name.span, Program {
             name: name.into_inner(),
             declare,
             body,
-        }))
+        })
     }
 
-    fn parse_program_head(&self) -> Result<Positional<String>, String> {
+    fn parse_program_head(&self) -> Result<Positional<String>, SyntaxError> {
         let program = self.inner.take(TokenType::Program)?;
         let program_name = self.inner.take(TokenType::Identifier)?.image.clone();
         Ok(Positional::from_token(program, program_name))
     }
 
-    fn parse_declare_part(&self) -> Result<ProgramDeclare, String> {
+    fn parse_declare_part(&self) -> ProgramDeclare {
         let type_declare = if TokenType::Type == self.inner.current() {
-            self.parse_declare_type()?
+            self.parse_declare_type()
         } else {
             Default::default()
         };
 
         let variable_declare = if TokenType::Var == self.inner.current() {
-            self.parse_declare_var()?
+            self.parse_declare_var()
         } else {
             Default::default()
         };
 
         let procedure_declare = if TokenType::Procedure == self.inner.current() {
-            self.parse_declare_procedure()?
+            self.parse_declare_procedure()
         } else {
             Default::default()
         };
 
-        Ok(ProgramDeclare {
+        ProgramDeclare {
             type_declare,
             variable_declare,
             procedure_declare,
-        })
+        }
     }
 
-    fn parse_declare_type(&self) -> Result<PositionalVec<TypeDeclare>, String> {
+    fn parse_declare_type(&self) -> PositionalVec<TypeDeclare> {
         let mut declare = Vec::new();
-        self.inner.take(TokenType::Type)?;
+        self.inner.move_next();
         loop {
-            let name = self.inner.take(TokenType::Identifier)?;
-            self.inner.take(TokenType::Equal)?;
-            let inner_type = self.parse_type_name(true)?;
-            self.inner.take(TokenType::Semicolon)?;
-            declare.push(Positional::from_token(name, TypeDeclare {
-                base: inner_type,
-                name: name.image.clone(),
-            }));
+            // a declaration is kept even if the `;` after it is missing
+            let parsed = self.parse_type_declare().and_then(|type_declare| {
+                declare.push(type_declare);
+                self.inner.take(TokenType::Semicolon)
+            });
+            if let Err(e) = parsed {
+                self.recover(e, &DECLARE_SYNC);
+                if !self.recovered_to_semicolon() {
+                    break;
+                }
+            }
             if TokenType::Identifier != self.inner.current() {
                 break;
             }
         }
-        Ok(declare)
+        declare
     }
 
-    fn parse_declare_var(&self) -> Result<PositionalVec<TypedIdentifiers>, String> {
+    fn parse_type_declare(&self) -> Result<Positional<TypeDeclare>, SyntaxError> {
+        let name = self.inner.take(TokenType::Identifier)?;
+        self.inner.take(TokenType::Equal)?;
+        let inner_type = self.parse_type_name(true)?;
+        Ok(Positional::from_token(name, TypeDeclare {
+            base: inner_type,
+            name: name.image.clone(),
+        }))
+    }
+
+    fn parse_declare_var(&self) -> PositionalVec<TypedIdentifiers> {
         let mut result = PositionalVec::new();
-        self.inner.take(TokenType::Var)?;
+        self.inner.move_next();
         loop {
-            let type_name = self.parse_type_name(true)?;
-            let ids = self.parse_identifier_list()?;
-            self.inner.take(TokenType::Semicolon)?;
-            result.push(Positional::from_position(
-                type_name.position(),
-                TypedIdentifiers {
-                    type_name,
-                    identifiers: ids,
-                },
-            ));
+            let parsed = self.parse_var_declare().and_then(|var_declare| {
+                result.push(var_declare);
+                self.inner.take(TokenType::Semicolon)
+            });
+            if let Err(e) = parsed {
+                self.recover(e, &DECLARE_SYNC);
+                if !self.recovered_to_semicolon() {
+                    break;
+                }
+            }
 
             if self.inner.current() == TokenType::Procedure || self.inner.current() == TokenType::Begin {
                 break;
             }
         }
-        Ok(result)
+        result
+    }
+
+    fn parse_var_declare(&self) -> Result<Positional<TypedIdentifiers>, SyntaxError> {
+        let type_name = self.parse_type_name(true)?;
+        let ids = self.parse_identifier_list()?;
+        Ok(self.spanned(type_name.span, TypedIdentifiers {
+            type_name,
+            identifiers: ids,
+        }))
     }
 
-    fn parse_declare_procedure(&self) -> Result<PositionalVec<ProcedureDeclare>, String> {
+    fn parse_declare_procedure(&self) -> PositionalVec<ProcedureDeclare> {
         let mut result = PositionalVec::new();
         loop {
-            self.inner.take(TokenType::Procedure)?;
-            let name = self.inner.take(TokenType::Identifier)?;
-            self.inner.take(TokenType::BracketOpen)?;
-            let params = self.parse_param_list()?;
-            self.inner.take(TokenType::BracketClose)?;
-            self.inner.take(TokenType::Semicolon)?;
-            let declare = self.parse_declare_part()?;
-            let body = self.parse_program_body()?;
-            result.push(Positional::from_token(name, ProcedureDeclare {
-                name: name.image.clone(),
-                params,
-                declare: Box::new(declare),
-                body,
-            }));
+            self.inner.move_next();
+            let head = self.parse_procedure_head();
+            if let Err(e) = &head {
+                self.recover(e.clone(), &DECLARE_SYNC);
+                self.recovered_to_semicolon();
+            }
+            // declarations and body of a broken procedure are still checked
+            let declare = self.parse_declare_part();
+            let body = self.parse_program_body();
+            if let Ok((name, params)) = head {
+                result.push(Positional::from_token(name, ProcedureDeclare {
+                    name: name.image.clone(),
+                    params,
+                    declare: Box::new(declare),
+                    body,
+                }));
+            }
             if TokenType::Procedure != self.inner.current() {
                 break;
             }
         }
-        Ok(result)
+        result
+    }
+
+    fn parse_procedure_head(&self) -> Result<(&Token, PositionalVec<Param>), SyntaxError> {
+        let name = self.inner.take(TokenType::Identifier)?;
+        self.inner.take(TokenType::BracketOpen)?;
+        let params = self.parse_param_list()?;
+        self.inner.take(TokenType::BracketClose)?;
+        self.inner.take(TokenType::Semicolon)?;
+        Ok((name, params))
     }
 
-    fn parse_type_name(&self, full: bool) -> Result<Positional<SNLType>, String> {
+    fn parse_type_name(&self, full: bool) -> Result<Positional<SNLType>, SyntaxError> {
         let next = self.inner.current();
-        let next_pos = (self.inner.current_token().line, self.inner.current_token().column);
+        let start = self.inner.current_token().span;
         match next {
             TokenType::Integer => {
                 self.inner.move_next();
-                return Ok(Positional::from_position(next_pos, SNLType::Integer));
+                return Ok(self.spanned(start, SNLType::Integer));
             }
             TokenType::Char => {
                 self.inner.move_next();
-                return Ok(Positional::from_position(next_pos, SNLType::Char));
+                return Ok(self.spanned(start, SNLType::Char));
             }
             TokenType::Array => {
-                return Ok(Positional::from_position(next_pos, SNLType::Array(self.parse_array_type()?)));
+                let array = self.parse_array_type()?;
+                return Ok(self.spanned(start, SNLType::Array(array)));
             }
             _ => {}
         }
         if full {
             match next {
                 TokenType::Record => {
-                    return Ok(Positional::from_position(next_pos, SNLType::Record(self.parse_record_type()?)));
+                    let record = self.parse_record_type()?;
+                    return Ok(self.spanned(start, SNLType::Record(record)));
                 }
                 TokenType::Identifier => {
                     let name = self.inner.current_token().image.clone();
                     self.inner.move_next();
-                    return Ok(Positional::from_position(next_pos, SNLType::Others(name)));
+                    return Ok(self.spanned(start, SNLType::Others(name)));
                 }
                 _ => {}
             }
         }
-        Err(format!("unexpected token {:?}", next))
+        let expected: &[TokenType] = if full {
+            &[TokenType::Integer, TokenType::Char, TokenType::Array, TokenType::Record, TokenType::Identifier]
+        } else {
+            &[TokenType::Integer, TokenType::Char, TokenType::Array]
+        };
+        Err(SyntaxError::unexpected(expected, next))
     }
 
-    fn parse_array_type(&self) -> Result<SNLTypeArray, String> {
+    fn parse_array_type(&self) -> Result<SNLTypeArray, SyntaxError> {
         self.inner.take(TokenType::Array)?;
         self.inner.take(TokenType::SquareBracketOpen)?;
-        let low = self.inner.take(TokenType::Int)?.image.as_str();
+        let lower_bound = self.take_integer()?;
         self.inner.take(TokenType::DotDot)?;
-        let top = self.inner.take(TokenType::Int)?.image.as_str();
+        let upper_bound = self.take_integer()?;
         self.inner.take(TokenType::SquareBracketClose)?;
         self.inner.take(TokenType::Of)?;
         let base = self.inner.current();
         let base = match base {
             TokenType::Integer => SNLBaseType::Integer,
             TokenType::Char => SNLBaseType::Char,
-            t => {
-                let token = self.inner.current_token();
-                return Err(format!("unexpected base type {:?} at line {}, column {}", t, token.line, token.column));
-            }
+            t => return Err(SyntaxError::unexpected(&[TokenType::Integer, TokenType::Char], t)),
         };
         self.inner.move_next();
         Ok(SNLTypeArray {
             base,
-            lower_bound: usize::from_str(low).unwrap(),
-            upper_bound: usize::from_str(top).unwrap(),
+            lower_bound,
+            upper_bound,
         })
     }
 
-    fn parse_record_type(&self) -> Result<SNLTypeRecord, String> {
+    fn parse_record_type(&self) -> Result<SNLTypeRecord, SyntaxError> {
         let mut records = Vec::new();
         self.inner.take(TokenType::Record)?;
         loop {
             let type_name = self.parse_type_name(false)?;
             let identifiers = self.parse_identifier_list()?;
             self.inner.take(TokenType::Semicolon)?;
-            records.push(TypedIdentifiers { type_name: type_name, identifiers });
+            records.push(TypedIdentifiers { type_name, identifiers });
 
             match self.inner.current() {
                 TokenType::Integer | TokenType::Char | TokenType::Array => {}
@@ -202,7 +391,7 @@ impl Parser {
         Ok(records)
     }
 
-    fn parse_identifier_list(&self) -> Result<PositionalVec<String>, String> {
+    fn parse_identifier_list(&self) -> Result<PositionalVec<String>, SyntaxError> {
         let mut ids = PositionalVec::new();
         let mut need_comma = false;
         loop {
@@ -218,7 +407,7 @@ impl Parser {
                 }
                 TokenType::Comma => {
                     if !need_comma {
-                        return Err(format!("unexpected ','"));
+                        return Err(SyntaxError::unexpected(&[TokenType::Identifier], TokenType::Comma));
                     } else {
                         need_comma = false;
                         self.inner.move_next();
@@ -227,29 +416,50 @@ impl Parser {
                 _ => break,
             }
         }
+        if !need_comma {
+            // empty list or trailing ','
+            return Err(SyntaxError::unexpected(&[TokenType::Identifier], self.inner.current()));
+        }
         Ok(ids)
     }
 
-    fn parse_program_body(&self) -> Result<StatementList, String> {
-        self.inner.take(TokenType::Begin)?;
-        let body = self.parse_statement_list()?;
-        self.inner.take(TokenType::End)?;
-        Ok(body)
+    /// A missing `begin` or `end` is reported and parsing goes on as if it were there
+    fn parse_program_body(&self) -> StatementList {
+        if let Err(e) = self.inner.take(TokenType::Begin) {
+            self.report(e);
+        }
+        let body = self.parse_statement_list();
+        if let Err(e) = self.inner.take(TokenType::End) {
+            self.report(e);
+        }
+        body
     }
 
-    fn parse_statement_list(&self) -> Result<StatementList, String> {
+    fn parse_statement_list(&self) -> StatementList {
         let mut statements = Vec::new();
-        let statement = self.parse_statement()?.ok_or(format!("empty statement list"))?;
-        statements.push(statement);
-        while TokenType::Semicolon == self.inner.current() {
-            let pos = self.inner.take(TokenType::Semicolon)?;
-            let statement = self.parse_statement()?.ok_or(format!("statement expected near line {}, column {}", pos.line, pos.column))?;
-            statements.push(statement);
+        loop {
+            let start = self.inner.index();
+            let start_span = self.inner.current_token().span;
+            let statement = self.parse_statement().and_then(|statement| {
+                statement.ok_or_else(|| SyntaxError::unexpected(&STATEMENT_START, self.inner.current()))
+            });
+            match statement {
+                Ok(statement) => statements.push(statement),
+                Err(e) => {
+                    let message = e.to_string();
+                    self.recover_statement(e, start);
+                    statements.push(Statement::Error(self.spanned(start_span, message)));
+                }
+            }
+            if TokenType::Semicolon != self.inner.current() {
+                break;
+            }
+            self.inner.move_next();
         }
-        Ok(statements)
+        statements
     }
 
-    fn parse_statement(&self) -> Result<Option<Statement>, String> {
+    fn parse_statement(&self) -> Result<Option<Statement>, SyntaxError> {
         Ok(match self.inner.current() {
             TokenType::If => Some(self.parse_conditional_statement()?),
             TokenType::While => Some(self.parse_loop_statement()?),
@@ -260,20 +470,20 @@ impl Parser {
                 match self.inner.look_after() {
                     Some(TokenType::BracketOpen) => Some(self.parse_call_statement()?),
                     Some(_) => Some(self.parse_assign_statement()?),
-                    None => return Err(format!("unexpected EOF after statement Identifer")),
+                    None => return Err(SyntaxError::UnexpectedEof { expected: vec![TokenType::BracketOpen, TokenType::Assign] }),
                 }
             }
             _ => None,
         })
     }
 
-    fn parse_conditional_statement(&self) -> Result<Statement, String> {
+    fn parse_conditional_statement(&self) -> Result<Statement, SyntaxError> {
         self.inner.take(TokenType::If)?;
         let condition = self.parse_relation_expression()?;
         self.inner.take(TokenType::Then)?;
-        let body = self.parse_statement_list()?;
+        let body = self.parse_statement_list();
         self.inner.take(TokenType::Else)?;
-        let else_body = self.parse_statement_list()?;
+        let else_body = self.parse_statement_list();
         self.inner.take(TokenType::Fi)?;
         Ok(Statement::Conditional(ConditionalStatement {
             condition,
@@ -282,16 +492,16 @@ impl Parser {
         }))
     }
 
-    fn parse_loop_statement(&self) -> Result<Statement, String> {
+    fn parse_loop_statement(&self) -> Result<Statement, SyntaxError> {
         self.inner.take(TokenType::While)?;
         let condition = self.parse_relation_expression()?;
         self.inner.take(TokenType::Do)?;
-        let body = self.parse_statement_list()?;
+        let body = self.parse_statement_list();
         self.inner.take(TokenType::EndWhile)?;
         Ok(Statement::Loop(LoopStatement { condition, body }))
     }
 
-    fn parse_input_statement(&self) -> Result<Statement, String> {
+    fn parse_input_statement(&self) -> Result<Statement, SyntaxError> {
         self.inner.take(TokenType::Read)?;
         self.inner.take(TokenType::BracketOpen)?;
         let name = self.inner.take(TokenType::Identifier)?;
@@ -299,7 +509,7 @@ impl Parser {
         Ok(Statement::Input(Positional::from_token(name, name.image.clone())))
     }
 
-    fn parse_output_statement(&self) -> Result<Statement, String> {
+    fn parse_output_statement(&self) -> Result<Statement, SyntaxError> {
         self.inner.take(TokenType::Write)?;
         self.inner.take(TokenType::BracketOpen)?;
         let exp = self.parse_expression()?;
@@ -307,7 +517,7 @@ impl Parser {
         Ok(Statement::Output(exp))
     }
 
-    fn parse_return_statement(&self) -> Result<Statement, String> {
+    fn parse_return_statement(&self) -> Result<Statement, SyntaxError> {
         self.inner.take(TokenType::Return)?;
         self.inner.take(TokenType::BracketOpen)?;
         let exp = self.parse_expression()?;
@@ -315,7 +525,7 @@ impl Parser {
         Ok(Statement::Return(exp))
     }
 
-    fn parse_call_statement(&self) -> Result<Statement, String> {
+    fn parse_call_statement(&self) -> Result<Statement, SyntaxError> {
         let mut params = Vec::new();
         let name = self.inner.take(TokenType::Identifier)?;
         self.inner.take(TokenType::BracketOpen)?;
@@ -323,20 +533,20 @@ impl Parser {
             if TokenType::BracketClose == self.inner.current() {
                 break;
             }
-            if params.len() != 0 {
+            if !params.is_empty() {
                 self.inner.take(TokenType::Comma)?;
             }
             let param = self.parse_expression()?;
             params.push(param);
         }
         self.inner.take(TokenType::BracketClose)?;
-        Ok(Statement::Call(Positional::from_token(&name, CallStatement {
+        Ok(Statement::Call(self.spanned(name.span, CallStatement {
             name: name.image.clone(),
             params,
         })))
     }
 
-    fn parse_assign_statement(&self) -> Result<Statement, String> {
+    fn parse_assign_statement(&self) -> Result<Statement, SyntaxError> {
         let base = self.inner.take(TokenType::Identifier)?;
         let visit = self.parse_variable_visit()?;
         self.inner.take(TokenType::Assign)?;
@@ -350,7 +560,7 @@ impl Parser {
         }))
     }
 
-    fn parse_relation_expression(&self) -> Result<RelationExpression, String> {
+    fn parse_relation_expression(&self) -> Result<RelationExpression, SyntaxError> {
         let left = self.parse_expression()?;
         let op = match self.inner.current() {
             TokenType::LessThan | TokenType::Equal => {
@@ -358,10 +568,7 @@ impl Parser {
                 self.inner.move_next();
                 op
             }
-            t => {
-                let token = self.inner.current_token();
-                return Err(format!("unexpected token {:?} at line {}, column {}", t, token.line, token.column));
-            }
+            t => return Err(SyntaxError::unexpected(&[TokenType::LessThan, TokenType::Equal], t)),
         };
         let right = self.parse_expression()?;
         Ok(RelationExpression {
@@ -371,45 +578,31 @@ impl Parser {
         })
     }
 
-    fn parse_expression(&self) -> Result<Expression, String> {
-        let left = self.parse_term()?;
-        let (op, right) = match self.inner.current() {
-            TokenType::Add | TokenType::Minus => {
-                let op = self.inner.current_token().image.clone();
-                self.inner.move_next();
-                let right = self.parse_expression()?;
-                (Some(op), Some(Positional::from_position(right.left.position(), Box::new(right))))
-            }
-            _ => { (None, None) }
-        };
-        Ok(Expression {
-            left: Positional::from_position(left.left.position(), left),
-            op,
-            right,
-        })
+    fn parse_expression(&self) -> Result<Expression, SyntaxError> {
+        let mut left = self.parse_term()?;
+        // operators of the same precedence associate to the left
+        while let TokenType::Add | TokenType::Minus = self.inner.current() {
+            let op = self.inner.current_token().image.parse().unwrap();
+            self.inner.move_next();
+            let right = self.parse_term()?;
+            left = Expression::binary(left, op, right);
+        }
+        Ok(left)
     }
 
-    fn parse_term(&self) -> Result<ExpressionTerm, String> {
-        let left = self.parse_factor()?;
-        let (op, right) = match self.inner.current() {
-            TokenType::Multiply | TokenType::Divide => {
-                let op = self.inner.current_token().image.clone();
-                self.inner.move_next();
-                let right_token = self.inner.current_token();
-                let right = self.parse_term()?;
-                (Some(op), Some(Positional::from_token(right_token, Box::new(right))))
-            }
-            _ => { (None, None) }
-        };
-        Ok(ExpressionTerm {
-            left,
-            op,
-            right,
-        })
+    fn parse_term(&self) -> Result<Expression, SyntaxError> {
+        let mut left = Expression::Factor(self.parse_factor()?);
+        while let TokenType::Multiply | TokenType::Divide = self.inner.current() {
+            let op = self.inner.current_token().image.parse().unwrap();
+            self.inner.move_next();
+            let right = Expression::Factor(self.parse_factor()?);
+            left = Expression::binary(left, op, right);
+        }
+        Ok(left)
     }
 
-    fn parse_factor(&self) -> Result<Positional<ExpressionFactor>, String> {
-        let pos = self.inner.current_token().position();
+    fn parse_factor(&self) -> Result<Positional<ExpressionFactor>, SyntaxError> {
+        let start = self.inner.current_token().span;
         let inner = match self.inner.current() {
             TokenType::BracketOpen => {
                 self.inner.take(TokenType::BracketOpen)?;
@@ -417,10 +610,7 @@ impl Parser {
                 self.inner.take(TokenType::BracketClose)?;
                 ExpressionFactor::Bracket(Box::new(exp))
             }
-            TokenType::Int => {
-                let num = self.inner.take(TokenType::Int)?.image.as_str();
-                ExpressionFactor::Constant(u32::from_str(num).unwrap())
-            }
+            TokenType::Int => ExpressionFactor::Constant(self.take_integer()?),
             TokenType::Identifier => {
                 let base = self.inner.take(TokenType::Identifier)?;
                 let visit = self.parse_variable_visit()?;
@@ -429,27 +619,28 @@ impl Parser {
                     visit,
                 })
             }
-            _ => return Err(format!("unexpected factor token: {:?}", self.inner.current()))
+            t => return Err(SyntaxError::unexpected(&[TokenType::BracketOpen, TokenType::Int, TokenType::Identifier], t)),
         };
-        Ok(Positional::from_position(pos, inner))
+        Ok(self.spanned(start, inner))
     }
 
-    fn parse_param_list(&self) -> Result<PositionalVec<Param>, String> {
+    /// Parameters are separated by `;`, as in the grammar
+    fn parse_param_list(&self) -> Result<PositionalVec<Param>, SyntaxError> {
         let mut result = PositionalVec::new();
+        if TokenType::BracketClose == self.inner.current() {
+            return Ok(result);
+        }
         loop {
-            if TokenType::BracketClose == self.inner.current() {
+            result.push(self.parse_param()?);
+            if TokenType::Semicolon != self.inner.current() {
                 break;
             }
-            if result.len() != 0 {
-                self.inner.take(TokenType::Comma)?;
-            }
-            let param = self.parse_param()?;
-            result.push(param);
+            self.inner.move_next();
         }
         Ok(result)
     }
 
-    fn parse_param(&self) -> Result<Positional<Param>, String> {
+    fn parse_param(&self) -> Result<Positional<Param>, SyntaxError> {
         let is_var = TokenType::Var == self.inner.current();
         if is_var {
             self.inner.move_next();
@@ -457,7 +648,7 @@ impl Parser {
 
         let type_name = self.parse_type_name(true)?;
         let identifiers = self.parse_identifier_list()?;
-        Ok(Positional::from_position(type_name.position(), Param {
+        Ok(self.spanned(type_name.span, Param {
             is_var,
             definition: TypedIdentifiers {
                 type_name,
@@ -466,7 +657,7 @@ impl Parser {
         }))
     }
 
-    fn parse_variable_visit(&self) -> Result<Option<VariableVisit>, String> {
+    fn parse_variable_visit(&self) -> Result<Option<VariableVisit>, SyntaxError> {
         let dot = if TokenType::Dot == self.inner.current() {
             self.inner.take(TokenType::Dot)?;
             Some(self.inner.take(TokenType::Identifier)?)
@@ -491,4 +682,148 @@ impl Parser {
             })
         })
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use crate::Parser;
+    use snl_utils::ast::{BinaryOperator, Expression, Statement};
+    use snl_utils::error::SyntaxError;
+    use snl_utils::token::TokenType;
+
+    fn parse(source: &str) -> Parser {
+        Parser::new(snl_lexer::read_tokens(source).unwrap())
+    }
+
+    fn error_positions(source: &str) -> Vec<(u32, u32)> {
+        let (_, errors) = parse(source).parse_with_errors();
+        errors.iter().map(|e| e.position()).collect()
+    }
+
+    #[test]
+    fn test_examples_without_errors() {
+        for source in &[
+            include_str!("../../examples/program1.snl"),
+            include_str!("../../examples/bubble.snl"),
+        ] {
+            assert!(parse(source).parse().is_ok());
+        }
+    }
+
+    #[test]
+    fn test_multiple_statement_errors() {
+        let (program, errors) = parse(r#"program p
+var integer a, b;
+begin
+  read(a);
+  a := (a + 1;
+  if a < b then
+    b = 1
+  else
+    write(b)
+  fi;
+  while a < do
+    a := a - 1
+  endwh;
+  write(a
+end."#).parse_with_errors();
+        let positions: Vec<_> = errors.iter().map(|e| e.position()).collect();
+        assert_eq!(positions, vec![(5, 14), (7, 7), (11, 13), (15, 1)]);
+
+        let body = &program.body;
+        assert_eq!(body.len(), 5);
+        assert!(matches!(body[0], Statement::Input(_)));
+        assert!(matches!(body[1], Statement::Error(_)));
+        assert!(matches!(body[3], Statement::Error(_)));
+        match &body[2] {
+            Statement::Conditional(con) => {
+                assert!(matches!(con.body[0], Statement::Error(_)));
+                assert!(matches!(con.else_body[0], Statement::Output(_)));
+            }
+            s => panic!("unexpected statement {:?}", s),
+        }
+    }
+
+    #[test]
+    fn test_declaration_errors() {
+        assert_eq!(error_positions(r#"program p
+type t = ;
+     u = integer;
+var integer a, ;
+    char c;
+procedure q(integer x;
+begin
+  x := x +
+end
+begin
+  q(a);
+end."#), vec![(2, 10), (4, 16), (7, 1), (9, 1), (12, 1)]);
+    }
+
+    #[test]
+    fn test_declarations_without_semicolon() {
+        let (program, errors) = parse(r#"program p
+type t = integer
+var char c;
+    integer a, b
+begin
+  read(a)
+end."#).parse_with_errors();
+        let positions: Vec<_> = errors.iter().map(|e| e.position()).collect();
+        assert_eq!(positions, vec![(3, 1), (5, 1)]);
+
+        let declare = &program.declare;
+        assert_eq!(declare.type_declare.len(), 1);
+        assert_eq!(declare.type_declare[0].name(), "t");
+        assert_eq!(declare.variable_declare.len(), 2);
+        let names: Vec<_> = declare.variable_declare[1].identifiers.iter().map(|id| id.as_str()).collect();
+        assert_eq!(names, vec!["a", "b"]);
+        assert_eq!(program.body.len(), 1);
+    }
+
+    #[test]
+    fn test_typed_errors() {
+        let error = parse("program p\nbegin\n  a := ;\n  b := \nend.").parse().unwrap_err();
+        assert_eq!(error.position(), (3, 8));
+        assert_eq!(error.inner(), &SyntaxError::UnexpectedToken {
+            expected: vec![TokenType::BracketOpen, TokenType::Int, TokenType::Identifier],
+            found: TokenType::Semicolon,
+        });
+
+        let error = parse("program p\nbegin\n  write(4294967296)\nend.").parse().unwrap_err();
+        assert_eq!(error.position(), (3, 9));
+        // the error spans the whole literal
+        assert_eq!((error.span.end.line, error.span.end.column), (3, 19));
+        assert_eq!(error.inner(), &SyntaxError::IntegerOverflow("4294967296".to_owned()));
+
+        let error = parse("program p\nbegin\n  write(1)").parse().unwrap_err();
+        assert_eq!(error.inner(), &SyntaxError::UnexpectedEof { expected: vec![TokenType::End] });
+
+        let error = parse("program p\nbegin\n  write(1)\nend").parse().unwrap_err();
+        assert_eq!(error.inner(), &SyntaxError::UnexpectedEof { expected: vec![TokenType::Dot] });
+
+        let error = parse("program p\nbegin\n  write(1)\nend. garbage").parse().unwrap_err();
+        assert_eq!(error.position(), (4, 6));
+        assert_eq!(error.inner(), &SyntaxError::UnexpectedToken { expected: vec![TokenType::EOF], found: TokenType::Identifier });
+    }
+
+    #[test]
+    fn test_left_associative() {
+        let program = parse("program p\nbegin\n  a := a - b - c * d / e;\n  write(10 - 4 - 3 + 8 / 4 / 2)\nend.").parse().unwrap();
+        match &program.body[0] {
+            Statement::Assign(assign) => match &assign.value {
+                Expression::Binary(binary) => {
+                    assert_eq!(binary.op, BinaryOperator::Sub);
+                    assert_eq!(binary.left.to_string(), "a - b");
+                    assert!(matches!(&binary.left, Expression::Binary(left) if left.op == BinaryOperator::Sub));
+                    assert!(matches!(&binary.right, Expression::Binary(right) if right.op == BinaryOperator::Div));
+                }
+                exp => panic!("unexpected expression {:?}", exp),
+            },
+            s => panic!("unexpected statement {:?}", s),
+        }
+        match &program.body[1] {
+            Statement::Output(exp) => assert_eq!(exp.constant(), Some(4)),
+            s => panic!("unexpected statement {:?}", s),
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use crate::error::Warning;
+use crate::symbol::SymbolTable;
+use snl_rdp::Program;
+use snl_utils::ast::*;
+use snl_utils::span::Span;
+use std::collections::{HashMap, HashSet};
+
+enum Entry {
+    /// Type alias, whether it is integer or char
+    Type(bool),
+    /// Procedure with whether each parameter is a var parameter, and the declarations of the
+    /// variables of enclosing blocks it may assign, itself or through the procedures it calls
+    Procedure { params: Vec<bool>, assigns: HashSet<Span> },
+    /// Variable or parameter with where it is declared
+    Variable(Span),
+}
+
+/// Variables definitely assigned at a point of a block, `None` after a return
+type State = Option<HashSet<String>>;
+
+/// Warns about local variables which may be read before they are assigned.
+///
+/// Only integer and char variables declared in the block being analyzed are tracked, elements
+/// of arrays and records can not be told apart. Passing a variable to a var parameter counts
+/// as an assignment, and so does calling a procedure which assigns the variable anywhere in
+/// its body or in the procedures it calls.
+struct DefiniteAssignment {
+    symbols: SymbolTable<Entry>,
+    /// Tracked locals of the block being analyzed with where they are declared
+    locals: HashMap<String, Span>,
+    /// Declarations of the variables assigned by the body being analyzed, directly or by calls
+    writes: HashSet<Span>,
+    /// Locals already reported, each one is reported once
+    reported: HashSet<String>,
+    warnings: Vec<Positional<Warning>>,
+}
+
+pub fn check(program: &Positional<Program>) -> Vec<Positional<Warning>> {
+    let mut analysis = DefiniteAssignment {
+        symbols: Default::default(),
+        locals: Default::default(),
+        writes: Default::default(),
+        reported: Default::default(),
+        warnings: Default::default(),
+    };
+    analysis.check_block(&program.declare, &program.body, &[]);
+    analysis.warnings
+}
+
+impl DefiniteAssignment {
+    /// Check a block, returns the declarations of the variables of enclosing blocks it may assign
+    fn check_block(&mut self, declare: &ProgramDeclare, body: &StatementList, params: &[&Positional<String>]) -> HashSet<Span> {
+        for t in declare.type_declare.iter() {
+            let scalar = self.is_scalar(t.base.inner());
+            self.symbols.insert(t.name().to_owned(), Entry::Type(scalar));
+        }
+
+        // params are assigned by the caller
+        let mut locals = HashMap::new();
+        let mut declared = HashSet::new();
+        for param in params {
+            self.symbols.insert(param.inner().clone(), Entry::Variable(param.span));
+            declared.insert(param.span);
+        }
+        for v in declare.variable_declare.iter() {
+            let scalar = self.is_scalar(&v.type_name);
+            for name in v.identifiers.iter() {
+                self.symbols.insert(name.inner().clone(), Entry::Variable(name.span));
+                declared.insert(name.span);
+                // a local duplicating a param is reported by the semantic analysis
+                if scalar && !params.iter().any(|param| param.inner() == name.inner()) {
+                    locals.insert(name.inner().clone(), name.span);
+                }
+            }
+        }
+
+        for p in declare.procedure_declare.iter() {
+            let mut flags = Vec::new();
+            let mut names = Vec::new();
+            for param in p.params.iter() {
+                for name in param.definition.identifiers.iter() {
+                    flags.push(param.is_var);
+                    names.push(name);
+                }
+            }
+
+            self.symbols.step_in();
+            // recursive calls assign nothing its own body does not assign already
+            self.symbols.insert(p.name().to_owned(), Entry::Procedure { params: flags.clone(), assigns: HashSet::new() });
+            let outer = std::mem::take(&mut self.locals);
+            let reported = std::mem::take(&mut self.reported);
+            let writes = std::mem::take(&mut self.writes);
+            let assigns = self.check_block(&p.declare, &p.body, &names);
+            self.locals = outer;
+            self.reported = reported;
+            self.writes = writes;
+            self.symbols.step_out();
+            self.symbols.insert(p.name().to_owned(), Entry::Procedure { params: flags, assigns });
+        }
+
+        self.locals = locals;
+        self.check_statement_list(body, Some(HashSet::new()));
+        std::mem::take(&mut self.writes).into_iter().filter(|span| !declared.contains(span)).collect()
+    }
+
+    /// Record an assignment of the variable `name` refers to
+    fn write(&mut self, name: &str) {
+        if let Some(Entry::Variable(span)) = self.symbols.query(name) {
+            self.writes.insert(*span);
+        }
+    }
+
+    fn is_scalar(&self, ty: &SNLType) -> bool {
+        match ty {
+            SNLType::Integer | SNLType::Char => true,
+            SNLType::Others(name) => matches!(self.symbols.query(name), Some(Entry::Type(true))),
+            _ => false,
+        }
+    }
+
+    fn check_statement_list(&mut self, list: &StatementList, mut state: State) -> State {
+        for statement in list.iter() {
+            state = self.check_statement(statement, state);
+        }
+        state
+    }
+
+    fn check_statement(&mut self, statement: &Statement, state: State) -> State {
+        // nothing is read after a return
+        let mut assigned = state?;
+        match statement {
+            Statement::Conditional(con) => {
+                self.check_expression(&con.condition.left, &assigned);
+                self.check_expression(&con.condition.right, &assigned);
+                let body = self.check_statement_list(&con.body, Some(assigned.clone()));
+                let else_body = self.check_statement_list(&con.else_body, Some(assigned));
+                match (body, else_body) {
+                    (Some(body), Some(else_body)) => Some(body.intersection(&else_body).cloned().collect()),
+                    (body, else_body) => body.or(else_body),
+                }
+            }
+            Statement::Loop(lo) => {
+                self.check_expression(&lo.condition.left, &assigned);
+                self.check_expression(&lo.condition.right, &assigned);
+                // the body may run no times
+                self.check_statement_list(&lo.body, Some(assigned.clone()));
+                Some(assigned)
+            }
+            Statement::Input(input) => {
+                self.write(input);
+                assigned.insert(input.inner().clone());
+                Some(assigned)
+            }
+            Statement::Output(exp) => {
+                self.check_expression(exp, &assigned);
+                Some(assigned)
+            }
+            Statement::Return(exp) => {
+                self.check_expression(exp, &assigned);
+                None
+            }
+            Statement::Assign(assign) => {
+                if let Some(index) = assign.variable.visit.as_ref().and_then(|visit| visit.sqbr.as_ref()) {
+                    self.check_expression(index, &assigned);
+                }
+                self.check_expression(&assign.value, &assigned);
+                self.write(&assign.variable.base);
+                assigned.insert(assign.variable.base.inner().clone());
+                Some(assigned)
+            }
+            Statement::Call(call) => {
+                let (params, assigns) = match self.symbols.query(call.name()) {
+                    Some(Entry::Procedure { params, assigns }) => (params.clone(), assigns.clone()),
+                    _ => Default::default(),
+                };
+                let mut references = Vec::new();
+                for (i, exp) in call.params.iter().enumerate() {
+                    match exp.as_variable() {
+                        // the callee may assign it before reading
+                        Some(repr) if params.get(i) == Some(&true) => references.push(repr.base.inner().clone()),
+                        _ => self.check_expression(exp, &assigned),
+                    }
+                }
+                for name in references.iter() {
+                    self.write(name);
+                }
+                assigned.extend(references);
+                // locals are told apart from variables of the same name by their declaration
+                assigned.extend(self.locals.iter().filter(|(_, span)| assigns.contains(span)).map(|(name, _)| name.clone()));
+                self.writes.extend(assigns);
+                Some(assigned)
+            }
+            Statement::Error(_) => Some(assigned),
+        }
+    }
+
+    fn check_expression(&mut self, exp: &Expression, assigned: &HashSet<String>) {
+        match exp {
+            Expression::Binary(binary) => {
+                self.check_expression(&binary.left, assigned);
+                self.check_expression(&binary.right, assigned);
+            }
+            Expression::Factor(factor) => self.check_factor(factor, assigned),
+        }
+    }
+
+    fn check_factor(&mut self, factor: &ExpressionFactor, assigned: &HashSet<String>) {
+        match factor {
+            ExpressionFactor::Bracket(exp) => self.check_expression(exp, assigned),
+            ExpressionFactor::Constant(_) => {}
+            ExpressionFactor::Variable(repr) => {
+                let name = repr.base.inner();
+                if let Some(declared) = self.locals.get(name) {
+                    if !assigned.contains(name) && self.reported.insert(name.clone()) {
+                        self.warnings.push(Positional::from_span(
+                            repr.base.span,
+                            Warning::UninitializedVariable { name: name.clone(), declared: *declared },
+                        ));
+                    }
+                }
+                if let Some(index) = repr.visit.as_ref().and_then(|visit| visit.sqbr.as_ref()) {
+                    self.check_expression(index, assigned);
+                }
+            }
+        }
+    }
+}
@@ -1,8 +1,12 @@
+use crate::lint::Lint;
+use snl_utils::diagnostic::{Diagnose, Note, Severity};
+use snl_utils::span::Span;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     /// Identifier with the same name exists at current tier
-    #[error("Duplicated identifier '{0}'")]
-    DuplicatedIdentifier(String),
+    #[error("Duplicated identifier '{name}'")]
+    DuplicatedIdentifier { name: String, previous: Span },
     /// Identified use is not found in symbol table
     #[error("Undefined identifier '{0}'")]
     UndefinedIdentifier(String),
@@ -23,7 +27,7 @@ pub enum Error {
     InvalidArrayDefinition,
     /// Constant array visit index is lower than lower bound or higher than high bound
     #[error("Array index({0}) out of bound({1}..{2})")]
-    ArrayIndexOutbound(u32, u32, u32),
+    ArrayIndexOutbound(i64, usize, usize),
     /// Unexpected array index
     #[error("Unexpected array index, only array type can be indexed")]
     UnexpectedArrayIndex,
@@ -58,4 +62,137 @@ pub enum Error {
     /// Write only accepts Integer and Char
     #[error("Write expression type can only be integer or char, got '{0}'")]
     InvalidWriteType(String),
-}
\ No newline at end of file
+    /// Argument of a var parameter is an expression which can not be assigned to
+    #[error("Var parameter '{name}' requires a variable argument")]
+    InvalidVarArgument { name: String, declared: Span },
+
+    /// Return value type is different from the first return of the same procedure or program
+    #[error("Return type '{got}' is not compatable with type '{expected}' returned before")]
+    ReturnTypeMismatch { expected: String, got: String, first: Span },
+}
+
+/// Suspicious code which is still valid, each kind of warning belongs to a lint
+#[derive(Debug, thiserror::Error)]
+pub enum Warning {
+    /// Statement follows a return in every path
+    #[error("Unreachable statement")]
+    UnreachableStatement,
+    /// Local variable may be read before any assignment or read reaches it
+    #[error("Variable '{name}' may be used before being assigned")]
+    UninitializedVariable { name: String, declared: Span },
+    /// Variable is declared but never referred to
+    #[error("Variable '{0}' is never used")]
+    UnusedVariable(String),
+    /// Type alias is declared but never referred to
+    #[error("Type '{0}' is never used")]
+    UnusedType(String),
+    /// Procedure is declared but never called other than by itself
+    #[error("Procedure '{0}' is never called")]
+    UnusedProcedure(String),
+    /// Parameter is never referred to in the body of its procedure
+    #[error("Parameter '{0}' is never used")]
+    UnusedParameter(String),
+    /// Declaration hides an identifier declared by an enclosing block
+    #[error("Identifier '{name}' shadows a declaration of an enclosing block")]
+    ShadowedIdentifier { name: String, previous: Span },
+}
+
+impl Diagnose for Error {
+    fn code(&self) -> &'static str {
+        match self {
+            Error::DuplicatedIdentifier { .. } => "E0301",
+            Error::UndefinedIdentifier(_) => "E0302",
+            Error::UndefinedType(_) => "E0303",
+            Error::UncompatableType { .. } => "E0304",
+            Error::InvalidVariableRepresent(_) => "E0305",
+            Error::InvalidArrayDefinition => "E0306",
+            Error::ArrayIndexOutbound(..) => "E0307",
+            Error::UnexpectedArrayIndex => "E0308",
+            Error::InvalidFieldIndexType(_) => "E0309",
+            Error::UndefinedRecordField(_) => "E0310",
+            Error::AssignTypeMismatch { .. } => "E0311",
+            Error::InvalidAssignee => "E0312",
+            Error::CallParameterTypeMismatch { .. } => "E0313",
+            Error::CallParameterCountMismatch { .. } => "E0314",
+            Error::InvalidBoolExpression => "E0315",
+            Error::InvalidReadType(_) => "E0316",
+            Error::InvalidWriteType(_) => "E0317",
+            Error::InvalidVarArgument { .. } => "E0318",
+            Error::ReturnTypeMismatch { .. } => "E0319",
+        }
+    }
+
+    fn notes(&self) -> Vec<Note> {
+        match self {
+            Error::DuplicatedIdentifier { previous, .. } => vec![Note {
+                message: "previous declaration here".to_owned(),
+                span: Some(*previous),
+            }],
+            Error::InvalidVarArgument { declared, .. } => vec![Note {
+                message: "parameter declared with var here".to_owned(),
+                span: Some(*declared),
+            }],
+            Error::ReturnTypeMismatch { first, .. } => vec![Note {
+                message: "first return here".to_owned(),
+                span: Some(*first),
+            }],
+            Error::ArrayIndexOutbound(_, low, top) => vec![Note {
+                message: format!("valid indexes are {} to {}", low, top),
+                span: None,
+            }],
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Diagnose for Warning {
+    fn code(&self) -> &'static str {
+        match self {
+            Warning::UnreachableStatement => "W0301",
+            Warning::UninitializedVariable { .. } => "W0302",
+            Warning::UnusedVariable(_) => "W0303",
+            Warning::UnusedType(_) => "W0304",
+            Warning::UnusedProcedure(_) => "W0305",
+            Warning::UnusedParameter(_) => "W0306",
+            Warning::ShadowedIdentifier { .. } => "W0307",
+        }
+    }
+
+    /// Warnings are reported as errors if their lint is denied, see `Lints::severity`
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn notes(&self) -> Vec<Note> {
+        match self {
+            Warning::UninitializedVariable { name, declared } => vec![Note {
+                message: format!("'{}' declared here", name),
+                span: Some(*declared),
+            }],
+            Warning::ShadowedIdentifier { previous, .. } => vec![Note {
+                message: "shadowed declaration here".to_owned(),
+                span: Some(*previous),
+            }],
+            Warning::UnreachableStatement => vec![Note {
+                message: "any code following a return is never run".to_owned(),
+                span: None,
+            }],
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Warning {
+    /// Lint of the warning, its level decides how the warning is reported
+    pub fn lint(&self) -> Lint {
+        match self {
+            Warning::UnreachableStatement => Lint::UnreachableStatement,
+            Warning::UninitializedVariable { .. } => Lint::UninitializedVariable,
+            Warning::UnusedVariable(_) => Lint::UnusedVariable,
+            Warning::UnusedType(_) => Lint::UnusedType,
+            Warning::UnusedProcedure(_) => Lint::UnusedProcedure,
+            Warning::UnusedParameter(_) => Lint::UnusedParameter,
+            Warning::ShadowedIdentifier { .. } => Lint::ShadowedIdentifier,
+        }
+    }
+}
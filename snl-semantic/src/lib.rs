@@ -1,138 +1,195 @@
-use crate::symbol::{SymbolTable, Symbol};
+use crate::symbol::{Parameter, SymbolTable, Symbol};
 use snl_rdp::Program;
 use snl_utils::ast::*;
-use crate::error::Error;
-use std::cell::RefCell;
-use std::collections::HashSet;
+use snl_utils::span::Span;
+use crate::error::{Error, Warning};
+use crate::lint::Lints;
+use crate::types::{Type, TypeId, TypeTable};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 
-mod error;
+mod assignment;
+pub mod error;
+pub mod lint;
 pub mod symbol;
+pub mod types;
 
-pub struct Semantic {
-    ast: Positional<Program>,
+/// What a declaration is, to report it when it is never used
+#[derive(Debug, Copy, Clone)]
+enum Declared {
+    Variable,
+    Parameter,
+    Type,
+    Procedure,
+}
+
+/// Where a symbol is declared and whether it is referred to
+struct Declaration {
+    span: Span,
+    kind: Declared,
+    used: Cell<bool>,
+}
+
+pub struct Semantic<'a> {
+    ast: &'a Positional<Program>,
     symbols: RefCell<SymbolTable<Symbol>>,
+    /// Where symbols in `symbols` are declared, kept in the same tiers
+    declarations: RefCell<SymbolTable<Declaration>>,
+    types: RefCell<TypeTable>,
+    /// Type and span of the first return of every block being analyzed,
+    /// the main program is at the bottom and the innermost procedure at the top
+    returns: RefCell<Vec<Option<(TypeId, Span)>>>,
     errors: RefCell<Vec<Positional<Error>>>,
+    warnings: RefCell<Vec<Positional<Warning>>>,
+    lints: Lints,
 }
 
-impl Semantic {
-    pub fn new(ast: Positional<Program>) -> Self {
-        let result = Semantic {
+impl<'a> Semantic<'a> {
+    pub fn new(ast: &'a Positional<Program>) -> Self {
+        Semantic {
             ast,
             symbols: Default::default(),
+            declarations: Default::default(),
+            types: Default::default(),
+            returns: RefCell::new(vec![None]),
             errors: Default::default(),
-        };
-        result
+            warnings: Default::default(),
+            lints: Default::default(),
+        }
+    }
+
+    /// Levels of warnings, warnings of allowed lints are not returned by `analyze`
+    pub fn with_lints(mut self, lints: Lints) -> Self {
+        self.lints = lints;
+        self
     }
 
-    pub fn analyze(self) -> Vec<Positional<Error>> {
+    /// Errors and warnings of the program, each in the order they are found
+    pub fn analyze(self) -> (Vec<Positional<Error>>, Vec<Positional<Warning>>) {
         self.analyze_declare(&self.ast.declare);
         self.analyze_statement_list(&self.ast.body);
-        self.errors.into_inner()
+        let main = self.declarations.borrow_mut().step_out();
+        self.check_unused(main);
+        let mut warnings = self.warnings.into_inner();
+        warnings.extend(assignment::check(self.ast));
+        let lints = &self.lints;
+        warnings.retain(|warning| !lints.is_allowed(warning));
+        (self.errors.into_inner(), warnings)
+    }
+
+    /// Report `name` if it is already declared in current tier, returns whether it is
+    fn check_duplicated(&self, name: &str, span: Span) -> bool {
+        match self.declarations.borrow().query_own(name) {
+            Some(previous) => {
+                self.errors.borrow_mut().push(Positional::from_span(
+                    span,
+                    Error::DuplicatedIdentifier { name: name.to_owned(), previous: previous.span },
+                ));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Add symbol to current tier
+    fn declare(&self, name: &str, span: Span, kind: Declared, symbol: Symbol) {
+        // symbols of current tier are already checked to be different
+        if let Some(outer) = self.declarations.borrow().query(name) {
+            self.warnings.borrow_mut().push(Positional::from_span(
+                span,
+                Warning::ShadowedIdentifier { name: name.to_owned(), previous: outer.span },
+            ));
+        }
+        self.symbols.borrow_mut().insert(name.to_owned(), symbol);
+        self.declarations.borrow_mut().insert(name.to_owned(), Declaration { span, kind, used: Cell::new(false) });
+    }
+
+    /// Mark the declaration `name` refers to as used
+    fn refer(&self, name: &str) {
+        if let Some(declaration) = self.declarations.borrow().query(name) {
+            declaration.used.set(true);
+        }
+    }
+
+    /// Report declarations of a tier which are never referred to
+    fn check_unused(&self, tier: HashMap<String, Declaration>) {
+        let mut unused: Vec<_> = tier.into_iter().filter(|(_, d)| !d.used.get()).collect();
+        unused.sort_by_key(|(_, d)| d.span.start.offset);
+        for (name, declaration) in unused {
+            let warning = match declaration.kind {
+                Declared::Variable => Warning::UnusedVariable(name),
+                Declared::Parameter => Warning::UnusedParameter(name),
+                Declared::Type => Warning::UnusedType(name),
+                Declared::Procedure => Warning::UnusedProcedure(name),
+            };
+            self.warnings.borrow_mut().push(Positional::from_span(declaration.span, warning));
+        }
     }
 
     fn analyze_declare(&self, declare: &ProgramDeclare) {
         // type alias = original;
         for t in declare.type_declare.iter() {
+            // resolve original type first, so that the alias can not refer to itself
+            let ty = self.resolve_type(&t.inner().base());
             // alias exist in Symbol Table
-            if self.symbols.borrow().has_own_property(t.name()) {
-                self.errors.borrow_mut().push(Positional::from_position(
-                    t.position(),
-                    Error::DuplicatedIdentifier(t.name().to_owned()),
-                ))
-            } else {
-                // analyze whether original is valid
-                self.analyze_type(&t.inner().base());
-
-                let mut symbols = self.symbols.borrow_mut();
-                // get real type of aliased type
-                let ty = Symbol::Type(t.inner().base().inner().to_string(|ty| -> Option<String>{
-                    symbols.query_type(ty).map(|r| r.to_string())
-                }));
+            if !self.check_duplicated(t.name(), t.span) {
                 // add new type alias
-                symbols.insert(t.name().to_owned(), ty);
+                self.declare(t.name(), t.span, Declared::Type, Symbol::Type(ty));
             }
         }
 
         for v in declare.variable_declare.iter() {
             // check variable type
-            self.analyze_type(&Positional::from_position(v.position(), &v.type_name));
+            let ty = self.resolve_type(&Positional::from_span(v.type_name.span, &v.type_name));
             // check variable name
             for variable_name in v.identifiers.iter() {
-                if self.symbols.borrow().has_own_property(variable_name) {
-                    self.errors.borrow_mut().push(Positional::from_position(
-                        variable_name.position(),
-                        Error::DuplicatedIdentifier(variable_name.inner().clone()),
-                    ))
-                } else {
-                    let mut symbols = self.symbols.borrow_mut();
-                    // look for real type(deref alias)
-                    let ty = Symbol::Variable(v.type_name.to_string(|ty| -> Option<String>{
-                        symbols.query_type(ty).map(|r| r.to_string())
-                    }));
+                if !self.check_duplicated(variable_name, variable_name.span) {
                     // insert variable to symbol table
-                    symbols.insert(variable_name.inner().clone(), ty);
+                    self.declare(variable_name, variable_name.span, Declared::Variable, Symbol::Variable(ty));
                 }
             }
         }
 
         for p in declare.procedure_declare.iter() {
-            // procedure params type signature
-            let mut params: Vec<String> = Default::default();
+            // procedure params type signature, types are resolved in the scope procedure is declared
+            let mut params: Vec<Parameter> = Default::default();
             for param in p.params.iter() {
-                // param type string
-                let param_type = param.definition.type_name.to_string(|r| Some(r.to_string()));
-
-                // add to param list
-                for _ in param.definition.identifiers.iter() {
-                    params.push(param_type.clone())
+                let param_type = self.resolve_type(&Positional::from_span(
+                    param.definition.type_name.span,
+                    &param.definition.type_name,
+                ));
+                for param_name in param.definition.identifiers.iter() {
+                    params.push(Parameter {
+                        name: param_name.inner().clone(),
+                        ty: param_type,
+                        is_var: param.is_var,
+                        span: param_name.span,
+                    });
                 }
             }
 
             // check procedure name
-            if self.symbols.borrow().has_own_property(p.name()) {
-                self.errors.borrow_mut().push(Positional::from_position(
-                    p.position(),
-                    Error::DuplicatedIdentifier(p.name().to_owned()),
-                ));
-            } else {
+            if !self.check_duplicated(p.name(), p.span) {
                 // add procedure to Symbol Table
-                self.symbols.borrow_mut().insert(p.name().to_string(), Symbol::Procedure(params.clone()));
+                self.declare(p.name(), p.span, Declared::Procedure, Symbol::Procedure(params.clone()));
             }
 
             // start analyzing current procedure
             self.symbols.borrow_mut().step_in();
+            self.declarations.borrow_mut().step_in();
 
-            // add procedure itself to symbol table of current tier
-            self.symbols.borrow_mut().insert(p.name().to_string(), Symbol::Procedure(params));
+            // add procedure itself to symbol table of current tier, recursive calls do not count as uses
+            self.symbols.borrow_mut().insert(p.name().to_owned(), Symbol::Procedure(params.clone()));
+            self.declarations.borrow_mut().insert(p.name().to_owned(), Declaration {
+                span: p.span,
+                kind: Declared::Procedure,
+                used: Cell::new(true),
+            });
 
             // parameters
-            let mut params: Vec<String> = Default::default();
-            for param in p.params.iter() {
-                // param type
-                self.analyze_type(&Positional::from_position(
-                    param.position(),
-                    &param.definition.type_name,
-                ));
-
-                // param type string
-                let param_type = param.definition.type_name.to_string(|r| Some(r.to_string()));
-
-                // param name
-                for param_name in param.definition.identifiers.iter() {
-                    if self.symbols.borrow().has_own_property(param_name.as_str()) {
-                        self.errors.borrow_mut().push(Positional::from_position(
-                            param_name.position(),
-                            Error::DuplicatedIdentifier(param_name.inner().to_owned()),
-                        ));
-                    } else {
-                        self.symbols.borrow_mut().insert(
-                            param_name.inner().clone(),
-                            Symbol::Variable(param_type.clone()),
-                        );
-                    }
-
-                    // add to param list
-                    params.push(param_type.clone())
+            for param in params {
+                if !self.check_duplicated(&param.name, param.span) {
+                    self.declare(&param.name, param.span, Declared::Parameter, Symbol::Variable(param.ty));
                 }
             }
 
@@ -140,50 +197,69 @@ impl Semantic {
             self.analyze_declare(&p.declare);
 
             // check body
+            self.returns.borrow_mut().push(None);
             self.analyze_statement_list(&p.body);
+            self.returns.borrow_mut().pop();
 
             // finish analyzing current procedure
             self.symbols.borrow_mut().step_out();
+            let tier = self.declarations.borrow_mut().step_out();
+            self.check_unused(tier);
         }
     }
 
-    fn analyze_statement_list(&self, list: &StatementList) {
+    /// Returns whether the list always ends with a return
+    fn analyze_statement_list(&self, list: &StatementList) -> bool {
+        let mut returned = false;
+        let mut reported = false;
         for statement in list.iter() {
+            // only the first statement after a return is reported
+            if returned && !reported {
+                self.warnings.borrow_mut().push(Positional::from_span(
+                    statement.span(),
+                    Warning::UnreachableStatement,
+                ));
+                reported = true;
+            }
+
+            let mut returns = false;
             match statement {
                 Statement::Conditional(con) => {
                     self.analyze_analyze_relation(&con.condition);
-                    self.analyze_statement_list(&con.body);
-                    self.analyze_statement_list(&con.else_body);
+                    let body = self.analyze_statement_list(&con.body);
+                    let else_body = self.analyze_statement_list(&con.else_body);
+                    returns = body && else_body;
                 }
                 Statement::Loop(lo) => {
                     self.analyze_analyze_relation(&lo.condition);
                     self.analyze_statement_list(&lo.body);
                 }
                 Statement::Input(input) => {
+                    self.refer(input);
                     match self.symbols.borrow().query(input) {
                         Some(symbol) => {
                             match symbol {
                                 Symbol::Variable(variable) => {
                                     // only accept integer or char
-                                    if variable.starts_with("[") || variable.starts_with("{") || variable.starts_with("#") {
-                                        self.errors.borrow_mut().push(Positional::from_position(
-                                            input.position(),
-                                            Error::InvalidReadType(variable.clone()),
+                                    if !variable.is_scalar() && !variable.is_error() {
+                                        self.errors.borrow_mut().push(Positional::from_span(
+                                            input.span,
+                                            Error::InvalidReadType(self.type_name(*variable)),
                                         ))
                                     }
                                 }
                                 p => {
-                                    self.errors.borrow_mut().push(Positional::from_position(
-                                        input.position(),
-                                        Error::UncompatableType { expected: "Variable".to_owned(), got: format!("{:?}", p) },
+                                    self.errors.borrow_mut().push(Positional::from_span(
+                                        input.span,
+                                        Error::UncompatableType { expected: "Variable".to_owned(), got: p.kind().to_owned() },
                                     ))
                                 }
                             }
                         }
                         None => {
                             // no symbol found
-                            self.errors.borrow_mut().push(Positional::from_position(
-                                input.position(),
+                            self.errors.borrow_mut().push(Positional::from_span(
+                                input.span,
                                 Error::UndefinedIdentifier(input.inner().to_owned()),
                             ))
                         }
@@ -191,57 +267,69 @@ impl Semantic {
                 }
                 Statement::Output(output) => {
                     let write_type = self.analyze_expression(output);
-                    match write_type.as_str() {
-                        // integer and char are valid types
-                        // skip mixex type(empty string)
-                        "integer" | "char" | "" => {}
-                        _ => {
-                            self.errors.borrow_mut().push(Positional::from_position(
-                                output.left.position(),
-                                Error::InvalidWriteType(write_type),
-                            ))
-                        }
+                    // integer and char are valid types, errors in expression are already reported
+                    if !write_type.is_scalar() && !write_type.is_error() {
+                        self.errors.borrow_mut().push(Positional::from_span(
+                            output.span(),
+                            Error::InvalidWriteType(self.type_name(write_type)),
+                        ))
                     }
                 }
                 Statement::Return(ret) => {
-                    self.analyze_expression(ret);
+                    let ty = self.analyze_expression(ret);
+                    self.check_return(ty, ret.span());
+                    returns = true;
                 }
                 Statement::Assign(assign) => {
                     let left_type = self.analyze_variable_represent(&assign.variable);
                     let right_type = self.analyze_expression(&assign.value);
 
-                    if left_type == "" {
-                        self.errors.borrow_mut().push(Positional::from_position(
-                            assign.variable.base.position(),
+                    if left_type.is_error() {
+                        self.errors.borrow_mut().push(Positional::from_span(
+                            assign.variable.base.span,
                             Error::InvalidAssignee,
                         ))
-                    } else if left_type != right_type {
-                        self.errors.borrow_mut().push(Positional::from_position(
-                            assign.value.left.position(),
-                            Error::AssignTypeMismatch { expected: left_type, got: right_type },
+                    } else if !right_type.is_error() && left_type != right_type {
+                        self.errors.borrow_mut().push(Positional::from_span(
+                            assign.value.span(),
+                            Error::AssignTypeMismatch {
+                                expected: self.type_name(left_type),
+                                got: self.type_name(right_type),
+                            },
                         ))
                     }
                 }
                 Statement::Call(call) => {
                     // look for symbol in table
+                    self.refer(call.name());
                     match self.symbols.borrow().query(call.name()) {
                         Some(symbol) => {
                             match symbol {
                                 Symbol::Procedure(params) => {
                                     if params.len() != call.params.len() {
                                         // parameter count mismatch
-                                        self.errors.borrow_mut().push(Positional::from_position(
-                                            call.position(),
+                                        self.errors.borrow_mut().push(Positional::from_span(
+                                            call.span,
                                             Error::CallParameterCountMismatch { expected: params.len(), got: call.params.len() },
                                         ))
                                     } else {
                                         //
-                                        for (exp, param_type) in call.params.iter().zip(params) {
+                                        for (exp, param) in call.params.iter().zip(params) {
                                             let exp_type = self.analyze_expression(exp);
-                                            if exp_type != "" && &exp_type != param_type {
-                                                self.errors.borrow_mut().push(Positional::from_position(
-                                                    call.position(),
-                                                    Error::CallParameterTypeMismatch { expected: param_type.to_string(), got: exp_type },
+                                            if !exp_type.is_error() && !param.ty.is_error() && exp_type != param.ty {
+                                                self.errors.borrow_mut().push(Positional::from_span(
+                                                    call.span,
+                                                    Error::CallParameterTypeMismatch {
+                                                        expected: self.type_name(param.ty),
+                                                        got: self.type_name(exp_type),
+                                                    },
+                                                ))
+                                            }
+                                            // only variables can be passed by reference
+                                            if param.is_var && exp.as_variable().is_none() {
+                                                self.errors.borrow_mut().push(Positional::from_span(
+                                                    exp.span(),
+                                                    Error::InvalidVarArgument { name: param.name.clone(), declared: param.span },
                                                 ))
                                             }
                                         }
@@ -249,228 +337,562 @@ impl Semantic {
                                 }
                                 // idenfier called is not procedure
                                 p => {
-                                    self.errors.borrow_mut().push(Positional::from_position(
-                                        call.position(),
-                                        Error::UncompatableType { expected: "Procedure".to_owned(), got: format!("{:?}", p) },
+                                    self.errors.borrow_mut().push(Positional::from_span(
+                                        call.span,
+                                        Error::UncompatableType { expected: "Procedure".to_owned(), got: p.kind().to_owned() },
                                     ))
                                 }
                             }
                         }
                         None => {
                             // no symbol found
-                            self.errors.borrow_mut().push(Positional::from_position(
-                                call.position(),
+                            self.errors.borrow_mut().push(Positional::from_span(
+                                call.span,
                                 Error::UndefinedIdentifier(call.name().to_owned()),
                             ))
                         }
                     }
                 }
+                // already reported by the parser
+                Statement::Error(_) => {}
             }
+            returned |= returns;
         }
+        returned
     }
 
-    fn analyze_type(&self, t: &Positional<&SNLType>) {
-        match t.inner() {
-            SNLType::Array(arr) => {
-                // check array definition bounds
-                if arr.lower_bound > arr.upper_bound {
-                    self.errors.borrow_mut().push(Positional::from_position(
-                        t.position(),
-                        Error::InvalidArrayDefinition,
+    /// All returns of a block should return the same type as the first one.
+    ///
+    /// `return` is allowed in any statement of a procedure body or of the main program body,
+    /// including statements nested in conditionals and loops. In the main program it ends the
+    /// program. A procedure has no result type of its own, so returns are only compared with
+    /// the other returns of the same block, never with those of enclosing or nested blocks.
+    fn check_return(&self, ty: TypeId, span: Span) {
+        if ty.is_error() {
+            return;
+        }
+        let mut returns = self.returns.borrow_mut();
+        match returns.last_mut().unwrap() {
+            Some((first, first_span)) => {
+                if *first != ty {
+                    self.errors.borrow_mut().push(Positional::from_span(
+                        span,
+                        Error::ReturnTypeMismatch {
+                            expected: self.type_name(*first),
+                            got: self.type_name(ty),
+                            first: *first_span,
+                        },
                     ));
                 }
             }
+            first => *first = Some((ty, span)),
+        }
+    }
+
+    /// Name of type for error messages
+    fn type_name(&self, ty: TypeId) -> String {
+        self.types.borrow().name(ty)
+    }
+
+    /// Check type definition and get its id, aliases are resolved to the type they refer to
+    fn resolve_type(&self, t: &Positional<&SNLType>) -> TypeId {
+        let ty = match t.inner() {
+            SNLType::Integer => Type::Integer,
+            SNLType::Char => Type::Char,
+            // check array definition bounds
+            SNLType::Array(arr) if arr.lower_bound > arr.upper_bound => {
+                self.errors.borrow_mut().push(Positional::from_span(
+                    t.span,
+                    Error::InvalidArrayDefinition,
+                ));
+                return TypeId::ERROR;
+            }
+            SNLType::Array(arr) => Type::Array {
+                element: match arr.base {
+                    SNLBaseType::Integer => TypeId::INTEGER,
+                    SNLBaseType::Char => TypeId::CHAR,
+                },
+                lower_bound: arr.lower_bound,
+                upper_bound: arr.upper_bound,
+            },
             SNLType::Record(records) => {
+                let mut fields: Vec<(String, TypeId)> = Vec::new();
+                // fields duplication check
+                let mut declared: HashMap<&str, Span> = HashMap::new();
                 for rec in records {
                     // analyze type
-                    let ty = Positional::from_position(rec.type_name.position(), rec.type_name.inner());
-                    self.analyze_type(&ty);
+                    let ty = self.resolve_type(&Positional::from_span(rec.type_name.span, rec.type_name.inner()));
 
-                    // fields duplication check
-                    let mut fields: HashSet<String> = HashSet::new();
                     for id in &rec.identifiers {
-                        if fields.contains(id.inner()) {
-                            self.errors.borrow_mut().push(Positional::from_position(
-                                id.position(),
-                                Error::DuplicatedIdentifier(id.inner().clone()),
-                            ));
-                        } else {
-                            fields.insert(id.inner().clone());
+                        match declared.get(id.as_str()) {
+                            Some(previous) => {
+                                self.errors.borrow_mut().push(Positional::from_span(
+                                    id.span,
+                                    Error::DuplicatedIdentifier { name: id.inner().clone(), previous: *previous },
+                                ));
+                            }
+                            None => {
+                                declared.insert(id.as_str(), id.span);
+                                fields.push((id.inner().clone(), ty));
+                            }
                         }
                     }
                 }
+                Type::Record(fields)
             }
             SNLType::Others(id) => {
-                if !self.symbols.borrow().has_own_property(id) {
-                    self.errors.borrow_mut().push(Positional::from_position(
-                        t.position(),
-                        Error::UndefinedType(id.to_owned()),
-                    ))
-                }
+                self.refer(id);
+                return match self.symbols.borrow().query(id) {
+                    Some(Symbol::Type(ty)) => *ty,
+                    _ => {
+                        self.errors.borrow_mut().push(Positional::from_span(
+                            t.span,
+                            Error::UndefinedType(id.to_owned()),
+                        ));
+                        TypeId::ERROR
+                    }
+                };
             }
-            _ => {}
-        }
+        };
+        self.types.borrow_mut().intern(ty)
     }
 
-    fn analyze_expression(&self, exp: &Expression) -> String {
-        let left_type = self.analyze_expression_term(exp.left.inner());
-        match &exp.right {
-            Some(right) => {
-                let right_type = self.analyze_expression(right.inner());
-                if left_type != right_type {
-                    self.errors.borrow_mut().push(Positional::from_position(
-                        exp.left.position(),
-                        Error::UncompatableType { expected: left_type.clone(), got: right_type },
-                    ))
-                }
+    fn analyze_expression(&self, exp: &Expression) -> TypeId {
+        match exp {
+            Expression::Binary(binary) => {
+                let left_type = self.analyze_expression(&binary.left);
+                let right_type = self.analyze_expression(&binary.right);
+                self.check_operands(exp.span(), left_type, right_type);
+                left_type
             }
-            None => {}
+            Expression::Factor(factor) => self.analyze_expression_factor(factor),
         }
-        left_type
     }
 
-    fn analyze_expression_term(&self, exp: &ExpressionTerm) -> String {
-        let left_type = self.analyze_expression_factor(exp.left.inner());
-        match &exp.right {
-            Some(right) => {
-                let right_type = self.analyze_expression_term(right.inner());
-                if left_type != right_type {
-                    self.errors.borrow_mut().push(Positional::from_position(
-                        exp.left.position(),
-                        Error::UncompatableType { expected: left_type.clone(), got: right_type },
-                    ))
-                }
-            }
-            None => {}
+    /// Both sides of a binary operator should have the same type
+    fn check_operands(&self, span: Span, left: TypeId, right: TypeId) {
+        if !left.is_error() && !right.is_error() && left != right {
+            self.errors.borrow_mut().push(Positional::from_span(
+                span,
+                Error::UncompatableType { expected: self.type_name(left), got: self.type_name(right) },
+            ))
         }
-        left_type
     }
 
-    fn analyze_expression_factor(&self, exp: &ExpressionFactor) -> String {
+    fn analyze_expression_factor(&self, exp: &ExpressionFactor) -> TypeId {
         match exp {
             ExpressionFactor::Bracket(exp) => self.analyze_expression(exp),
-            ExpressionFactor::Constant(_) => SNLType::Integer.to_string(|r| Some(r.to_string())),
+            ExpressionFactor::Constant(_) => TypeId::INTEGER,
             ExpressionFactor::Variable(repr) => self.analyze_variable_represent(repr),
         }
     }
 
-    fn analyze_variable_represent(&self, repr: &VariableRepresent) -> String {
-        match self.symbols.borrow().query(&repr.base) {
-            Some(symbol) => {
-                // found symbol in table
-                match symbol {
-                    Symbol::Variable(current_type) => {
-                        // the only valid variable represent base is Variable
-                        let mut current_type = current_type.to_owned();
-                        // if visit exist
-                        if let Some(visit) = &repr.visit {
-                            // record field
-                            if let Some(field) = &visit.dot {
-                                // only record type can be visited
-                                if !current_type.starts_with("{") {
-                                    self.errors.borrow_mut().push(Positional::from_position(
-                                        field.position(),
-                                        Error::InvalidFieldIndexType(current_type.clone()),
-                                    ));
-                                    current_type.clear();
-                                } else {
-                                    let mut type_got = String::new();
-                                    let parts: Vec<_> = current_type[1..(current_type.len() - 1)].split(";").collect();
-                                    for p in parts {
-                                        let fields: Vec<_> = p.split(":").collect();
-                                        let variables: Vec<_> = fields[0].split(",").collect();
-                                        if variables.contains(&field.as_str()) {
-                                            type_got += fields[1];
-                                            break;
-                                        }
-                                    }
-                                    // field not found in record
-                                    if type_got == "" {
-                                        self.errors.borrow_mut().push(Positional::from_position(
-                                            field.position(),
-                                            Error::UndefinedRecordField(field.inner().clone()),
-                                        ));
-                                    }
-                                    current_type = type_got;
-                                }
-                            }
+    fn analyze_variable_represent(&self, repr: &VariableRepresent) -> TypeId {
+        self.refer(&repr.base);
+        let symbol = match self.symbols.borrow().query(&repr.base) {
+            Some(Symbol::Variable(ty)) => Ok(*ty),
+            Some(symbol) => Err(symbol.kind()),
+            None => {
+                // repr.base not found in symbol table
+                self.errors.borrow_mut().push(Positional::from_span(
+                    repr.base.span,
+                    Error::UndefinedIdentifier(repr.base.inner().clone()),
+                ));
+                return TypeId::ERROR;
+            }
+        };
+        // the only valid variable represent base is Variable
+        let mut current_type = match symbol {
+            Ok(ty) => ty,
+            Err(kind) => {
+                self.errors.borrow_mut().push(Positional::from_span(
+                    repr.base.span,
+                    Error::InvalidVariableRepresent(kind.to_owned()),
+                ));
+                return TypeId::ERROR;
+            }
+        };
 
-                            // array index
-                            if !current_type.is_empty() {
-                                if let Some(index) = &visit.sqbr {
-                                    // only array type can be indexed
-                                    if !current_type.starts_with("[") {
-                                        self.errors.borrow_mut().push(Positional::from_position(
-                                            index.left.position(),
-                                            Error::UnexpectedArrayIndex,
-                                        ));
-                                        current_type.clear();
-                                    } else {
-                                        // get index type
-                                        let index_type = self.analyze_expression(index);
-                                        // only integer is valid index type
-                                        if index_type != "integer" {
-                                            self.errors.borrow_mut().push(Positional::from_position(
-                                                index.left.position(),
-                                                Error::UncompatableType { expected: "integer".to_owned(), got: index_type },
-                                            ));
-                                            current_type.clear();
-                                        } else {
-                                            // return type
-                                            current_type = if current_type.ends_with("integer]") {
-                                                "integer".to_owned()
-                                            } else {
-                                                "char".to_owned()
-                                            }
-                                        }
-                                    }
-                                }
-                            }
+        // if visit exist
+        if let Some(visit) = &repr.visit {
+            // record field
+            if let Some(field) = &visit.dot {
+                let types = self.types.borrow();
+                current_type = if current_type.is_error() {
+                    TypeId::ERROR
+                } else if !types.is_record(current_type) {
+                    // only record type can be visited
+                    self.errors.borrow_mut().push(Positional::from_span(
+                        field.span,
+                        Error::InvalidFieldIndexType(types.name(current_type)),
+                    ));
+                    TypeId::ERROR
+                } else {
+                    match types.field(current_type, field) {
+                        Some(ty) => ty,
+                        None => {
+                            // field not found in record
+                            self.errors.borrow_mut().push(Positional::from_span(
+                                field.span,
+                                Error::UndefinedRecordField(field.inner().clone()),
+                            ));
+                            TypeId::ERROR
                         }
-                        current_type
                     }
-                    Symbol::Procedure(_) => {
-                        // procedure is not a valid **variable** represent
-                        self.errors.borrow_mut().push(Positional::from_position(
-                            repr.base.position(),
-                            Error::InvalidVariableRepresent("Procedure".to_owned()),
+                };
+            }
+
+            // array index
+            if let Some(index) = &visit.sqbr {
+                // get index type
+                let index_type = self.analyze_expression(index);
+                let array = self.types.borrow().array(current_type);
+                current_type = match array {
+                    _ if current_type.is_error() => TypeId::ERROR,
+                    // only array type can be indexed
+                    None => {
+                        self.errors.borrow_mut().push(Positional::from_span(
+                            index.span(),
+                            Error::UnexpectedArrayIndex,
                         ));
-                        String::new()
+                        TypeId::ERROR
                     }
-                    Symbol::Type(symbol_type) => {
-                        // type (alias) is not a valid **variable** represent
-                        self.errors.borrow_mut().push(Positional::from_position(
-                            repr.base.position(),
-                            Error::InvalidVariableRepresent(symbol_type.clone()),
-                        ));
-                        String::new()
+                    // only integer is valid index type
+                    Some(_) if index_type != TypeId::INTEGER => {
+                        if !index_type.is_error() {
+                            self.errors.borrow_mut().push(Positional::from_span(
+                                index.span(),
+                                Error::UncompatableType { expected: "integer".to_owned(), got: self.type_name(index_type) },
+                            ));
+                        }
+                        TypeId::ERROR
                     }
-                }
-            }
-            None => {
-                // repr.base not found in symbol table
-                self.errors.borrow_mut().push(Positional::from_position(
-                    repr.base.position(),
-                    Error::UndefinedIdentifier(repr.base.inner().clone()),
-                ));
-                String::new()
+                    Some((element, lower_bound, upper_bound)) => {
+                        // constant indexes are checked here, others at runtime
+                        if let Some(value) = index.constant() {
+                            if value < lower_bound as i64 || value > upper_bound as i64 {
+                                self.errors.borrow_mut().push(Positional::from_span(
+                                    index.span(),
+                                    Error::ArrayIndexOutbound(value, lower_bound, upper_bound),
+                                ));
+                            }
+                        }
+                        element
+                    }
+                };
             }
         }
+        current_type
     }
 
     fn analyze_analyze_relation(&self, rel: &RelationExpression) {
         let left = self.analyze_expression(&rel.left);
         let right = self.analyze_expression(&rel.right);
-        if left != "integer" && left != "char" {
-            self.errors.borrow_mut().push(Positional::from_position(
-                rel.left.left.position(),
+        if !left.is_scalar() && !left.is_error() {
+            self.errors.borrow_mut().push(Positional::from_span(
+                rel.left.span(),
                 Error::InvalidBoolExpression,
             ))
-        } else if right != "integer" && right != "char" {
-            self.errors.borrow_mut().push(Positional::from_position(
-                rel.right.left.position(),
+        } else if !right.is_scalar() && !right.is_error() {
+            self.errors.borrow_mut().push(Positional::from_span(
+                rel.right.span(),
                 Error::InvalidBoolExpression,
             ))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::Semantic;
+    use crate::error::{Error, Warning};
+    use crate::lint::{Level, Lint, Lints};
+    use snl_rdp::Parser;
+
+    fn analyze_with(source: &str, lints: Lints) -> (Vec<Error>, Vec<Warning>) {
+        let ast = Parser::new(snl_lexer::read_tokens(source).unwrap()).parse().unwrap();
+        let (errors, warnings) = Semantic::new(&ast).with_lints(lints).analyze();
+        (
+            errors.into_iter().map(|e| e.into_inner()).collect(),
+            warnings.into_iter().map(|w| w.into_inner()).collect(),
+        )
+    }
+
+    /// Unused and shadowed identifiers are allowed, they are all over the test programs
+    fn analyze(source: &str) -> (Vec<Error>, Vec<Warning>) {
+        let mut lints = Lints::default();
+        for lint in &[
+            Lint::UnusedVariable,
+            Lint::UnusedType,
+            Lint::UnusedProcedure,
+            Lint::UnusedParameter,
+            Lint::ShadowedIdentifier,
+        ] {
+            lints.set(*lint, Level::Allow);
+        }
+        analyze_with(source, lints)
+    }
+
+    #[test]
+    fn test_examples_without_errors() {
+        for source in &[
+            include_str!("../../examples/program1.snl"),
+            include_str!("../../examples/bubble.snl"),
+        ] {
+            let (errors, warnings) = analyze(source);
+            assert!(errors.is_empty() && warnings.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_alias_and_record_types() {
+        let (errors, warnings) = analyze("program p
+type t = array [1..3] of char;
+     r = record integer a; array [1..3] of char b; end;
+var r x;
+    t y;
+procedure q(t z);
+begin
+  write(z[1])
+end
+begin
+  x.b := y;
+  q(x.b);
+  x.a := x.b[2];
+  x.c := 1
+end.");
+        assert!(warnings.is_empty());
+        assert_eq!(errors.len(), 3);
+        assert!(matches!(
+            &errors[0],
+            Error::AssignTypeMismatch { expected, got } if expected == "integer" && got == "char"
+        ));
+        assert!(matches!(&errors[1], Error::UndefinedRecordField(field) if field == "c"));
+        assert!(matches!(&errors[2], Error::InvalidAssignee));
+    }
+
+    #[test]
+    fn test_array_index_outbound() {
+        let (errors, warnings) = analyze("program p
+var array [1..3] of integer a;
+    integer i;
+begin
+  read(i);
+  a[0] := 1;
+  a[1 + 2] := 1;
+  a[(2 - 1) * 4] := 1;
+  a[i + 4] := 1;
+  a[4 / 0] := 1
+end.");
+        assert!(warnings.is_empty());
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(errors[0], Error::ArrayIndexOutbound(0, 1, 3)));
+        assert!(matches!(errors[1], Error::ArrayIndexOutbound(4, 1, 3)));
+    }
+
+    #[test]
+    fn test_var_parameter_argument() {
+        let (errors, warnings) = analyze("program p
+var integer a;
+    array [1..2] of integer b;
+procedure q(var integer x, y);
+begin
+  x := y
+end
+begin
+  q(a, b[1]);
+  q(a, 1);
+  q(a + 1, (a))
+end.");
+        assert!(warnings.is_empty());
+        let names: Vec<_> = errors.iter().map(|e| match e {
+            Error::InvalidVarArgument { name, .. } => name.as_str(),
+            e => panic!("unexpected error {}", e),
+        }).collect();
+        assert_eq!(names, vec!["y", "x", "y"]);
+    }
+
+    #[test]
+    fn test_return() {
+        let (errors, warnings) = analyze("program p
+var integer a;
+    char c;
+procedure q(integer x);
+procedure r();
+begin
+  return(c)
+end
+begin
+  if x < 1 then
+    return(x)
+  else
+    return(c)
+  fi;
+  write(x)
+end
+begin
+  read(a);
+  read(c);
+  while a < 1 do
+    return(c);
+    a := 1;
+    a := 2
+  endwh;
+  return(a)
+end.");
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            &errors[0],
+            Error::ReturnTypeMismatch { expected, got, .. } if expected == "integer" && got == "char"
+        ));
+        // returns of the main program are checked on their own
+        assert!(matches!(
+            &errors[1],
+            Error::ReturnTypeMismatch { expected, got, .. } if expected == "char" && got == "integer"
+        ));
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings.iter().all(|warning| matches!(warning, Warning::UnreachableStatement)));
+    }
+
+    #[test]
+    fn test_return_context() {
+        // returns are allowed in procedures and in the main program, at any depth
+        let (errors, warnings) = analyze("program p
+var integer a;
+procedure q(integer x);
+procedure r(integer y);
+begin
+  while y < y do
+    return(y)
+  endwh
+end
+begin
+  r(x);
+  if x < 1 then
+    return(x)
+  else
+    write(x)
+  fi
+end
+begin
+  read(a);
+  q(a);
+  if a < 1 then
+    while a < 1 do
+      return(a)
+    endwh
+  else
+    return(0)
+  fi
+end.");
+        assert!(errors.is_empty() && warnings.is_empty(), "{:?} {:?}", errors, warnings);
+    }
+
+    #[test]
+    fn test_uninitialized_variable() {
+        let (errors, warnings) = analyze("program p
+type int = integer;
+var integer a, b, c, d;
+    int e, f;
+    array [1..2] of integer g;
+procedure set(var integer x);
+begin
+  x := 1
+end
+procedure init();
+begin
+  f := 1
+end
+begin
+  read(a);
+  if a < 1 then
+    b := 1;
+    c := 1
+  else
+    b := 2;
+    return(0)
+  fi;
+  while a < b do
+    d := 1
+  endwh;
+  write(a + b + c + d + d);
+  set(e);
+  write(e + g[1]);
+  init();
+  write(f)
+end.");
+        assert!(errors.is_empty());
+        let names: Vec<_> = warnings.iter().map(|w| match w {
+            Warning::UninitializedVariable { name, .. } => name.as_str(),
+            w => panic!("unexpected warning {}", w),
+        }).collect();
+        assert_eq!(names, vec!["d"]);
+    }
+
+    #[test]
+    fn test_uninitialized_after_call() {
+        // calls only assign what the callee, or a procedure it calls, assigns
+        let (errors, warnings) = analyze("program p
+var integer i, j, k;
+procedure seti();
+begin
+  i := 1
+end
+procedure setk();
+begin
+  seti();
+  k := 1
+end
+procedure other();
+var integer j;
+begin
+  j := 1;
+  write(j)
+end
+begin
+  setk();
+  other();
+  write(i);
+  write(k);
+  write(j)
+end.");
+        assert!(errors.is_empty());
+        let names: Vec<_> = warnings.iter().map(|w| match w {
+            Warning::UninitializedVariable { name, .. } => name.as_str(),
+            w => panic!("unexpected warning {}", w),
+        }).collect();
+        assert_eq!(names, vec!["j"]);
+    }
+
+    #[test]
+    fn test_unused_and_shadowed() {
+        let (errors, warnings) = analyze_with("program p
+type t = integer;
+     u = char;
+var t a, b;
+procedure q(integer x, y);
+var char a;
+begin
+  read(a);
+  write(y);
+  q(1, 2)
+end
+procedure r();
+begin
+  write(1)
+end
+begin
+  read(b);
+  r()
+end.", Lints::default());
+        assert!(errors.is_empty());
+        let warnings: Vec<_> = warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(warnings, vec![
+            "Identifier 'a' shadows a declaration of an enclosing block",
+            "Parameter 'x' is never used",
+            "Type 'u' is never used",
+            "Variable 'a' is never used",
+            "Procedure 'q' is never called",
+        ]);
+    }
+}
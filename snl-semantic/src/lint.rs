@@ -0,0 +1,132 @@
+use crate::error::Warning;
+use snl_utils::diagnostic::Severity;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Warnings of the semantic analysis, each one can be allowed, warned or denied on its own
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Lint {
+    UnreachableStatement,
+    UninitializedVariable,
+    UnusedVariable,
+    UnusedType,
+    UnusedProcedure,
+    UnusedParameter,
+    ShadowedIdentifier,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 7] = [
+        Lint::UnreachableStatement,
+        Lint::UninitializedVariable,
+        Lint::UnusedVariable,
+        Lint::UnusedType,
+        Lint::UnusedProcedure,
+        Lint::UnusedParameter,
+        Lint::ShadowedIdentifier,
+    ];
+
+    /// Name of the lint on the command line, e.g. `unused-variable`
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::UnreachableStatement => "unreachable-statement",
+            Lint::UninitializedVariable => "uninitialized-variable",
+            Lint::UnusedVariable => "unused-variable",
+            Lint::UnusedType => "unused-type",
+            Lint::UnusedProcedure => "unused-procedure",
+            Lint::UnusedParameter => "unused-parameter",
+            Lint::ShadowedIdentifier => "shadowed-identifier",
+        }
+    }
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Lint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Lint::ALL
+            .iter()
+            .copied()
+            .find(|lint| lint.name() == s)
+            .ok_or_else(|| format!("Unknown lint '{}'", s))
+    }
+}
+
+/// What to do with the diagnostics of a lint
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Level {
+    /// Not reported at all
+    Allow,
+    Warn,
+    /// Reported as an error
+    Deny,
+}
+
+/// Level of every lint, all of them are warned by default
+#[derive(Debug, Clone, Default)]
+pub struct Lints {
+    levels: HashMap<Lint, Level>,
+}
+
+impl Lints {
+    pub fn level(&self, lint: Lint) -> Level {
+        self.levels.get(&lint).copied().unwrap_or(Level::Warn)
+    }
+
+    pub fn set(&mut self, lint: Lint, level: Level) {
+        self.levels.insert(lint, level);
+    }
+
+    pub fn set_all(&mut self, level: Level) {
+        for lint in Lint::ALL.iter() {
+            self.set(*lint, level);
+        }
+    }
+
+    pub fn is_allowed(&self, warning: &Warning) -> bool {
+        self.level(warning.lint()) == Level::Allow
+    }
+
+    /// Severity `warning` is reported with, denied lints are errors
+    pub fn severity(&self, warning: &Warning) -> Severity {
+        match self.level(warning.lint()) {
+            Level::Deny => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Warning;
+    use crate::lint::{Level, Lint, Lints};
+    use snl_utils::diagnostic::Severity;
+
+    #[test]
+    fn test_lint_name() {
+        for lint in Lint::ALL.iter() {
+            assert_eq!(lint.name().parse::<Lint>(), Ok(*lint));
+        }
+        assert!("unused".parse::<Lint>().is_err());
+    }
+
+    #[test]
+    fn test_levels() {
+        let mut lints = Lints::default();
+        let unused = Warning::UnusedVariable("a".to_owned());
+        assert_eq!(lints.severity(&unused), Severity::Warning);
+
+        lints.set_all(Level::Allow);
+        lints.set(Lint::UnusedVariable, Level::Deny);
+        assert_eq!(lints.severity(&unused), Severity::Error);
+        assert!(!lints.is_allowed(&unused));
+        assert!(lints.is_allowed(&Warning::UnreachableStatement));
+    }
+}
@@ -1,11 +1,34 @@
 use std::collections::HashMap;
 use std::cmp::min;
+use crate::types::TypeId;
+use snl_utils::span::Span;
 
 #[derive(Debug)]
 pub enum Symbol {
-    Variable(String),
-    Procedure(Vec<String>),
-    Type(String),
+    Variable(TypeId),
+    Procedure(Vec<Parameter>),
+    Type(TypeId),
+}
+
+/// Parameter in the signature of a procedure
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    pub name: String,
+    pub ty: TypeId,
+    /// Passed by reference, the argument has to be assignable
+    pub is_var: bool,
+    pub span: Span,
+}
+
+impl Symbol {
+    /// Kind of the symbol, used in error messages
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Symbol::Variable(_) => "Variable",
+            Symbol::Procedure(_) => "Procedure",
+            Symbol::Type(_) => "Type",
+        }
+    }
 }
 
 pub struct SymbolTable<T> {
@@ -40,7 +63,7 @@ impl<T> SymbolTable<T> {
     }
 
     pub fn query_at(&self, key: &str, level: usize) -> Option<&T> {
-        if self.inner.len() == 0 {
+        if self.inner.is_empty() {
             return None;
         }
 
@@ -59,27 +82,12 @@ impl<T> SymbolTable<T> {
         self.inner.last_mut().unwrap().insert(key, value);
     }
 
+    /// Look for symbol in current tier only
+    pub fn query_own(&self, key: &str) -> Option<&T> {
+        self.inner.last().unwrap().get(key)
+    }
+
     pub fn has_own_property(&self, key: &str) -> bool {
         self.inner.last().unwrap().contains_key(key)
     }
 }
-
-impl SymbolTable<Symbol> {
-    pub fn query_type(&self, ty: &str) -> Option<&str> {
-        let ty = if ty.starts_with("#") {
-            &ty[1..]
-        } else {
-            ty
-        };
-        match self.query(ty) {
-            Some(Symbol::Type(ty)) => {
-                if ty.starts_with("#") {
-                    self.query_type(&ty)
-                } else {
-                    Some(ty.as_str())
-                }
-            }
-            _ => None,
-        }
-    }
-}
\ No newline at end of file
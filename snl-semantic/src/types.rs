@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+/// Handle of a type interned in a `TypeTable`,
+/// two handles of the same table are equal iff the types are structurally equal
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TypeId(u32);
+
+impl TypeId {
+    pub const INTEGER: TypeId = TypeId(0);
+    pub const CHAR: TypeId = TypeId(1);
+    /// Type of anything which failed to analyze, errors about it are already reported
+    pub const ERROR: TypeId = TypeId(2);
+
+    #[inline]
+    pub fn is_error(self) -> bool {
+        self == TypeId::ERROR
+    }
+
+    /// integer or char
+    #[inline]
+    pub fn is_scalar(self) -> bool {
+        self == TypeId::INTEGER || self == TypeId::CHAR
+    }
+}
+
+/// Structural descriptor of a type, aliases are resolved before interning
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Type {
+    Integer,
+    Char,
+    Error,
+    Array { element: TypeId, lower_bound: usize, upper_bound: usize },
+    /// Fields in declaration order
+    Record(Vec<(String, TypeId)>),
+}
+
+pub struct TypeTable {
+    types: Vec<Type>,
+    ids: HashMap<Type, TypeId>,
+}
+
+impl Default for TypeTable {
+    fn default() -> Self {
+        TypeTable::new()
+    }
+}
+
+impl TypeTable {
+    pub fn new() -> Self {
+        let mut table = Self { types: Vec::new(), ids: HashMap::new() };
+        table.intern(Type::Integer);
+        table.intern(Type::Char);
+        table.intern(Type::Error);
+        table
+    }
+
+    /// Get the id of `ty`, adding it to the table if it is new
+    pub fn intern(&mut self, ty: Type) -> TypeId {
+        if let Some(id) = self.ids.get(&ty) {
+            return *id;
+        }
+        let id = TypeId(self.types.len() as u32);
+        self.types.push(ty.clone());
+        self.ids.insert(ty, id);
+        id
+    }
+
+    pub fn get(&self, id: TypeId) -> &Type {
+        &self.types[id.0 as usize]
+    }
+
+    /// Element type and bounds of an array type
+    pub fn array(&self, id: TypeId) -> Option<(TypeId, usize, usize)> {
+        match self.get(id) {
+            Type::Array { element, lower_bound, upper_bound } => Some((*element, *lower_bound, *upper_bound)),
+            _ => None,
+        }
+    }
+
+    pub fn is_record(&self, id: TypeId) -> bool {
+        matches!(self.get(id), Type::Record(_))
+    }
+
+    /// Type of `field` if `id` is a record containing it
+    pub fn field(&self, id: TypeId, field: &str) -> Option<TypeId> {
+        match self.get(id) {
+            Type::Record(fields) => fields.iter().find(|(name, _)| name == field).map(|(_, ty)| *ty),
+            _ => None,
+        }
+    }
+
+    /// Type written the way it is declared in SNL, used in error messages
+    pub fn name(&self, id: TypeId) -> String {
+        match self.get(id) {
+            Type::Integer => "integer".to_owned(),
+            Type::Char => "char".to_owned(),
+            Type::Error => "{unknown}".to_owned(),
+            Type::Array { element, lower_bound, upper_bound } => {
+                format!("array [{}..{}] of {}", lower_bound, upper_bound, self.name(*element))
+            }
+            Type::Record(fields) => {
+                let mut result = "record".to_owned();
+                for (name, ty) in fields {
+                    result += &format!(" {} {};", self.name(*ty), name);
+                }
+                result + " end"
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Type, TypeId, TypeTable};
+
+    #[test]
+    fn test_intern() {
+        let mut table = TypeTable::new();
+        assert_eq!(table.intern(Type::Integer), TypeId::INTEGER);
+        assert_eq!(table.intern(Type::Char), TypeId::CHAR);
+
+        let array = Type::Array { element: TypeId::CHAR, lower_bound: 1, upper_bound: 10 };
+        let id = table.intern(array.clone());
+        assert_eq!(table.intern(array), id);
+        assert_ne!(table.intern(Type::Array { element: TypeId::CHAR, lower_bound: 0, upper_bound: 10 }), id);
+        assert_eq!(table.array(id), Some((TypeId::CHAR, 1, 10)));
+        assert_eq!(table.array(TypeId::INTEGER), None);
+    }
+
+    #[test]
+    fn test_record_fields() {
+        let mut table = TypeTable::new();
+        let array = table.intern(Type::Array { element: TypeId::INTEGER, lower_bound: 0, upper_bound: 3 });
+        let record = table.intern(Type::Record(vec![
+            ("a".to_owned(), TypeId::INTEGER),
+            ("b".to_owned(), array),
+        ]));
+        assert!(table.is_record(record));
+        assert_eq!(table.field(record, "b"), Some(array));
+        assert_eq!(table.field(record, "c"), None);
+        assert_eq!(table.field(array, "a"), None);
+    }
+
+    #[test]
+    fn test_name() {
+        let mut table = TypeTable::new();
+        let array = table.intern(Type::Array { element: TypeId::INTEGER, lower_bound: 0, upper_bound: 3 });
+        let record = table.intern(Type::Record(vec![
+            ("a".to_owned(), TypeId::CHAR),
+            ("b".to_owned(), array),
+        ]));
+        assert_eq!(table.name(TypeId::INTEGER), "integer");
+        assert_eq!(table.name(array), "array [0..3] of integer");
+        assert_eq!(table.name(record), "record char a; array [0..3] of integer b; end");
+    }
+}
@@ -1,31 +1,31 @@
 use serde::Serialize;
 use std::ops::Deref;
+use crate::span::Span;
 use crate::token::Token;
-use std::collections::{BTreeMap, BTreeSet};
 use std::str::FromStr;
+use std::fmt::{Display, Formatter};
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, PartialEq, Serialize)]
 pub struct Positional<T> {
-    pub line: u32,
-    pub column: u32,
+    pub span: Span,
     inner: T,
 }
 
 impl<T> Positional<T> {
     pub fn new(line: u32, column: u32, inner: T) -> Self {
-        Self {
-            line,
-            column,
-            inner,
-        }
+        Positional::from_span(Span::point(line, column), inner)
     }
 
     pub fn from_position((line, column): (u32, u32), inner: T) -> Self {
         Positional::new(line, column, inner)
     }
 
+    pub fn from_span(span: Span, inner: T) -> Self {
+        Self { span, inner }
+    }
+
     pub fn from_token(token: &Token, inner: T) -> Self {
-        Positional::new(token.line, token.column, inner)
+        Positional::from_span(token.span, inner)
     }
 
     pub fn dump(inner: T) -> Self {
@@ -40,18 +40,31 @@ impl<T> Positional<T> {
         self.inner
     }
 
+    /// Convert the inner value, keeping the span
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Positional<U> {
+        Positional::from_span(self.span, f(self.inner))
+    }
+
     pub fn position(&self) -> (u32, u32) {
-        (self.line, self.column)
+        self.span.position()
+    }
+
+    pub fn line(&self) -> u32 {
+        self.span.start.line
+    }
+
+    pub fn column(&self) -> u32 {
+        self.span.start.column
     }
 }
 
 impl Positional<String> {
     pub fn from_token_image(token: &Token) -> Self {
-        Positional::new(token.line, token.column, token.image.clone())
+        Positional::from_span(token.span, token.image.clone())
     }
 
     pub fn from_token_image_raw(token: Token) -> Self {
-        Positional::new(token.line, token.column, token.image)
+        Positional::from_span(token.span, token.image)
     }
 }
 
@@ -65,21 +78,21 @@ impl<T> Deref for Positional<T> {
 
 pub type PositionalVec<T> = Vec<Positional<T>>;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, PartialEq, Serialize)]
 pub struct Program {
     pub name: String,
     pub declare: ProgramDeclare,
     pub body: StatementList,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, PartialEq, Serialize)]
 pub struct ProgramDeclare {
     pub type_declare: PositionalVec<TypeDeclare>,
     pub variable_declare: PositionalVec<TypedIdentifiers>,
     pub procedure_declare: PositionalVec<ProcedureDeclare>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, PartialEq, Serialize)]
 pub struct TypeDeclare {
     pub base: Positional<SNLType>,
     pub name: String,
@@ -93,11 +106,11 @@ impl TypeDeclare {
 
     #[inline]
     pub fn base(&self) -> Positional<&SNLType> {
-        Positional::from_position(self.base.position(), &self.base.inner)
+        Positional::from_span(self.base.span, &self.base.inner)
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, PartialEq, Serialize)]
 pub struct ProcedureDeclare {
     pub name: String,
     pub params: PositionalVec<Param>,
@@ -111,18 +124,18 @@ impl ProcedureDeclare {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, PartialEq, Serialize)]
 #[serde(tag = "type")]
 pub enum SNLBaseType {
     Integer,
     Char,
 }
 
-impl ToString for SNLBaseType {
-    fn to_string(&self) -> String {
+impl Display for SNLBaseType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            SNLBaseType::Integer => "integer".to_string(),
-            SNLBaseType::Char => "char".to_string(),
+            SNLBaseType::Integer => write!(f, "integer"),
+            SNLBaseType::Char => write!(f, "char"),
         }
     }
 }
@@ -139,7 +152,7 @@ impl FromStr for SNLBaseType {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, PartialEq, Serialize)]
 #[serde(tag = "type", content = "value")]
 pub enum SNLType {
     Integer,
@@ -158,138 +171,22 @@ impl From<SNLBaseType> for SNLType {
     }
 }
 
-/// Begin with:
-///  letter: integer or char
-/// `[`: array
-/// `{`: record
-/// `#`: custom
-impl SNLType {
-    pub fn to_string<F>(&self, query: F) -> String
-        where F: Fn(&str) -> Option<String> {
-        let (result, _) = self.to_string_inner(query);
-        result
-    }
-
-    fn to_string_inner<F>(&self, mut query: F) -> (String, F)
-        where F: Fn(&str) -> Option<String>
-    {
-        let str = match self {
-            SNLType::Integer => "integer".to_string(),
-            SNLType::Char => "char".to_string(),
-            SNLType::Array(array) => array.to_string(),
-            SNLType::Record(record) => {
-                let mut fields: BTreeMap<String, BTreeSet<&str>> = Default::default();
-                for r in record.iter() {
-                    let ty = r.type_name.to_string_inner(query);
-                    query = ty.1;
-                    let ty = ty.0;
-                    if !fields.contains_key(&ty) {
-                        fields.insert(ty, r.identifiers.iter().map(|r| r.inner.as_str()).collect());
-                    } else {
-                        for f in r.identifiers.iter() {
-                            fields.get_mut(&ty).unwrap().insert(f.inner());
-                        }
-                    }
-                }
-
-                let mut result = "{".to_owned();
-                let mut is_first = true;
-                for (type_name, variables) in fields {
-                    if !is_first {
-                        result += ";";
-                    } else {
-                        is_first = false;
-                    }
-
-                    let mut is_first = true;
-                    for var in variables {
-                        if !is_first {
-                            result += ",";
-                        } else {
-                            is_first = false;
-                        }
-                        result += var;
-                    }
-                    result += ":";
-                    result += &type_name;
-                }
-                result += "}";
-                result
-            }
-            SNLType::Others(others) => {
-                match query(others) {
-                    Some(got) => got,
-                    None => format!("#{}", others),
-                }
-            }
-        };
-        (str, query)
-    }
-}
-
-impl FromStr for SNLType {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s == "integer" {
-            Ok(SNLType::Integer)
-        } else if s == "char" {
-            Ok(SNLType::Char)
-        } else if s.starts_with("[") {
-            let balance = if s.ends_with("]") {
-                s.len()
-            } else {
-                let mut balance = 0;
-                for (offset, ch) in s.char_indices() {
-                    if offset != 0 && balance == 0 {
-                        balance = offset;
-                        break;
-                    }
-
-                    if ch == '[' {
-                        balance += 1;
-                    } else if ch == ']' {
-                        balance -= 1;
-                    }
-                }
-                balance
-            };
-            let s = &s[1..(balance - 1)];
-            // low..top
-            let splits: Vec<_> = s.split(';').collect();
-            let bounds: Vec<_> = splits[0].split("..").collect();
-            Ok(SNLType::Array(SNLTypeArray {
-                base: SNLBaseType::from_str(splits[1])?,
-                lower_bound: usize::from_str(bounds[0]).unwrap(),
-                upper_bound: usize::from_str(bounds[1]).unwrap(),
-            }))
-        } else if s.starts_with("{") {
-            unimplemented!()
-        } else if s.starts_with("#") {
-            let (_, s) = s.split_at(1);
-            Ok(SNLType::Others(s.to_owned()))
-        } else {
-            unreachable!()
-        }
-    }
-}
-
-#[derive(Debug, Serialize)]
+#[derive(Debug, PartialEq, Serialize)]
 pub struct SNLTypeArray {
     pub base: SNLBaseType,
     pub lower_bound: usize,
     pub upper_bound: usize,
 }
 
-impl ToString for SNLTypeArray {
-    fn to_string(&self) -> String {
-        format!("[{}..{};{}]", self.lower_bound, self.upper_bound, self.base.to_string())
+impl Display for SNLTypeArray {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}..{};{}]", self.lower_bound, self.upper_bound, self.base)
     }
 }
 
 pub type SNLTypeRecord = Vec<TypedIdentifiers>;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, PartialEq, Serialize)]
 pub struct TypedIdentifiers {
     pub type_name: Positional<SNLType>,
     pub identifiers: PositionalVec<String>,
@@ -297,7 +194,7 @@ pub struct TypedIdentifiers {
 
 pub type StatementList = Vec<Statement>;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, PartialEq, Serialize)]
 #[serde(tag = "type", content = "value")]
 pub enum Statement {
     Conditional(ConditionalStatement),
@@ -307,28 +204,50 @@ pub enum Statement {
     Return(Expression),
     Assign(AssignStatement),
     Call(Positional<CallStatement>),
+    /// Placeholder left by an error-recovering parser where a statement failed to parse
+    Error(Positional<String>),
+}
+
+impl Statement {
+    /// Position of the statement, conditions are used for `if` and `while`
+    pub fn position(&self) -> (u32, u32) {
+        self.span().position()
+    }
+
+    /// Span of the main part of the statement, conditions are used for `if` and `while`
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Conditional(con) => con.condition.left.span().to(con.condition.right.span()),
+            Statement::Loop(lo) => lo.condition.left.span().to(lo.condition.right.span()),
+            Statement::Input(input) => input.span,
+            Statement::Output(exp) | Statement::Return(exp) => exp.span(),
+            Statement::Assign(assign) => assign.variable.base.span.to(assign.value.span()),
+            Statement::Call(call) => call.span,
+            Statement::Error(error) => error.span,
+        }
+    }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, PartialEq, Serialize)]
 pub struct ConditionalStatement {
     pub condition: RelationExpression,
     pub body: StatementList,
     pub else_body: StatementList,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, PartialEq, Serialize)]
 pub struct LoopStatement {
     pub condition: RelationExpression,
     pub body: StatementList,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, PartialEq, Serialize)]
 pub struct AssignStatement {
     pub variable: VariableRepresent,
     pub value: Expression,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, PartialEq, Serialize)]
 pub struct CallStatement {
     pub name: String,
     pub params: Vec<Expression>,
@@ -340,18 +259,120 @@ impl CallStatement {
     }
 }
 
-#[derive(Debug, Serialize)]
-pub struct ExpressionTemplate<Next> {
-    pub left: Positional<Next>,
-    pub op: Option<String>,
-    pub right: Option<Positional<Box<Self>>>,
+/// Arithmetic operator of a binary expression
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub enum BinaryOperator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl BinaryOperator {
+    /// Apply the operator to constants the way the interpreter does, wrapping on overflow.
+    /// Division by zero is `None`, it is left to be reported at runtime.
+    pub fn eval(self, left: i64, right: i64) -> Option<i64> {
+        match self {
+            BinaryOperator::Add => Some(left.wrapping_add(right)),
+            BinaryOperator::Sub => Some(left.wrapping_sub(right)),
+            BinaryOperator::Mul => Some(left.wrapping_mul(right)),
+            BinaryOperator::Div if right == 0 => None,
+            BinaryOperator::Div => Some(left.wrapping_div(right)),
+        }
+    }
+}
+
+impl Display for BinaryOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Sub => "-",
+            BinaryOperator::Mul => "*",
+            BinaryOperator::Div => "/",
+        })
+    }
 }
 
-pub type Expression = ExpressionTemplate<ExpressionTerm>;
+impl FromStr for BinaryOperator {
+    type Err = ();
 
-pub type ExpressionTerm = ExpressionTemplate<ExpressionFactor>;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "+" => Self::Add,
+            "-" => Self::Sub,
+            "*" => Self::Mul,
+            "/" => Self::Div,
+            _ => unreachable!()
+        })
+    }
+}
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "type", content = "value")]
+pub enum Expression {
+    Binary(Box<BinaryExpression>),
+    Factor(Positional<ExpressionFactor>),
+}
+
+/// `left op right`, parsers build chains of operators of the same precedence to the left,
+/// so `a - b - c` has `a - b` as its left operand
+#[derive(Debug, PartialEq, Serialize)]
+pub struct BinaryExpression {
+    pub left: Expression,
+    pub op: BinaryOperator,
+    pub right: Expression,
+}
+
+impl Expression {
+    pub fn binary(left: Expression, op: BinaryOperator, right: Expression) -> Self {
+        Expression::Binary(Box::new(BinaryExpression { left, op, right }))
+    }
+
+    /// Span from the first to the last operand
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Binary(binary) => binary.left.span().to(binary.right.span()),
+            Expression::Factor(factor) => factor.span,
+        }
+    }
+
+    pub fn position(&self) -> (u32, u32) {
+        self.span().position()
+    }
+
+    /// Returns the variable represent if the expression is a single variable,
+    /// which is the only kind of expression that can be passed by reference.
+    pub fn as_variable(&self) -> Option<&VariableRepresent> {
+        match self {
+            Expression::Factor(factor) => match factor.inner() {
+                ExpressionFactor::Variable(repr) => Some(repr),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Value of the expression if it only consists of constants, evaluated the way
+    /// the interpreter does. Division by zero is left to be reported at runtime.
+    pub fn constant(&self) -> Option<i64> {
+        match self {
+            Expression::Binary(binary) => binary.op.eval(binary.left.constant()?, binary.right.constant()?),
+            Expression::Factor(factor) => factor.constant(),
+        }
+    }
+}
+
+/// Written the way it is in SNL source, brackets are kept as factors
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expression::Binary(binary) => write!(f, "{} {} {}", binary.left, binary.op, binary.right),
+            Expression::Factor(factor) => write!(f, "{}", factor.inner()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
 #[serde(tag = "type", content = "value")]
 pub enum ExpressionFactor {
     Bracket(Box<Expression>),
@@ -359,64 +380,68 @@ pub enum ExpressionFactor {
     Variable(VariableRepresent),
 }
 
-#[derive(Debug, Serialize)]
+impl Display for ExpressionFactor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExpressionFactor::Bracket(exp) => write!(f, "({})", exp),
+            ExpressionFactor::Constant(c) => write!(f, "{}", c),
+            ExpressionFactor::Variable(repr) => write!(f, "{}", repr),
+        }
+    }
+}
+
+impl ExpressionFactor {
+    pub fn constant(&self) -> Option<i64> {
+        match self {
+            ExpressionFactor::Bracket(exp) => exp.constant(),
+            ExpressionFactor::Constant(c) => Some(*c as i64),
+            ExpressionFactor::Variable(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
 pub struct RelationExpression {
     pub left: Expression,
     pub op: String,
     pub right: Expression,
 }
 
-#[derive(Debug, Serialize)]
+impl Display for RelationExpression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.left, self.op, self.right)
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
 pub struct Param {
     pub is_var: bool,
     pub definition: TypedIdentifiers,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, PartialEq, Serialize)]
 pub struct VariableVisit {
     pub dot: Option<Positional<String>>,
     pub sqbr: Option<Box<Expression>>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, PartialEq, Serialize)]
 pub struct VariableRepresent {
     pub base: Positional<String>,
     pub visit: Option<VariableVisit>,
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::ast::{SNLType, SNLTypeArray, SNLBaseType, TypedIdentifiers, Positional};
-    use std::str::FromStr;
-
-    #[test]
-    fn test_type_to_signature() {
-        assert_eq!(SNLType::Integer.to_string(|r| Some(r.to_string())), "integer");
-        assert_eq!(SNLType::Char.to_string(|r| Some(r.to_string())), "char");
-        assert_eq!(SNLType::Others("others".to_owned()).to_string(|r| Some(r.to_string())), "#others");
-        assert_eq!(SNLType::Array(SNLTypeArray {
-            base: SNLBaseType::Integer,
-            lower_bound: 0,
-            upper_bound: 10,
-        }).to_string(|r| Some(r.to_string())), "[0..10;integer]");
-        assert_eq!(SNLType::Record(vec![
-            TypedIdentifiers {
-                type_name: SNLType::Integer,
-                identifiers: vec![Positional::dump("a".to_owned()), Positional::dump("c".to_owned())],
-            },
-            TypedIdentifiers { type_name: SNLType::Integer, identifiers: vec![Positional::dump("b".to_owned())] },
-        ]).to_string(|r| Some(r.to_string())), "{a,b,c:integer}");
-    }
-
-    #[test]
-    fn test_type_from_signature() {
-        assert_eq!(SNLType::Integer, SNLType::from_str("integer").unwrap());
-        assert_eq!(SNLType::Char, SNLType::from_str("char").unwrap());
-        assert_eq!(SNLType::Others("test".to_owned()), SNLType::from_str("#test").unwrap());
-        assert_eq!(SNLType::Array(SNLTypeArray {
-            base: SNLBaseType::Integer,
-            lower_bound: 0,
-            upper_bound: 10,
-        }), SNLType::from_str("[0..10;integer]").unwrap());
-    }
-}
\ No newline at end of file
+impl Display for VariableRepresent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.base.inner())?;
+        if let Some(visit) = &self.visit {
+            if let Some(field) = &visit.dot {
+                write!(f, ".{}", field.inner())?;
+            }
+            if let Some(index) = &visit.sqbr {
+                write!(f, "[{}]", index)?;
+            }
+        }
+        Ok(())
+    }
+}
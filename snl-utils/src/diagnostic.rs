@@ -0,0 +1,470 @@
+use crate::ast::Positional;
+use crate::error::{LexError, RuntimeError, SyntaxError};
+use crate::span::{FileId, Span};
+use std::fmt::{Display, Write};
+use std::ops::Range;
+
+/// How serious a diagnostic is
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    fn label(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        }
+    }
+
+    fn color(self) -> &'static str {
+        match self {
+            Severity::Error => "\x1b[1;31m",
+            Severity::Warning => "\x1b[1;33m",
+            Severity::Note => "\x1b[1;32m",
+        }
+    }
+}
+
+/// Extra information attached to a diagnostic, shown with a snippet when it has a position
+#[derive(Debug, Clone, PartialEq)]
+pub struct Note {
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+/// Error kinds which can be reported as a diagnostic
+pub trait Diagnose: Display {
+    /// Stable code of the error kind, e.g. `E0201`
+    fn code(&self) -> &'static str;
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn notes(&self) -> Vec<Note> {
+        Vec::new()
+    }
+}
+
+/// A positioned message to be rendered with the source it refers to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+    pub notes: Vec<Note>,
+}
+
+impl<T: Diagnose> From<&Positional<T>> for Diagnostic {
+    fn from(error: &Positional<T>) -> Self {
+        Diagnostic {
+            severity: error.severity(),
+            code: error.code(),
+            message: error.to_string(),
+            span: error.span,
+            notes: error.notes(),
+        }
+    }
+}
+
+/// Columns a tab is expanded to when source lines are shown
+const TAB_WIDTH: usize = 4;
+
+const BOLD: &str = "\x1b[1m";
+const GUTTER: &str = "\x1b[1;34m";
+const RESET: &str = "\x1b[0m";
+
+/// Renders diagnostics in the style of rustc:
+///
+/// ```text
+/// error[E0301]: Duplicated identifier 'a'
+///  --> example.snl:3:16
+///   |
+/// 3 | var integer a, a;
+///   |                ^
+/// ```
+pub struct Renderer<'a> {
+    /// Name and lines of each file, indexed by `FileId`
+    files: Vec<(&'a str, Vec<&'a str>)>,
+    color: bool,
+}
+
+impl<'a> Renderer<'a> {
+    /// Renderer with `source` as the default file
+    pub fn new(source: &'a str, file: &'a str) -> Self {
+        Self {
+            files: vec![(file, source.lines().collect())],
+            color: false,
+        }
+    }
+
+    /// Add another source file, spans refer to it with the returned id
+    pub fn add_file(&mut self, source: &'a str, file: &'a str) -> FileId {
+        self.files.push((file, source.lines().collect()));
+        FileId(self.files.len() as u32 - 1)
+    }
+
+    /// Use ANSI escape codes for colours
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let mut out = String::new();
+        let gutter = self.gutter_width(diagnostic);
+        writeln!(
+            out,
+            "{}{}[{}]{}{}: {}{}",
+            self.paint(diagnostic.severity.color()),
+            diagnostic.severity.label(),
+            diagnostic.code,
+            self.paint(RESET),
+            self.paint(BOLD),
+            diagnostic.message,
+            self.paint(RESET),
+        ).unwrap();
+        self.snippet(&mut out, gutter, diagnostic.span, diagnostic.severity);
+
+        for note in diagnostic.notes.iter() {
+            match note.span {
+                Some(span) => {
+                    writeln!(
+                        out,
+                        "{}note{}{}: {}{}",
+                        self.paint(Severity::Note.color()),
+                        self.paint(RESET),
+                        self.paint(BOLD),
+                        note.message,
+                        self.paint(RESET),
+                    ).unwrap();
+                    self.snippet(&mut out, gutter, span, Severity::Note);
+                }
+                None => {
+                    writeln!(
+                        out,
+                        "{:gutter$} {}={} {}note{}: {}",
+                        "",
+                        self.paint(GUTTER),
+                        self.paint(RESET),
+                        self.paint(BOLD),
+                        self.paint(RESET),
+                        note.message,
+                        gutter = gutter,
+                    ).unwrap();
+                }
+            }
+        }
+        // blank line between diagnostics
+        out.push('\n');
+        out
+    }
+
+    /// Width of line numbers of all snippets in the diagnostic
+    fn gutter_width(&self, diagnostic: &Diagnostic) -> usize {
+        diagnostic
+            .notes
+            .iter()
+            .filter_map(|n| n.span)
+            .chain(std::iter::once(diagnostic.span))
+            .map(|span| span.start.line.max(span.end.line))
+            .max()
+            .unwrap()
+            .to_string()
+            .len()
+    }
+
+    fn snippet(&self, out: &mut String, gutter: usize, span: Span, severity: Severity) {
+        let (gutter_color, reset) = (self.paint(GUTTER), self.paint(RESET));
+        let (line, column) = span.position();
+        let (file, lines) = match self.files.get(span.file.0 as usize) {
+            Some((file, lines)) => (*file, Some(lines)),
+            None => ("<unknown>", None),
+        };
+        writeln!(out, "{:gutter$}{}-->{} {}:{}:{}", "", gutter_color, reset, file, line, column, gutter = gutter).unwrap();
+        let get = |line: u32| lines.and_then(|lines| lines.get((line as usize).wrapping_sub(1)));
+        let text = match get(line) {
+            Some(text) => text,
+            // position outside of the source, e.g. end of file
+            None => return,
+        };
+        let start = (column as usize).saturating_sub(1);
+        writeln!(out, "{:gutter$} {}|{}", "", gutter_color, reset, gutter = gutter).unwrap();
+
+        // a span ending at the start of a line ends with the line before
+        let (end_line, end_text) = match span.end.line {
+            end if end > line && span.end.column <= 1 => (end - 1, get(end - 1)),
+            end => (end, get(end)),
+        };
+        match end_text {
+            Some(end_text) if end_line > line => {
+                // spans over multiple lines show their first and last line
+                let length = text.chars().count().saturating_sub(start).max(1);
+                self.underline(out, gutter, line, text, start..start + length, severity);
+                if end_line > line + 1 {
+                    writeln!(out, "{}...{}", gutter_color, reset).unwrap();
+                }
+                let indent = end_text.chars().take_while(|c| c.is_whitespace()).count();
+                let end = if span.end.line == end_line {
+                    (span.end.column as usize).saturating_sub(1)
+                } else {
+                    end_text.chars().count()
+                };
+                self.underline(out, gutter, end_line, end_text, indent..end.max(indent + 1), severity);
+            }
+            _ => {
+                let length = if span.is_empty() {
+                    token_length(text, start)
+                } else if span.end.line == line {
+                    // a reversed span still marks its start
+                    span.end.column.saturating_sub(column).max(1) as usize
+                } else {
+                    // the end is right after this line, or past the source
+                    text.chars().count().saturating_sub(start).max(1)
+                };
+                self.underline(out, gutter, line, text, start..start + length, severity);
+            }
+        }
+    }
+
+    /// Show a source line with the characters in `chars` underlined
+    fn underline(&self, out: &mut String, gutter: usize, line: u32, text: &str, chars: Range<usize>, severity: Severity) {
+        let (gutter_color, reset) = (self.paint(GUTTER), self.paint(RESET));
+        // tabs are expanded the same way in the text and before the carets
+        let padding = display_width(text.chars().take(chars.start));
+        let width = display_width(text.chars().take(chars.end).skip(chars.start)).max(1);
+        writeln!(out, "{}{:>gutter$} |{} {}", gutter_color, line, reset, text.replace('\t', &" ".repeat(TAB_WIDTH)), gutter = gutter).unwrap();
+        writeln!(
+            out,
+            "{:gutter$} {}|{} {:padding$}{}{}{}",
+            "",
+            gutter_color,
+            reset,
+            "",
+            self.paint(severity.color()),
+            "^".repeat(width),
+            reset,
+            gutter = gutter,
+            padding = padding,
+        ).unwrap();
+    }
+
+    fn paint(&self, code: &'static str) -> &'static str {
+        if self.color { code } else { "" }
+    }
+}
+
+/// Columns taken by characters once tabs are expanded
+fn display_width<I: Iterator<Item = char>>(chars: I) -> usize {
+    chars.map(|c| if c == '\t' { TAB_WIDTH } else { 1 }).sum()
+}
+
+/// Length of the token starting at `start`, used to underline empty spans
+fn token_length(text: &str, start: usize) -> usize {
+    let rest: Vec<char> = text.chars().skip(start).collect();
+    match rest.as_slice() {
+        [] => 1,
+        [c, ..] if c.is_alphanumeric() => rest.iter().take_while(|c| c.is_alphanumeric()).count(),
+        [':', '=', ..] | ['.', '.', ..] => 2,
+        _ => 1,
+    }
+}
+
+impl Diagnose for LexError {
+    fn code(&self) -> &'static str {
+        match self {
+            LexError::InvalidCharacter(_) => "E0101",
+            LexError::UnexpectedCharacter { .. } => "E0102",
+        }
+    }
+}
+
+impl Diagnose for SyntaxError {
+    fn code(&self) -> &'static str {
+        match self {
+            SyntaxError::UnexpectedToken { .. } => "E0201",
+            SyntaxError::UnexpectedEof { .. } => "E0202",
+            SyntaxError::IntegerOverflow(_) => "E0203",
+        }
+    }
+}
+
+impl Diagnose for RuntimeError {
+    fn code(&self) -> &'static str {
+        match self {
+            RuntimeError::ArrayIndexOutbound(..) => "E0501",
+            RuntimeError::DivideByZero => "E0502",
+            RuntimeError::CallDepthExceeded(_) => "E0503",
+            RuntimeError::StackOverflow(_) => "E0504",
+            RuntimeError::UnexpectedEndOfInput => "E0505",
+            RuntimeError::InvalidInput(..) => "E0506",
+            RuntimeError::Io(_) => "E0507",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::Positional;
+    use crate::diagnostic::{Diagnostic, Note, Renderer, Severity};
+    use crate::error::SyntaxError;
+    use crate::span::{FileId, Location, Span};
+    use crate::token::TokenType;
+
+    const SOURCE: &str = "program p\nvar integer abc, abc;\nbegin\n  abc := ;\nend.";
+
+    #[test]
+    fn test_render_error() {
+        let error = Positional::new(4, 10, SyntaxError::UnexpectedToken {
+            expected: vec![TokenType::Int],
+            found: TokenType::Semicolon,
+        });
+        let rendered = Renderer::new(SOURCE, "p.snl").render(&Diagnostic::from(&error));
+        assert_eq!(rendered, "\
+error[E0201]: Expected Int, got Semicolon
+ --> p.snl:4:10
+  |
+4 |   abc := ;
+  |          ^
+
+");
+    }
+
+    #[test]
+    fn test_render_notes() {
+        let diagnostic = Diagnostic {
+            severity: Severity::Warning,
+            code: "E0301",
+            message: "Duplicated identifier 'abc'".to_owned(),
+            span: Span::point(2, 18),
+            notes: vec![
+                Note { message: "previous declaration here".to_owned(), span: Some(Span::point(2, 13)) },
+                Note { message: "identifiers must be unique in a scope".to_owned(), span: None },
+            ],
+        };
+        let rendered = Renderer::new(SOURCE, "p.snl").render(&diagnostic);
+        assert_eq!(rendered, "\
+warning[E0301]: Duplicated identifier 'abc'
+ --> p.snl:2:18
+  |
+2 | var integer abc, abc;
+  |                  ^^^
+note: previous declaration here
+ --> p.snl:2:13
+  |
+2 | var integer abc, abc;
+  |             ^^^
+  = note: identifiers must be unique in a scope
+
+");
+
+        let colored = Renderer::new(SOURCE, "p.snl").with_color(true).render(&diagnostic);
+        assert!(colored.starts_with("\x1b[1;33mwarning[E0301]\x1b[0m"));
+    }
+
+    #[test]
+    fn test_render_span() {
+        let location = |offset, line, column| Location { offset, line, column };
+        let mut renderer = Renderer::new(SOURCE, "p.snl");
+        let file = renderer.add_file("program q\nbegin\n  write(1 + abc)\nend.", "q.snl");
+        let diagnostic = Diagnostic {
+            severity: Severity::Error,
+            code: "E0304",
+            message: "Type mismatch".to_owned(),
+            span: Span { file, start: location(24, 3, 9), end: location(31, 3, 16) },
+            notes: vec![],
+        };
+        assert_eq!(file, FileId(1));
+        assert_eq!(renderer.render(&diagnostic), "\
+error[E0304]: Type mismatch
+ --> q.snl:3:9
+  |
+3 |   write(1 + abc)
+  |         ^^^^^^^
+
+");
+    }
+
+    #[test]
+    fn test_render_reversed_span() {
+        let location = |offset, line, column| Location { offset, line, column };
+        let diagnostic = Diagnostic {
+            severity: Severity::Error,
+            code: "E0304",
+            message: "Type mismatch".to_owned(),
+            span: Span { file: FileId(0), start: location(31, 3, 16), end: location(24, 3, 9) },
+            notes: vec![],
+        };
+        assert_eq!(Renderer::new("program p\nbegin\n  write(1 + abc)\nend.", "p.snl").render(&diagnostic), "\
+error[E0304]: Type mismatch
+ --> p.snl:3:16
+  |
+3 |   write(1 + abc)
+  |                ^
+
+");
+    }
+
+    #[test]
+    fn test_render_tabs() {
+        let source = "program p\nbegin\n\twrite(\ta)\nend.";
+        let diagnostic = Diagnostic {
+            severity: Severity::Error,
+            code: "E0302",
+            message: "Undefined identifier 'a'".to_owned(),
+            span: Span::point(3, 9),
+            notes: vec![],
+        };
+        assert_eq!(Renderer::new(source, "p.snl").render(&diagnostic), "\
+error[E0302]: Undefined identifier 'a'
+ --> p.snl:3:9
+  |
+3 |     write(    a)
+  |               ^
+
+");
+    }
+
+    #[test]
+    fn test_render_multiline_span() {
+        let location = |offset, line, column| Location { offset, line, column };
+        let source = "program p\nbegin\n  write(1 +\n  2 +\n  abc)\nend.";
+        let mut diagnostic = Diagnostic {
+            severity: Severity::Error,
+            code: "E0304",
+            message: "Type mismatch".to_owned(),
+            span: Span { file: FileId(0), start: location(24, 3, 9), end: location(40, 5, 6) },
+            notes: vec![],
+        };
+        assert_eq!(Renderer::new(source, "p.snl").render(&diagnostic), "\
+error[E0304]: Type mismatch
+ --> p.snl:3:9
+  |
+3 |   write(1 +
+  |         ^^^
+...
+5 |   abc)
+  |   ^^^
+
+");
+
+        // ending at the start of a line, the span ends with the line before
+        diagnostic.span.end = location(36, 5, 1);
+        assert_eq!(Renderer::new(source, "p.snl").render(&diagnostic), "\
+error[E0304]: Type mismatch
+ --> p.snl:3:9
+  |
+3 |   write(1 +
+  |         ^^^
+4 |   2 +
+  |   ^^^
+
+");
+    }
+}
@@ -0,0 +1,83 @@
+use crate::input::InputError;
+use crate::token::TokenType;
+
+/// Failure of splitting source text into tokens
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum LexError {
+    /// Character which can not start any token
+    #[error("Invalid character '{0}'")]
+    InvalidCharacter(char),
+    /// Character following the first character of a two-character token does not complete it
+    #[error("Invalid character '{found}' after '{after}', expected '{expected}'")]
+    UnexpectedCharacter { after: char, expected: char, found: char },
+}
+
+/// Failure of parsing tokens into the AST
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum SyntaxError {
+    /// Token found is not in the set of tokens valid at this point
+    #[error("Expected {}, got {found:?}", one_of(.expected))]
+    UnexpectedToken { expected: Vec<TokenType>, found: TokenType },
+    /// Tokens ended before the program is complete
+    #[error("Unexpected end of file, expected {}", one_of(.expected))]
+    UnexpectedEof { expected: Vec<TokenType> },
+    /// Integer literal does not fit in the integer type
+    #[error("Integer '{0}' is too large")]
+    IntegerOverflow(String),
+}
+
+impl SyntaxError {
+    /// Error for finding `found` where one of `expected` should be
+    pub fn unexpected(expected: &[TokenType], found: TokenType) -> Self {
+        if found == TokenType::EOF {
+            SyntaxError::UnexpectedEof { expected: expected.to_vec() }
+        } else {
+            SyntaxError::UnexpectedToken { expected: expected.to_vec(), found }
+        }
+    }
+}
+
+/// Failure of running a program, in the interpreter or the virtual machine
+#[derive(Debug, thiserror::Error)]
+pub enum RuntimeError {
+    /// Array index is lower than lower bound or higher than high bound
+    #[error("Array index({0}) out of bound({1}..{2})")]
+    ArrayIndexOutbound(i64, i64, i64),
+    /// Right hand side of a division evaluated to zero
+    #[error("Division by zero")]
+    DivideByZero,
+    /// Nested calls exceeded the maximum call depth of the interpreter
+    #[error("Stack overflow, call depth exceeded {0}")]
+    CallDepthExceeded(usize),
+    /// Stack grew over the limit of the virtual machine
+    #[error("Stack overflow, stack exceeded {0} cells")]
+    StackOverflow(usize),
+    /// Input stream ended before read finished
+    #[error("Unexpected end of input")]
+    UnexpectedEndOfInput,
+    /// Input could not be converted to the type of the variable read
+    #[error("Invalid input '{0}' for type '{1}'")]
+    InvalidInput(String, &'static str),
+    /// Error reported by the underlying input or output stream
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl RuntimeError {
+    /// Error of reading a value of type `ty`
+    pub fn from_input(error: InputError, ty: &'static str) -> Self {
+        match error {
+            InputError::EndOfInput => RuntimeError::UnexpectedEndOfInput,
+            InputError::Invalid(image) => RuntimeError::InvalidInput(image, ty),
+            InputError::Io(e) => RuntimeError::Io(e),
+        }
+    }
+}
+
+fn one_of(expected: &[TokenType]) -> String {
+    match expected {
+        [] => "nothing".to_owned(),
+        [single] => format!("{:?}", single),
+        _ => format!("one of {}", expected.iter().map(|t| format!("{:?}", t)).collect::<Vec<_>>().join(", ")),
+    }
+}
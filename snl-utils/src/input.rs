@@ -0,0 +1,67 @@
+use std::io::BufRead;
+
+/// Failure of reading the value of a `read` statement
+#[derive(Debug)]
+pub enum InputError {
+    /// Input stream ended before a value was found
+    EndOfInput,
+    /// Characters read can not be converted to the expected type
+    Invalid(String),
+    Io(std::io::Error),
+}
+
+impl From<std::io::Error> for InputError {
+    fn from(error: std::io::Error) -> Self {
+        InputError::Io(error)
+    }
+}
+
+fn peek<R: BufRead>(input: &mut R) -> std::io::Result<Option<u8>> {
+    Ok(input.fill_buf()?.first().copied())
+}
+
+fn skip_whitespace<R: BufRead>(input: &mut R) -> std::io::Result<()> {
+    while let Some(ch) = peek(input)? {
+        if !ch.is_ascii_whitespace() {
+            break;
+        }
+        input.consume(1);
+    }
+    Ok(())
+}
+
+/// Read an optionally negative decimal integer, skipping leading whitespace
+pub fn read_integer<R: BufRead>(input: &mut R) -> Result<i64, InputError> {
+    skip_whitespace(input)?;
+    let mut image = String::new();
+    if let Some(b'-') = peek(input)? {
+        image.push('-');
+        input.consume(1);
+    }
+    while let Some(ch) = peek(input)? {
+        if !ch.is_ascii_digit() {
+            break;
+        }
+        image.push(ch as char);
+        input.consume(1);
+    }
+    if image.is_empty() || image == "-" {
+        match peek(input)? {
+            Some(ch) => image.push(ch as char),
+            None => return Err(InputError::EndOfInput),
+        }
+    }
+    image.parse().map_err(|_| InputError::Invalid(image))
+}
+
+/// Read the next non-whitespace character
+pub fn read_char<R: BufRead>(input: &mut R) -> Result<char, InputError> {
+    skip_whitespace(input)?;
+    match peek(input)? {
+        Some(ch) => {
+            input.consume(1);
+            Ok(ch as char)
+        }
+        None => Err(InputError::EndOfInput),
+    }
+}
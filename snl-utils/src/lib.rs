@@ -1,3 +1,8 @@
 pub mod token;
 pub mod ast;
 pub mod tokens;
+pub mod input;
+pub mod error;
+pub mod diagnostic;
+pub mod span;
+pub mod options;
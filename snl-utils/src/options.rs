@@ -0,0 +1,7 @@
+/// Options shared by the code generating backends
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct CodegenOptions {
+    /// Check indexes of array visits against the array bounds at runtime,
+    /// constant indexes are already checked by the semantic analysis
+    pub bounds_check: bool,
+}
@@ -0,0 +1,51 @@
+use serde::Serialize;
+
+/// Identifier of a source file, files are numbered in the order they are loaded
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default, Serialize)]
+pub struct FileId(pub u32);
+
+/// Place in a source file, `offset` is in bytes while `line` and `column` count characters from 1
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default, Serialize)]
+pub struct Location {
+    pub offset: usize,
+    pub line: u32,
+    pub column: u32,
+}
+
+/// Range of source text, `end` is the location right after the last character
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default, Serialize)]
+pub struct Span {
+    pub file: FileId,
+    pub start: Location,
+    pub end: Location,
+}
+
+impl Span {
+    /// Empty span for places only known by line and column
+    pub fn point(line: u32, column: u32) -> Self {
+        let location = Location { offset: 0, line, column };
+        Self {
+            file: FileId::default(),
+            start: location,
+            end: location,
+        }
+    }
+
+    /// Span from the start of `self` to the end of `end`
+    pub fn to(self, end: Span) -> Self {
+        Self {
+            file: self.file,
+            start: self.start,
+            end: end.end,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Line and column of the start
+    pub fn position(&self) -> (u32, u32) {
+        (self.start.line, self.start.column)
+    }
+}
@@ -1,18 +1,17 @@
 use std::str::FromStr;
 use std::fmt::{Display, Formatter};
+use crate::span::Span;
 
-#[repr(C)]
 #[derive(Debug, Clone)]
 pub struct Token {
     pub token_type: TokenType,
     pub image: String,
-    pub line: u32,
-    pub column: u32,
+    pub span: Span,
 }
 
 impl Display for Token {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}\t", self.line)?;
+        write!(f, "{}\t", self.span.start.line)?;
         write!(f, "{:?}", self.token_type)?;
         match self.token_type {
             TokenType::Identifier | TokenType::Int => {
@@ -26,12 +25,12 @@ impl Display for Token {
 
 impl Token {
     pub fn position(&self) -> (u32, u32) {
-        (self.line, self.column)
+        self.span.position()
     }
 }
 
 #[repr(u8)]
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum TokenType {
     Identifier,
     Int,
@@ -77,6 +76,60 @@ pub enum TokenType {
     EOF,
 }
 
+/// Names of the tokens in the BNF files under `grammar/`, `EOF` stands for the end of input
+const GRAMMAR_NAMES: [(TokenType, &str); 39] = [
+    (TokenType::Identifier, "IDENTIFIER"),
+    (TokenType::Int, "INTC"),
+    (TokenType::Program, "PROGRAM"),
+    (TokenType::Type, "TYPE"),
+    (TokenType::Array, "ARRAY"),
+    (TokenType::Integer, "INTEGER"),
+    (TokenType::Char, "CHAR"),
+    (TokenType::Record, "RECORD"),
+    (TokenType::Of, "OF"),
+    (TokenType::Var, "VAR"),
+    (TokenType::Procedure, "PROCEDURE"),
+    (TokenType::Begin, "BEGIN"),
+    (TokenType::End, "END"),
+    (TokenType::If, "IF"),
+    (TokenType::Then, "THEN"),
+    (TokenType::Else, "ELSE"),
+    (TokenType::Fi, "FI"),
+    (TokenType::While, "WHILE"),
+    (TokenType::Do, "DO"),
+    (TokenType::EndWhile, "ENDWH"),
+    (TokenType::Read, "READ"),
+    (TokenType::Write, "WRITE"),
+    (TokenType::Return, "RETURN"),
+    (TokenType::Add, "ADD"),
+    (TokenType::Minus, "MINUS"),
+    (TokenType::Multiply, "MUL"),
+    (TokenType::Divide, "DIV"),
+    (TokenType::Equal, "EQ"),
+    (TokenType::Assign, "ASSIGN"),
+    (TokenType::Comma, "COMMA"),
+    (TokenType::Semicolon, "SEMICOLON"),
+    (TokenType::LessThan, "LT"),
+    (TokenType::BracketOpen, "BR_OPEN"),
+    (TokenType::BracketClose, "BR_CLOSE"),
+    (TokenType::SquareBracketOpen, "SQBR_OPEN"),
+    (TokenType::SquareBracketClose, "SQBR_CLOSE"),
+    (TokenType::Dot, "DOT"),
+    (TokenType::DotDot, "DOT2"),
+    (TokenType::EOF, "EOF"),
+];
+
+impl TokenType {
+    /// Name of the token in the BNF grammars, e.g. `SQBR_OPEN` for `[`
+    pub fn grammar_name(self) -> &'static str {
+        GRAMMAR_NAMES.iter().find(|(token, _)| *token == self).unwrap().1
+    }
+
+    pub fn from_grammar_name(name: &str) -> Option<Self> {
+        GRAMMAR_NAMES.iter().find(|(_, token)| *token == name).map(|(token, _)| *token)
+    }
+}
+
 impl FromStr for TokenType {
     type Err = String;
 
@@ -1,4 +1,5 @@
 use std::cell::Cell;
+use crate::error::SyntaxError;
 use crate::token::{Token, TokenType};
 
 pub struct Tokens {
@@ -39,17 +40,31 @@ impl Tokens {
         self.look_after_token().map(|t| t.token_type)
     }
 
-    pub fn take(&self, t: TokenType) -> Result<&Token, String> {
+    pub fn take(&self, t: TokenType) -> Result<&Token, SyntaxError> {
         if t == self.current() {
             let token = self.current_token();
             self.pos.set(self.pos.get() + 1);
             Ok(token)
         } else {
-            let now = self.current_token();
-            Err(format!("line: {}, column: {}, expected {:?}, got {:?}", now.line, now.column, t, now.token_type))
+            Err(SyntaxError::unexpected(&[t], self.current()))
         }
     }
 
+    /// Last token taken, the current one if nothing is taken yet
+    pub fn previous_token(&self) -> &Token {
+        self.inner.get(self.pos.get().saturating_sub(1)).unwrap()
+    }
+
+    /// Index of the current token, to be passed to `consumed_since` later
+    pub fn index(&self) -> usize {
+        self.pos.get()
+    }
+
+    /// Tokens taken after `index`
+    pub fn consumed_since(&self, index: usize) -> &[Token] {
+        &self.inner[index..self.pos.get()]
+    }
+
     pub fn move_next(&self) {
         self.pos.set(self.pos.get() + 1);
     }
@@ -0,0 +1,660 @@
+use snl_ir::scope::{self, Binding, Result, Scope};
+use snl_ir::{ScalarType, Type};
+use snl_utils::ast::*;
+use snl_utils::options::CodegenOptions;
+
+/// Bytes of a memory cell, every scalar and every frame slot takes one cell
+const CELL: usize = 8;
+
+/// Variable in the frame of a procedure at `level`, `offset` counts cells from the frame base
+struct Variable {
+    level: usize,
+    offset: usize,
+    is_var: bool,
+    ty: Type,
+}
+
+/// Procedure whose own frame is at `level`
+struct Procedure {
+    level: usize,
+    symbol: String,
+    params: Vec<(bool, Type)>,
+}
+
+struct Generator {
+    scope: Scope<Variable, Procedure>,
+    /// Finished functions
+    functions: Vec<String>,
+    /// Body of the function being generated
+    body: String,
+    indent: usize,
+    /// Nesting level of the block being generated, 0 for the main program
+    level: usize,
+    /// Qualified name of the block being generated
+    path: Vec<String>,
+    options: CodegenOptions,
+}
+
+/// Generate a WebAssembly text module from a semantically checked program.
+///
+/// Frames live in linear memory, growing upwards from address 0 with `$sp` pointing past the
+/// last one. A frame holds the static link in its first cell, followed by parameters and local
+/// variables. Callers write the static link and arguments into the frame at `$sp`, then pass
+/// its address as the only parameter, `$fp`, of the callee.
+///
+/// The host provides `env.read_integer`, `env.read_char`, `env.write_integer`,
+/// `env.write_char`, `env.divide_by_zero` and `env.stack_overflow`, and with bounds checks
+/// `env.index_out_of_bound`; the program runs by calling the exported `main`.
+pub fn generate(program: &Positional<Program>, options: CodegenOptions) -> Result<String> {
+    let mut generator = Generator {
+        scope: Default::default(),
+        functions: Default::default(),
+        body: Default::default(),
+        indent: 2,
+        level: 0,
+        path: Default::default(),
+        options,
+    };
+    generator.generate_block("$program", &program.declare, &program.body, 0)?;
+
+    let mut module = format!(";; program {}\n(module\n", program.name);
+    module.push_str(IMPORTS);
+    if options.bounds_check {
+        module.push_str(BOUNDS_CHECK_IMPORT);
+    }
+    module.push_str(HEADER);
+    if options.bounds_check {
+        module.push_str(BOUNDS_CHECK);
+    }
+    for function in generator.functions.iter() {
+        module.push('\n');
+        module.push_str(function);
+    }
+    module.push_str(")\n");
+    Ok(module)
+}
+
+const IMPORTS: &str = r#"  (import "env" "read_integer" (func $read_integer (result i64)))
+  (import "env" "read_char" (func $read_char (result i64)))
+  (import "env" "write_integer" (func $write_integer (param i64)))
+  (import "env" "write_char" (func $write_char (param i64)))
+  (import "env" "divide_by_zero" (func $divide_by_zero))
+  (import "env" "stack_overflow" (func $stack_overflow))
+"#;
+
+/// Imports have to precede all definitions, so the import of the bounds check is separated
+const BOUNDS_CHECK_IMPORT: &str = r#"  (import "env" "index_out_of_bound" (func $index_out_of_bound (param i64 i64 i64)))
+"#;
+
+const HEADER: &str = r#"
+  (memory (export "memory") 1)
+  (global $sp (mut i32) (i32.const 0))
+
+  ;; move the stack pointer to $top, growing the memory when needed
+  (func $reserve (param $top i32)
+    (local $pages i32)
+    local.get $top
+    i32.const 65535
+    i32.add
+    i32.const 16
+    i32.shr_u
+    memory.size
+    i32.sub
+    local.tee $pages
+    i32.const 0
+    i32.gt_s
+    if
+      local.get $pages
+      memory.grow
+      i32.const -1
+      i32.eq
+      if
+        call $stack_overflow
+        unreachable
+      end
+    end
+    local.get $top
+    global.set $sp)
+
+  ;; integer division, wrapping on overflow like the interpreter
+  (func $divide (param $a i64) (param $b i64) (result i64)
+    local.get $b
+    i64.eqz
+    if
+      call $divide_by_zero
+      unreachable
+    end
+    local.get $b
+    i64.const -1
+    i64.eq
+    if (result i64)
+      i64.const 0
+      local.get $a
+      i64.sub
+    else
+      local.get $a
+      local.get $b
+      i64.div_s
+    end)
+
+  (func (export "main")
+    global.get $sp
+    call $program)
+"#;
+
+/// Added when array indexes are checked at runtime
+const BOUNDS_CHECK: &str = r#"
+  ;; pass $index through, stopping unless $lower <= $index <= $upper
+  (func $check_index (param $index i64) (param $lower i64) (param $upper i64) (result i64)
+    local.get $index
+    local.get $lower
+    i64.lt_s
+    local.get $index
+    local.get $upper
+    i64.gt_s
+    i32.or
+    if
+      local.get $index
+      local.get $lower
+      local.get $upper
+      call $index_out_of_bound
+      unreachable
+    end
+    local.get $index)
+"#;
+
+impl Generator {
+    fn line<S: AsRef<str>>(&mut self, line: S) {
+        for _ in 0..self.indent {
+            self.body.push_str("  ");
+        }
+        self.body.push_str(line.as_ref());
+        self.body.push('\n');
+    }
+
+    /// Open a structured instruction, `if`, `else`, `block` or `loop`
+    fn open<S: AsRef<str>>(&mut self, line: S) {
+        self.line(line);
+        self.indent += 1;
+    }
+
+    fn close(&mut self) {
+        self.indent -= 1;
+        self.line("end");
+    }
+
+    fn generate_block(&mut self, symbol: &str, declare: &ProgramDeclare, body: &StatementList, params: usize) -> Result<()> {
+        for t in declare.type_declare.iter() {
+            let ty = self.scope.resolve_type(&t.base)?;
+            self.scope.insert(t.name().to_owned(), Binding::Type(ty));
+        }
+
+        // the static link and params take the cells before the locals
+        let mut cells = 1 + params;
+        for v in declare.variable_declare.iter() {
+            let ty = self.scope.resolve_type(&v.type_name)?;
+            for name in v.identifiers.iter() {
+                let size = ty.size();
+                self.scope.insert(name.inner().clone(), Binding::Variable(Variable {
+                    level: self.level,
+                    offset: cells,
+                    is_var: false,
+                    ty: ty.clone(),
+                }));
+                cells += size;
+            }
+        }
+
+        for p in declare.procedure_declare.iter() {
+            self.generate_procedure(p)?;
+        }
+
+        self.line(format!(";; frame of {} cells", cells));
+        self.line("local.get $fp");
+        self.line(format!("i32.const {}", cells * CELL));
+        self.line("i32.add");
+        self.line("call $reserve");
+        let locals = cells - 1 - params;
+        if locals > 0 {
+            // the memory of a previous call may be reused, locals are cleared for their initial value
+            self.line("local.get $fp");
+            self.line(format!("i32.const {}", (1 + params) * CELL));
+            self.line("i32.add");
+            self.line("i32.const 0");
+            self.line(format!("i32.const {}", locals * CELL));
+            self.line("memory.fill");
+        }
+        self.generate_statement_list(body)?;
+        self.leave();
+
+        let body = std::mem::take(&mut self.body);
+        self.functions.push(format!("  (func {} (param $fp i32)\n{}  )\n", symbol, body));
+        Ok(())
+    }
+
+    /// Pop the frame and return
+    fn leave(&mut self) {
+        self.line("local.get $fp");
+        self.line("global.set $sp");
+        self.line("return");
+    }
+
+    fn generate_procedure(&mut self, p: &Positional<ProcedureDeclare>) -> Result<()> {
+        let mut params = Vec::new();
+        for param in p.params.iter() {
+            let ty = self.scope.resolve_type(&param.definition.type_name)?;
+            for name in param.definition.identifiers.iter() {
+                params.push((param.is_var, ty.clone(), name));
+            }
+        }
+
+        self.path.push(p.name().to_owned());
+        let symbol = format!("$snl.{}", self.path.join("."));
+        self.scope.insert(p.name().to_owned(), Binding::Procedure(Procedure {
+            level: self.level + 1,
+            symbol: symbol.clone(),
+            params: params.iter().map(|(is_var, ty, _)| (*is_var, ty.clone())).collect(),
+        }));
+
+        self.scope.step_in();
+        self.level += 1;
+        let body = std::mem::take(&mut self.body);
+
+        let mut offset = 1;
+        for (is_var, ty, name) in params {
+            let size = if is_var { 1 } else { ty.size() };
+            self.scope.insert(name.inner().clone(), Binding::Variable(Variable { level: self.level, offset, is_var, ty }));
+            offset += size;
+        }
+        self.generate_block(&symbol, &p.declare, &p.body, offset - 1)?;
+
+        self.body = body;
+        self.level -= 1;
+        self.path.pop();
+        self.scope.step_out();
+        Ok(())
+    }
+
+    fn generate_statement_list(&mut self, list: &StatementList) -> Result<()> {
+        for statement in list.iter() {
+            self.generate_statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn generate_statement(&mut self, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::Conditional(con) => {
+                self.generate_condition(&con.condition)?;
+                self.open("if");
+                self.generate_statement_list(&con.body)?;
+                if !con.else_body.is_empty() {
+                    self.indent -= 1;
+                    self.open("else");
+                    self.generate_statement_list(&con.else_body)?;
+                }
+                self.close();
+            }
+            Statement::Loop(lo) => {
+                self.open("block");
+                self.open("loop");
+                self.generate_condition(&lo.condition)?;
+                self.line("i32.eqz");
+                self.line("br_if 1");
+                self.generate_statement_list(&lo.body)?;
+                self.line("br 0");
+                self.close();
+                self.close();
+            }
+            Statement::Input(input) => {
+                let repr = scope::input_variable(input);
+                let ty = self.generate_access(&repr)?;
+                match scope::scalar(&ty, input)? {
+                    ScalarType::Integer => self.line("call $read_integer"),
+                    ScalarType::Char => self.line("call $read_char"),
+                }
+                self.line("i64.store");
+            }
+            Statement::Output(output) => {
+                match self.generate_expression(output)? {
+                    ScalarType::Integer => self.line("call $write_integer"),
+                    ScalarType::Char => self.line("call $write_char"),
+                }
+            }
+            Statement::Return(ret) => {
+                // the value has no receiver
+                self.generate_expression(ret)?;
+                self.line("drop");
+                self.leave();
+            }
+            Statement::Assign(assign) => {
+                let ty = self.generate_access(&assign.variable)?;
+                if ty.scalar().is_some() {
+                    self.generate_expression(&assign.value)?;
+                    self.line("i64.store");
+                } else {
+                    self.generate_access(scope::structured_source(assign)?)?;
+                    self.line(format!("i32.const {}", ty.size() * CELL));
+                    self.line("memory.copy");
+                }
+            }
+            Statement::Call(call) => self.generate_call(call)?,
+            Statement::Error(error) => return Err(scope::syntax_error(error)),
+        }
+        Ok(())
+    }
+
+    fn generate_call(&mut self, call: &Positional<CallStatement>) -> Result<()> {
+        let Procedure { level, symbol, params } = self.scope.procedure(call)?;
+        let (level, symbol, params) = (*level, symbol.clone(), params.clone());
+
+        // the static link of the callee is the frame of its parent, at `level - 1`
+        self.line("global.get $sp");
+        self.frame(self.level + 1 - level);
+        self.line("i32.store");
+
+        let mut offset = 1;
+        for ((is_var, ty), exp) in params.iter().zip(call.params.iter()) {
+            self.line("global.get $sp");
+            if *is_var || ty.scalar().is_none() {
+                let repr = scope::referenced_argument(exp, *is_var, call.name())?;
+                if *is_var {
+                    self.generate_access(repr)?;
+                    self.line(format!("i32.store offset={}", offset * CELL));
+                    offset += 1;
+                } else {
+                    self.line(format!("i32.const {}", offset * CELL));
+                    self.line("i32.add");
+                    self.generate_access(repr)?;
+                    self.line(format!("i32.const {}", ty.size() * CELL));
+                    self.line("memory.copy");
+                    offset += ty.size();
+                }
+            } else {
+                self.generate_expression(exp)?;
+                self.line(format!("i64.store offset={}", offset * CELL));
+                offset += 1;
+            }
+        }
+        self.line("global.get $sp");
+        self.line(format!("call {}", symbol));
+        Ok(())
+    }
+
+    /// Push the result of a relation as an i32
+    fn generate_condition(&mut self, rel: &RelationExpression) -> Result<()> {
+        self.generate_expression(&rel.left)?;
+        self.generate_expression(&rel.right)?;
+        match rel.op.as_str() {
+            "<" => self.line("i64.lt_s"),
+            "=" => self.line("i64.eq"),
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+
+    fn generate_expression(&mut self, exp: &Expression) -> Result<ScalarType> {
+        match exp {
+            Expression::Binary(binary) => {
+                let left = self.generate_expression(&binary.left)?;
+                let right = self.generate_expression(&binary.right)?;
+                self.generate_operator(binary.op);
+                Ok(result_type(left, right))
+            }
+            Expression::Factor(factor) => self.generate_factor(factor),
+        }
+    }
+
+    fn generate_operator(&mut self, op: BinaryOperator) {
+        match op {
+            BinaryOperator::Add => self.line("i64.add"),
+            BinaryOperator::Sub => self.line("i64.sub"),
+            BinaryOperator::Mul => self.line("i64.mul"),
+            BinaryOperator::Div => self.line("call $divide"),
+        }
+    }
+
+    fn generate_factor(&mut self, factor: &Positional<ExpressionFactor>) -> Result<ScalarType> {
+        match factor.inner() {
+            ExpressionFactor::Bracket(exp) => self.generate_expression(exp),
+            ExpressionFactor::Constant(c) => {
+                self.line(format!("i64.const {}", c));
+                Ok(ScalarType::Integer)
+            }
+            ExpressionFactor::Variable(repr) => {
+                let ty = self.generate_access(repr)?;
+                let ty = scope::scalar(&ty, &repr.base)?;
+                self.line("i64.load");
+                Ok(ty)
+            }
+        }
+    }
+
+    /// Push the frame pointer reached by following `depth` static links
+    fn frame(&mut self, depth: usize) {
+        self.line("local.get $fp");
+        for _ in 0..depth {
+            self.line("i32.load");
+        }
+    }
+
+    /// Push the address of a variable represent, with field and index offsets applied
+    fn generate_access(&mut self, repr: &VariableRepresent) -> Result<Type> {
+        let Variable { level, offset, is_var, ty } = self.scope.variable(&repr.base)?;
+        let (depth, offset, is_var, mut ty) = (self.level - level, *offset, *is_var, ty.clone());
+        self.frame(depth);
+        if is_var {
+            // the cell of a var parameter holds the address of the argument
+            self.line(format!("i32.load offset={}", offset * CELL));
+        } else {
+            self.line(format!("i32.const {}", offset * CELL));
+            self.line("i32.add");
+        }
+
+        if let Some(visit) = &repr.visit {
+            if let Some(field) = &visit.dot {
+                let (offset, field_type) = scope::field(&ty, field)?;
+                let field_type = field_type.clone();
+                if offset != 0 {
+                    self.line(format!("i32.const {}", offset * CELL));
+                    self.line("i32.add");
+                }
+                ty = field_type;
+            }
+
+            if let Some(index) = &visit.sqbr {
+                let (base, lower_bound, upper_bound) = scope::element(&ty, repr, index)?;
+                self.generate_expression(index)?;
+                if self.options.bounds_check && index.constant().is_none() {
+                    self.line(format!("i64.const {}", lower_bound));
+                    self.line(format!("i64.const {}", upper_bound));
+                    self.line("call $check_index");
+                }
+                if lower_bound != 0 {
+                    self.line(format!("i64.const {}", lower_bound));
+                    self.line("i64.sub");
+                }
+                self.line("i32.wrap_i64");
+                self.line(format!("i32.const {}", CELL));
+                self.line("i32.mul");
+                self.line("i32.add");
+                ty = Type::Scalar(base);
+            }
+        }
+        Ok(ty)
+    }
+}
+
+fn result_type(left: ScalarType, right: ScalarType) -> ScalarType {
+    if left == ScalarType::Char && right == ScalarType::Char {
+        ScalarType::Char
+    } else {
+        ScalarType::Integer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generate;
+    use snl_rdp::Parser;
+    use snl_utils::options::CodegenOptions;
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    /// Host implementing the imports, reading the module from argv[1] and input from stdin
+    const HOST: &str = r#"
+const fs = require('fs');
+const input = fs.readFileSync(0, 'latin1');
+let position = 0;
+const skip = () => { while (position < input.length && /\s/.test(input[position])) position++; };
+const fail = (message) => { process.stderr.write(message + '\n'); process.exit(1); };
+const env = {
+  read_integer: () => {
+    skip();
+    const match = /^-?\d+/.exec(input.slice(position));
+    if (!match) fail(position < input.length ? 'Invalid input' : 'Unexpected end of input');
+    position += match[0].length;
+    return BigInt(match[0]);
+  },
+  read_char: () => {
+    skip();
+    if (position >= input.length) fail('Unexpected end of input');
+    return BigInt(input.charCodeAt(position++));
+  },
+  write_integer: (value) => process.stdout.write(value + '\n'),
+  write_char: (value) => process.stdout.write(String.fromCharCode(Number(value)) + '\n'),
+  divide_by_zero: () => fail('Division by zero'),
+  stack_overflow: () => fail('Stack overflow'),
+  index_out_of_bound: (index, lower, upper) => fail(`Array index(${index}) out of bound(${lower}..${upper})`),
+};
+WebAssembly.instantiate(fs.readFileSync(process.argv[1]), { env })
+  .then(({ instance }) => instance.exports.main());
+"#;
+
+    fn wat(source: &str, options: CodegenOptions) -> String {
+        let tokens = snl_lexer::read_tokens(source).unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        generate(&ast, options).unwrap()
+    }
+
+    /// Run the module with node, `None` if it is not installed
+    fn run(source: &str, input: &str, options: CodegenOptions) -> Option<(String, i32)> {
+        let wasm = wat::parse_str(wat(source, options)).unwrap();
+        let path = std::env::temp_dir().join(format!("snl-wasm-{}-{}.wasm", std::process::id(), source.len()));
+        std::fs::write(&path, wasm).unwrap();
+
+        let mut child = Command::new("node")
+            .arg("-e")
+            .arg(HOST)
+            .arg(&path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .ok()?;
+        child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+        let output = child.wait_with_output().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        Some((String::from_utf8(output.stdout).unwrap(), output.status.code().unwrap()))
+    }
+
+    #[test]
+    fn test_frame_pointer() {
+        let wat = wat(r#"program p
+var integer x;
+procedure outer();
+procedure inner();
+begin
+  x := 1
+end
+begin
+  inner()
+end
+begin
+  outer()
+end."#, Default::default());
+        assert!(wat.contains("(func $snl.outer.inner (param $fp i32)\n"));
+        // inner reaches main through the static link of outer
+        assert!(wat.contains("    local.get $fp\n    i32.load\n    i32.load\n    i32.const 8\n    i32.add\n"));
+        wat::parse_str(wat).unwrap();
+    }
+
+    #[test]
+    fn test_run() {
+        let result = run(r#"program nested
+type pair = record
+  integer first;
+  array [1..3] of char tag;
+end;
+var pair p, q;
+    array [2..4] of integer a;
+    integer offset;
+    char c;
+procedure outer(integer base);
+var integer offset;
+procedure inner(integer k);
+begin
+  a[k] := base + offset + k
+end
+procedure swap(var integer x, y);
+var integer t;
+begin
+  t := x;
+  x := y;
+  y := t
+end
+begin
+  offset := 100;
+  inner(2);
+  inner(4);
+  swap(a[2], a[4])
+end
+procedure show(pair r);
+begin
+  write(r.first);
+  write(r.tag[2]);
+  write(0 - r.first / 5)
+end
+begin
+  offset := 7;
+  outer(10);
+  read(c);
+  p.tag[2] := c;
+  p.first := a[4];
+  q := p;
+  show(q);
+  write(a[4]);
+  write(offset)
+end."#, "  x", Default::default());
+        if let Some(result) = result {
+            assert_eq!(result, ("112\nx\n-22\n112\n7\n".to_owned(), 0));
+        }
+    }
+
+    #[test]
+    fn test_bubble() {
+        if let Some(result) = run(include_str!("../../examples/bubble.snl"), "5 3 1 2 3 4", Default::default()) {
+            assert_eq!(result, ("1\n2\n3\n3\n4\n".to_owned(), 0));
+        }
+    }
+
+    #[test]
+    fn test_bounds_check() {
+        let source = r#"program bounds
+var array [1..3] of integer a;
+    integer i;
+begin
+  read(i);
+  a[i] := 1;
+  write(a[i])
+end."#;
+        assert!(!wat(source, Default::default()).contains("index_out_of_bound"));
+        let options = CodegenOptions { bounds_check: true };
+        if let Some(result) = run(source, "3", options) {
+            assert_eq!(result, ("1\n".to_owned(), 0));
+        }
+        if let Some(result) = run(source, "0", options) {
+            assert_eq!(result, (String::new(), 1));
+        }
+    }
+}
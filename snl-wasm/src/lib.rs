@@ -0,0 +1,3 @@
+pub use generator::generate;
+
+mod generator;
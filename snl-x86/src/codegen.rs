@@ -0,0 +1,408 @@
+use crate::runtime::RUNTIME;
+use snl_ir::*;
+use std::fmt::{Display, Formatter, Result};
+
+/// x86-64 assembly for a lowered module, in GNU as (AT&T) syntax.
+///
+/// Every procedure keeps a frame of 8 byte cells below `%rbp`: its variables in IR order
+/// followed by one cell for each temp. Callers pass the static link at `16(%rbp)` and the
+/// argument cells from `24(%rbp)`, which the prologue copies into the parameter variables.
+/// The main program is exported as `main`, so the output links with
+/// `cc out.s -o out`.
+pub struct Assembly<'m> {
+    module: &'m Module,
+}
+
+/// Argument buffered until the call that consumes it
+enum Argument {
+    Cell(Operand),
+    Block(Operand, usize),
+}
+
+impl<'m> Assembly<'m> {
+    pub fn new(module: &'m Module) -> Self {
+        Assembly { module }
+    }
+
+    fn symbol(&self, procedure: ProcedureId) -> String {
+        if procedure == 0 {
+            "main".to_owned()
+        } else {
+            format!("snl.{}", self.module.procedures[procedure].name)
+        }
+    }
+
+    /// Bytes reserved below `%rbp`, kept 16 byte aligned
+    fn frame_bytes(&self, procedure: ProcedureId) -> i64 {
+        let procedure = &self.module.procedures[procedure];
+        let cells = (procedure.frame_size + procedure.temps) as i64;
+        (cells * 8 + 15) / 16 * 16
+    }
+
+    /// Offset from `%rbp` of a frame cell
+    fn cell(&self, procedure: ProcedureId, cell: usize) -> i64 {
+        cell as i64 * 8 - self.frame_bytes(procedure)
+    }
+
+    fn temp(&self, current: ProcedureId, temp: Temp) -> String {
+        format!("{}(%rbp)", self.cell(current, self.module.procedures[current].frame_size + temp))
+    }
+
+    /// Follow `hops` static links from `%rbp` into `%r11`, returns the frame register
+    fn static_chain(&self, hops: usize, f: &mut Formatter<'_>) -> std::result::Result<&'static str, std::fmt::Error> {
+        if hops == 0 {
+            return Ok("%rbp");
+        }
+        writeln!(f, "    movq %rbp, %r11")?;
+        for _ in 0..hops {
+            writeln!(f, "    movq 16(%r11), %r11")?;
+        }
+        Ok("%r11")
+    }
+
+    /// Memory operand of the first cell of a variable, may clobber `%r11`
+    fn variable(&self, current: ProcedureId, variable: VariableId, f: &mut Formatter<'_>) -> std::result::Result<String, std::fmt::Error> {
+        let variable = &self.module.variables[variable];
+        let owner = variable.procedure;
+        let hops = self.module.procedures[current].level - self.module.procedures[owner].level;
+        let frame = self.static_chain(hops, f)?;
+        Ok(format!("{}({})", self.cell(owner, variable.offset), frame))
+    }
+
+    fn load(&self, current: ProcedureId, operand: &Operand, register: &str, f: &mut Formatter<'_>) -> Result {
+        match operand {
+            Operand::Const(c) if *c >= i32::MIN as i64 && *c <= i32::MAX as i64 => writeln!(f, "    movq ${}, {}", c, register),
+            Operand::Const(c) => writeln!(f, "    movabsq ${}, {}", c, register),
+            Operand::Temp(t) => writeln!(f, "    movq {}, {}", self.temp(current, *t), register),
+            Operand::Var(v) => {
+                let cell = self.variable(current, *v, f)?;
+                writeln!(f, "    movq {}, {}", cell, register)
+            }
+        }
+    }
+
+    fn store(&self, current: ProcedureId, place: &Place, register: &str, f: &mut Formatter<'_>) -> Result {
+        match place {
+            Place::Temp(t) => writeln!(f, "    movq {}, {}", register, self.temp(current, *t)),
+            Place::Var(v) => {
+                let cell = self.variable(current, *v, f)?;
+                writeln!(f, "    movq {}, {}", register, cell)
+            }
+        }
+    }
+
+    fn fmt_procedure(&self, id: ProcedureId, f: &mut Formatter<'_>) -> Result {
+        let procedure = &self.module.procedures[id];
+        let symbol = self.symbol(id);
+        let frame_bytes = self.frame_bytes(id);
+
+        writeln!(f)?;
+        writeln!(f, "# {} {} [level {}]", if id == 0 { "program" } else { "procedure" }, procedure.name, procedure.level)?;
+        writeln!(f, "{}:", symbol)?;
+        writeln!(f, "    pushq %rbp")?;
+        writeln!(f, "    movq %rsp, %rbp")?;
+        if frame_bytes > 0 {
+            // clear the frame so every local starts at zero
+            writeln!(f, "    subq ${}, %rsp", frame_bytes)?;
+            writeln!(f, "    movq %rsp, %rdi")?;
+            writeln!(f, "    movq ${}, %rcx", frame_bytes / 8)?;
+            writeln!(f, "    xorl %eax, %eax")?;
+            writeln!(f, "    rep stosq")?;
+        }
+
+        // parameters are the first variables of the frame
+        let param_cells: usize = procedure.params.iter().map(|p| self.module.variables[*p].size()).sum();
+        if param_cells > 0 {
+            writeln!(f, "    leaq 24(%rbp), %rsi")?;
+            writeln!(f, "    leaq {}(%rbp), %rdi", self.cell(id, 0))?;
+            writeln!(f, "    movq ${}, %rcx", param_cells)?;
+            writeln!(f, "    rep movsq")?;
+        }
+
+        let mut arguments = Vec::new();
+        for quad in procedure.code.iter() {
+            self.fmt_quad(id, quad, &mut arguments, f)?;
+        }
+
+        writeln!(f, ".L{}_return:", symbol)?;
+        if id == 0 {
+            writeln!(f, "    xorl %eax, %eax")?;
+        }
+        writeln!(f, "    leave")?;
+        writeln!(f, "    ret")
+    }
+
+    fn fmt_quad(&self, current: ProcedureId, quad: &Quad, arguments: &mut Vec<Argument>, f: &mut Formatter<'_>) -> Result {
+        let symbol = self.symbol(current);
+        let label = |label: &Label| format!(".L{}_{}", symbol, label);
+        match quad {
+            Quad::Label(l) => writeln!(f, "{}:", label(l))?,
+            Quad::Binary { op, dst, lhs, rhs } => {
+                self.load(current, lhs, "%rax", f)?;
+                self.load(current, rhs, "%rcx", f)?;
+                match op {
+                    BinaryOp::Add => writeln!(f, "    addq %rcx, %rax")?,
+                    BinaryOp::Sub => writeln!(f, "    subq %rcx, %rax")?,
+                    BinaryOp::Mul => writeln!(f, "    imulq %rcx, %rax")?,
+                    BinaryOp::Div => {
+                        writeln!(f, "    testq %rcx, %rcx")?;
+                        writeln!(f, "    jz snl_rt_divide_by_zero")?;
+                        // idiv traps on the overflow of MIN / -1, which wraps in the interpreter
+                        writeln!(f, "    cmpq $-1, %rcx")?;
+                        writeln!(f, "    jne 1f")?;
+                        writeln!(f, "    negq %rax")?;
+                        writeln!(f, "    jmp 2f")?;
+                        writeln!(f, "1:")?;
+                        writeln!(f, "    cqto")?;
+                        writeln!(f, "    idivq %rcx")?;
+                        writeln!(f, "2:")?;
+                    }
+                }
+                self.store(current, &Place::Temp(*dst), "%rax", f)?;
+            }
+            Quad::Copy { dst, src } => {
+                self.load(current, src, "%rax", f)?;
+                self.store(current, dst, "%rax", f)?;
+            }
+            Quad::AddressOf { dst, var } => {
+                let cell = self.variable(current, *var, f)?;
+                writeln!(f, "    leaq {}, %rax", cell)?;
+                self.store(current, &Place::Temp(*dst), "%rax", f)?;
+            }
+            Quad::Offset { dst, base, index } => {
+                self.load(current, base, "%rax", f)?;
+                self.load(current, index, "%rcx", f)?;
+                writeln!(f, "    leaq (%rax,%rcx,8), %rax")?;
+                self.store(current, &Place::Temp(*dst), "%rax", f)?;
+            }
+            Quad::Load { dst, addr } => {
+                self.load(current, addr, "%rax", f)?;
+                writeln!(f, "    movq (%rax), %rax")?;
+                self.store(current, &Place::Temp(*dst), "%rax", f)?;
+            }
+            Quad::Store { addr, src } => {
+                self.load(current, addr, "%rcx", f)?;
+                self.load(current, src, "%rax", f)?;
+                writeln!(f, "    movq %rax, (%rcx)")?;
+            }
+            Quad::CopyBlock { dst, src, size } => {
+                self.load(current, dst, "%rdi", f)?;
+                self.load(current, src, "%rsi", f)?;
+                writeln!(f, "    movq ${}, %rcx", size)?;
+                writeln!(f, "    rep movsq")?;
+            }
+            Quad::BoundsCheck { index, lower_bound, upper_bound } => {
+                self.load(current, index, "%rax", f)?;
+                self.load(current, &Operand::Const(*lower_bound as i64), "%rcx", f)?;
+                writeln!(f, "    cmpq %rcx, %rax")?;
+                writeln!(f, "    jl snl_rt_index_out_of_bound")?;
+                self.load(current, &Operand::Const(*upper_bound as i64), "%rcx", f)?;
+                writeln!(f, "    cmpq %rcx, %rax")?;
+                writeln!(f, "    jg snl_rt_index_out_of_bound")?;
+            }
+            Quad::Jump(l) => writeln!(f, "    jmp {}", label(l))?,
+            Quad::Branch { rel, lhs, rhs, target } => {
+                self.load(current, lhs, "%rax", f)?;
+                self.load(current, rhs, "%rcx", f)?;
+                writeln!(f, "    cmpq %rcx, %rax")?;
+                let jump = match rel {
+                    RelOp::Lt => "jl",
+                    RelOp::Le => "jle",
+                    RelOp::Gt => "jg",
+                    RelOp::Ge => "jge",
+                    RelOp::Eq => "je",
+                    RelOp::Ne => "jne",
+                };
+                writeln!(f, "    {} {}", jump, label(target))?;
+            }
+            Quad::Param(value) => arguments.push(Argument::Cell(*value)),
+            Quad::ParamBlock { addr, size } => arguments.push(Argument::Block(*addr, *size)),
+            Quad::Call { procedure, argc } => {
+                let arguments: Vec<_> = arguments.drain(arguments.len() - argc..).collect();
+                let cells: usize = arguments.iter()
+                    .map(|a| match a {
+                        Argument::Cell(_) => 1,
+                        Argument::Block(_, size) => *size,
+                    })
+                    .sum();
+                // static link and arguments, keeping the stack 16 byte aligned
+                let bytes = ((cells + 1) * 8).div_ceil(16) * 16;
+                writeln!(f, "    subq ${}, %rsp", bytes)?;
+                let mut offset = 8;
+                for argument in arguments {
+                    match argument {
+                        Argument::Cell(value) => {
+                            self.load(current, &value, "%rax", f)?;
+                            writeln!(f, "    movq %rax, {}(%rsp)", offset)?;
+                            offset += 8;
+                        }
+                        Argument::Block(addr, size) => {
+                            self.load(current, &addr, "%rsi", f)?;
+                            writeln!(f, "    leaq {}(%rsp), %rdi", offset)?;
+                            writeln!(f, "    movq ${}, %rcx", size)?;
+                            writeln!(f, "    rep movsq")?;
+                            offset += size * 8;
+                        }
+                    }
+                }
+                // the static link is the frame of the enclosing procedure of the callee
+                let callee = &self.module.procedures[*procedure];
+                let hops = self.module.procedures[current].level + 1 - callee.level;
+                let frame = self.static_chain(hops, f)?;
+                writeln!(f, "    movq {}, (%rsp)", frame)?;
+                writeln!(f, "    call {}", self.symbol(*procedure))?;
+                writeln!(f, "    addq ${}, %rsp", bytes)?;
+            }
+            // a returned value has no receiver
+            Quad::Return(_) => writeln!(f, "    jmp .L{}_return", symbol)?,
+            Quad::Read { dst, ty } => {
+                match ty {
+                    ScalarType::Integer => writeln!(f, "    call snl_rt_read_integer")?,
+                    ScalarType::Char => writeln!(f, "    call snl_rt_read_char")?,
+                }
+                self.store(current, &Place::Temp(*dst), "%rax", f)?;
+            }
+            Quad::Write { src, ty } => {
+                self.load(current, src, "%rdi", f)?;
+                match ty {
+                    ScalarType::Integer => writeln!(f, "    call snl_rt_write_integer")?,
+                    ScalarType::Char => writeln!(f, "    call snl_rt_write_char")?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Assembly<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        writeln!(f, "# program {}", self.module.name)?;
+        writeln!(f, "    .text")?;
+        writeln!(f, "    .globl main")?;
+        for id in 0..self.module.procedures.len() {
+            self.fmt_procedure(id, f)?;
+        }
+        write!(f, "{}", RUNTIME)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Assembly;
+    use snl_rdp::Parser;
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    fn assemble(source: &str) -> String {
+        let tokens = snl_lexer::read_tokens(source).unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        Assembly::new(&snl_ir::lower(&ast, Default::default()).unwrap()).to_string()
+    }
+
+    /// Assemble, link and run with the system C compiler, `None` if it is not installed
+    fn run(source: &str, input: &str) -> Option<(String, i32)> {
+        let dir = std::env::temp_dir().join(format!("snl-x86-{}-{}", std::process::id(), source.len()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (asm, exe) = (dir.join("out.s"), dir.join("out"));
+        std::fs::write(&asm, assemble(source)).unwrap();
+        let status = Command::new("cc").arg(&asm).arg("-o").arg(&exe).status().ok()?;
+        assert!(status.success());
+
+        let mut child = Command::new(&exe)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+        let output = child.wait_with_output().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        Some((String::from_utf8(output.stdout).unwrap(), output.status.code().unwrap()))
+    }
+
+    #[test]
+    fn test_static_link() {
+        let asm = assemble(r#"program p
+var integer x;
+procedure outer();
+procedure inner();
+begin
+  x := 1
+end
+begin
+  inner()
+end
+begin
+  outer()
+end."#);
+        // inner reaches main through the frame of outer
+        assert!(asm.contains("snl.outer.inner:\n"));
+        assert!(asm.contains("    movq %rbp, %r11\n    movq 16(%r11), %r11\n    movq 16(%r11), %r11\n"));
+    }
+
+    #[test]
+    fn test_run() {
+        let result = run(r#"program nested
+type pair = record
+  integer first;
+  array [1..3] of char tag;
+end;
+var pair p, q;
+    array [2..4] of integer a;
+    integer offset;
+    char c;
+procedure outer(integer base);
+var integer offset;
+procedure inner(integer k);
+begin
+  a[k] := base + offset + k
+end
+procedure swap(var integer x, y);
+var integer t;
+begin
+  t := x;
+  x := y;
+  y := t
+end
+begin
+  offset := 100;
+  inner(2);
+  inner(4);
+  swap(a[2], a[4])
+end
+procedure show(pair r);
+begin
+  write(r.first);
+  write(r.tag[2]);
+  write(0 - r.first / 5)
+end
+begin
+  offset := 7;
+  outer(10);
+  read(c);
+  p.tag[2] := c;
+  p.first := a[4];
+  q := p;
+  show(q);
+  write(a[4]);
+  write(offset)
+end."#, "  x");
+        if let Some(result) = result {
+            assert_eq!(result, ("112\nx\n-22\n112\n7\n".to_owned(), 0));
+        }
+    }
+
+    #[test]
+    fn test_runtime_error() {
+        let source = r#"program p
+var integer n;
+begin
+  read(n);
+  write(n);
+  write(10 / (n - n))
+end."#;
+        if let Some(result) = run(source, "-42") {
+            assert_eq!(result, ("-42\n".to_owned(), 1));
+        }
+    }
+}
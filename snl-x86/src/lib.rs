@@ -0,0 +1,4 @@
+pub use codegen::Assembly;
+
+mod codegen;
+mod runtime;
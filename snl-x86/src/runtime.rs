@@ -0,0 +1,189 @@
+/// Runtime bundled into every generated file.
+///
+/// Only Linux system calls are used, so the output links with or without libc.
+/// Input is read one byte at a time with a single byte of lookahead, following
+/// `snl_utils::input`: integers and chars skip leading whitespace.
+pub const RUNTIME: &str = r#"
+# ---- runtime ----
+
+# rax = next byte of stdin without consuming it, -1 at end of input
+snl_rt_peek:
+    cmpb $0, snl_rt_has_byte(%rip)
+    jne 1f
+    xorl %eax, %eax
+    xorl %edi, %edi
+    leaq snl_rt_byte(%rip), %rsi
+    movl $1, %edx
+    syscall
+    cmpq $1, %rax
+    jne 2f
+    movb $1, snl_rt_has_byte(%rip)
+1:
+    movzbq snl_rt_byte(%rip), %rax
+    ret
+2:
+    movq $-1, %rax
+    ret
+
+snl_rt_consume:
+    movb $0, snl_rt_has_byte(%rip)
+    ret
+
+snl_rt_skip_whitespace:
+    call snl_rt_peek
+    cmpq $32, %rax
+    je 1f
+    cmpq $9, %rax
+    je 1f
+    cmpq $10, %rax
+    je 1f
+    cmpq $12, %rax
+    je 1f
+    cmpq $13, %rax
+    je 1f
+    ret
+1:
+    call snl_rt_consume
+    jmp snl_rt_skip_whitespace
+
+snl_rt_read_char:
+    call snl_rt_skip_whitespace
+    call snl_rt_peek
+    cmpq $-1, %rax
+    je snl_rt_end_of_input
+    movb $0, snl_rt_has_byte(%rip)
+    ret
+
+snl_rt_read_integer:
+    pushq %rbx
+    pushq %r12
+    pushq %r13
+    call snl_rt_skip_whitespace
+    xorl %ebx, %ebx
+    xorl %r12d, %r12d
+    xorl %r13d, %r13d
+    call snl_rt_peek
+    cmpq $45, %rax
+    jne 1f
+    movl $1, %r12d
+    call snl_rt_consume
+1:
+    call snl_rt_peek
+    cmpq $48, %rax
+    jl 2f
+    cmpq $57, %rax
+    jg 2f
+    imulq $10, %rbx
+    subq $48, %rax
+    addq %rax, %rbx
+    incq %r13
+    call snl_rt_consume
+    jmp 1b
+2:
+    testq %r13, %r13
+    jz 4f
+    testq %r12, %r12
+    jz 3f
+    negq %rbx
+3:
+    movq %rbx, %rax
+    popq %r13
+    popq %r12
+    popq %rbx
+    ret
+4:
+    cmpq $-1, %rax
+    je snl_rt_end_of_input
+    leaq snl_rt_invalid_input_message(%rip), %rsi
+    movl $snl_rt_invalid_input_length, %edx
+    jmp snl_rt_error
+
+# write rdi in decimal followed by a newline
+snl_rt_write_integer:
+    leaq snl_rt_buffer+31(%rip), %rsi
+    movb $10, (%rsi)
+    movq %rdi, %rax
+    testq %rax, %rax
+    jns 1f
+    negq %rax
+1:
+    xorl %edx, %edx
+    movl $10, %ecx
+    divq %rcx
+    addb $48, %dl
+    decq %rsi
+    movb %dl, (%rsi)
+    testq %rax, %rax
+    jnz 1b
+    testq %rdi, %rdi
+    jns 2f
+    decq %rsi
+    movb $45, (%rsi)
+2:
+    leaq snl_rt_buffer+32(%rip), %rdx
+    subq %rsi, %rdx
+    movl $1, %eax
+    movl $1, %edi
+    syscall
+    ret
+
+# write the low byte of rdi followed by a newline
+snl_rt_write_char:
+    leaq snl_rt_buffer(%rip), %rsi
+    movb %dil, (%rsi)
+    movb $10, 1(%rsi)
+    movl $1, %eax
+    movl $1, %edi
+    movl $2, %edx
+    syscall
+    ret
+
+snl_rt_divide_by_zero:
+    leaq snl_rt_divide_by_zero_message(%rip), %rsi
+    movl $snl_rt_divide_by_zero_length, %edx
+    jmp snl_rt_error
+
+snl_rt_index_out_of_bound:
+    leaq snl_rt_index_out_of_bound_message(%rip), %rsi
+    movl $snl_rt_index_out_of_bound_length, %edx
+    jmp snl_rt_error
+
+snl_rt_end_of_input:
+    leaq snl_rt_end_of_input_message(%rip), %rsi
+    movl $snl_rt_end_of_input_length, %edx
+    jmp snl_rt_error
+
+# print the message at rsi with length rdx to stderr and exit with status 1
+snl_rt_error:
+    movl $1, %eax
+    movl $2, %edi
+    syscall
+    movl $60, %eax
+    movl $1, %edi
+    syscall
+
+    .section .rodata
+snl_rt_divide_by_zero_message:
+    .ascii "Runtime error: Division by zero\n"
+    .set snl_rt_divide_by_zero_length, . - snl_rt_divide_by_zero_message
+snl_rt_index_out_of_bound_message:
+    .ascii "Runtime error: Array index out of bound\n"
+    .set snl_rt_index_out_of_bound_length, . - snl_rt_index_out_of_bound_message
+snl_rt_end_of_input_message:
+    .ascii "Runtime error: Unexpected end of input\n"
+    .set snl_rt_end_of_input_length, . - snl_rt_end_of_input_message
+snl_rt_invalid_input_message:
+    .ascii "Runtime error: Invalid input for type 'integer'\n"
+    .set snl_rt_invalid_input_length, . - snl_rt_invalid_input_message
+
+    .bss
+snl_rt_byte:
+    .zero 1
+snl_rt_has_byte:
+    .zero 1
+    .balign 8
+snl_rt_buffer:
+    .zero 32
+
+    .section .note.GNU-stack,"",@progbits
+"#;
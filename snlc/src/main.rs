@@ -1,15 +1,26 @@
-use clap::{App, Arg};
-use std::io::Read;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use std::io::{IsTerminal, Read};
+use std::path::{Path, PathBuf};
 use std::process::exit;
 use snl_semantic::Semantic;
+use snl_semantic::error::Warning;
+use snl_semantic::lint::{Level, Lint, Lints};
+use snl_interpreter::Interpreter;
+use snl_utils::ast::Positional;
+use snl_utils::diagnostic::{Diagnostic, Renderer, Severity};
+use snl_utils::options::CodegenOptions;
+
+/// Stack size of the thread running the interpreter, nested SNL calls recurse on the host stack
+const INTERPRETER_STACK_SIZE: usize = 64 * 1024 * 1024;
 
 fn main() {
     let matches = App::new("SNL Compiler")
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(Arg::with_name("mode")
             .long("mode")
             .required(true)
             .takes_value(true)
-            .possible_values(&["lex", "parse", "semantic"])
+            .possible_values(&["lex", "parse", "cst", "semantic", "run", "ir", "opt", "bytecode", "cfg"])
             .default_value("semantic")
         )
         .arg(Arg::with_name("lexer")
@@ -25,19 +36,125 @@ fn main() {
             .short("p")
             .required(false)
             .takes_value(true)
-            // Recursive descent parser or LL(1) parser
-            .possible_values(&["rdp", "ll1"])
+            // Recursive descent parser, LL(1) parser or LALR(1) parser
+            .possible_values(&["rdp", "ll1", "lr"])
             .default_value("rdp")
+            .help("Parser to use, `--mode cst` defaults to ll1 as only it builds the parse tree")
+        )
+        .arg(Arg::with_name("trace")
+            .long("trace")
+            .help("Print each step of the LL(1) parser to stderr")
+        )
+        .arg(Arg::with_name("format")
+            .long("format")
+            .takes_value(true)
+            .possible_values(&["json", "dot"])
+            .default_value("json")
+            .help("Format of the parse tree printed by `--mode cst`")
+        )
+        .arg(Arg::with_name("emit")
+            .long("emit")
+            .required(false)
+            .takes_value(true)
+            .possible_values(&["asm", "wat", "c", "llvm"])
+            .help("Generate target code instead of running the selected mode")
+        )
+        .arg(Arg::with_name("output")
+            .long("output")
+            .short("o")
+            .required(false)
+            .takes_value(true)
+            .help("Output file, defaults to the input file name with the extension of the output")
         )
+        .arg(Arg::with_name("bounds-check")
+            .long("bounds-check")
+            .help("Check array indexes which are not constant against the bounds at runtime in generated code")
+        )
+        .arg(Arg::with_name("optimize")
+            .long("optimize")
+            .short("O")
+            .help("Fold and propagate constants in the three-address code used by `--mode ir` and `--emit asm`")
+        )
+        .arg(Arg::with_name("color")
+            .long("color")
+            .takes_value(true)
+            .possible_values(&["auto", "always", "never"])
+            .default_value("auto")
+            .help("Colour diagnostics, auto colours them when writing to a terminal")
+        )
+        .arg(lint_arg("warn", "W", "Report the lint as a warning, `warnings` selects all of them"))
+        .arg(lint_arg("allow", "A", "Do not report the lint, `warnings` selects all of them"))
+        .arg(lint_arg("deny", "D", "Report the lint as an error, `warnings` selects all of them"))
         .arg(Arg::with_name("filename")
             .required(true)
             .takes_value(true)
             .empty_values(false)
             .multiple(false)
         )
+        .subcommand(SubCommand::with_name("grammar")
+            .about("Inspect the grammars of the LL(1) and LR parsers")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(SubCommand::with_name("check")
+                .about("Print the FIRST, FOLLOW and PREDICT sets of a grammar and its LL(1) conflicts")
+                .arg(Arg::with_name("grammar")
+                    .takes_value(true)
+                    .help("BNF file, defaults to the grammar the LL(1) parser is generated from")
+                )
+            )
+            .subcommand(SubCommand::with_name("lr")
+                .about("Build the LR tables of a grammar and print their conflicts")
+                .arg(Arg::with_name("method")
+                    .long("method")
+                    .takes_value(true)
+                    .possible_values(&["slr", "lalr"])
+                    .default_value("lalr")
+                )
+                .arg(Arg::with_name("grammar")
+                    .takes_value(true)
+                    .help("EBNF file, defaults to the grammar the LR parser is generated from")
+                )
+            )
+        )
         .get_matches();
 
+    if let ("grammar", Some(grammar)) = matches.subcommand() {
+        match grammar.subcommand() {
+            ("check", Some(check)) => exit(check_grammar(check.value_of("grammar"))),
+            ("lr", Some(lr)) => exit(check_lr_grammar(lr.value_of("grammar"), lr.value_of("method").unwrap())),
+            _ => unreachable!(),
+        }
+    }
+
     let mode = matches.value_of("mode").unwrap();
+    // only the three-address code is optimized, other outputs would silently ignore `-O`
+    if matches.is_present("optimize") {
+        let optimized = match matches.value_of("emit") {
+            Some(target) => target == "asm",
+            None => mode == "ir",
+        };
+        if !optimized {
+            eprintln!("`--optimize` only applies to `--mode ir` and `--emit asm`");
+            exit(1);
+        }
+    }
+    let parser = if mode == "cst" && matches.occurrences_of("parser") == 0 {
+        Some("ll1")
+    } else {
+        matches.value_of("parser")
+    };
+    if mode == "cst" && parser != Some("ll1") {
+        eprintln!("The concrete parse tree is only built by the LL(1) parser, use `--parser ll1`");
+        exit(1);
+    }
+    if matches.is_present("trace") && (parser != Some("ll1") || mode == "lex") {
+        eprintln!("`--trace` only applies when parsing with `--parser ll1`");
+        exit(1);
+    }
+    // `--format` has a default, only an explicit one can be misplaced
+    if matches.occurrences_of("format") > 0 && mode != "cst" {
+        eprintln!("`--format` only applies to `--mode cst`");
+        exit(1);
+    }
     let file = matches.value_of("filename").unwrap();
     let input = if file == "-" {
         let mut data = String::new();
@@ -50,9 +167,20 @@ fn main() {
         data
     };
 
+    let file_name = if file == "-" { "<stdin>" } else { file };
+    let color = matches.value_of("color").unwrap();
+    let renderer = Renderer::new(&input, file_name)
+        .with_color(color_enabled(color, std::io::stderr().is_terminal()));
+
     let tokens = match matches.value_of("lexer").unwrap() {
         "rs" => {
-            snl_lexer::read_tokens(&input).unwrap()
+            match snl_lexer::read_tokens(&input) {
+                Ok(tokens) => tokens,
+                Err(error) => {
+                    eprint!("{}", renderer.render(&Diagnostic::from(&error)));
+                    exit(1);
+                }
+            }
         }
         "c" => {
             unimplemented!()
@@ -66,15 +194,54 @@ fn main() {
         exit(0);
     }
 
-    let ast = match matches.value_of("parser") {
+    let ast = match parser {
         Some("rdp") => {
             let parser = snl_rdp::Parser::new(tokens);
-            parser.parse().expect("Failed to parse")
+            let (ast, errors) = parser.parse_with_errors();
+            if !errors.is_empty() {
+                for error in errors.iter() {
+                    eprint!("{}", renderer.render(&Diagnostic::from(error)));
+                }
+                // the partial AST is still useful when only parsing
+                if mode == "parse" {
+                    println!("{}", serde_json::to_string(&ast).unwrap());
+                }
+                exit(1);
+            }
+            ast
         }
         Some("ll1") => {
-            let mut parser = snl_ll1::Parser::new(tokens);
-            parser.parse().expect("Failed to parse")
+            let mut parser = snl_ll1::Parser::new(tokens).with_trace(matches.is_present("trace"));
+            let result = parser.parse_with_errors();
+            // stdout is left to the tree or AST printed by the mode
+            if matches.is_present("trace") {
+                eprint!("{}", snl_ll1::trace_table(parser.trace()));
+            }
+            if mode == "cst" && result.is_ok() {
+                let tree = parser.parse_tree();
+                match matches.value_of("format").unwrap() {
+                    "dot" => print!("{}", tree.to_dot()),
+                    _ => println!("{}", serde_json::to_string(&tree).unwrap()),
+                }
+                exit(0);
+            }
+            match result {
+                Ok(ast) => ast,
+                Err(errors) => {
+                    for error in errors.iter() {
+                        eprint!("{}", renderer.render(&Diagnostic::from(error)));
+                    }
+                    exit(1);
+                }
+            }
         }
+        Some("lr") => match snl_lr::Parser::new(tokens).parse() {
+            Ok(ast) => ast,
+            Err(error) => {
+                eprint!("{}", renderer.render(&Diagnostic::from(&error)));
+                exit(1);
+            }
+        },
         None => panic!("no parser specified"),
         _ => unreachable!(),
     };
@@ -83,13 +250,230 @@ fn main() {
         exit(0);
     }
 
-    assert_eq!(mode, "semantic");
-    let errors = Semantic::new(ast).analyze();
-    if errors.is_empty() {
-        println!("No semantic error!");
-    } else {
-        for error in errors {
-            println!("At line {}, column {}:\t{}", error.line, error.column, error.inner());
+    let lints = lints(&matches);
+    let (errors, warnings) = Semantic::new(&ast).with_lints(lints.clone()).analyze();
+    let failed = !errors.is_empty() || warnings.iter().any(|warning| lints.severity(warning) == Severity::Error);
+    // denied lints turn their warnings into errors
+    let warning = |warning: &Positional<Warning>| {
+        let mut diagnostic = Diagnostic::from(warning);
+        diagnostic.severity = lints.severity(warning);
+        diagnostic
+    };
+    let diagnostics: Vec<_> = errors.iter().map(Diagnostic::from).chain(warnings.iter().map(warning)).collect();
+    let emit = matches.value_of("emit");
+    if mode == "semantic" && emit.is_none() {
+        let renderer = Renderer::new(&input, file_name)
+            .with_color(color_enabled(color, std::io::stdout().is_terminal()));
+        for diagnostic in diagnostics.iter() {
+            print!("{}", renderer.render(diagnostic));
+        }
+        if !failed {
+            println!("No semantic error!");
+        }
+        exit(0);
+    }
+    // warnings are shown without stopping
+    for diagnostic in diagnostics.iter() {
+        eprint!("{}", renderer.render(diagnostic));
+    }
+    if failed {
+        exit(1);
+    }
+
+    let options = CodegenOptions {
+        bounds_check: matches.is_present("bounds-check"),
+    };
+    let optimize = matches.is_present("optimize");
+    if let Some(target) = emit {
+        let result = match target {
+            "asm" => lower(&ast, options, optimize).map(|module| (snl_x86::Assembly::new(&module).to_string(), "s")),
+            "wat" => snl_wasm::generate(&ast, options).map(|code| (code, "wat")),
+            "c" => snl_c::generate(&ast, options).map(|code| (code, "c")),
+            "llvm" => snl_llvm::generate(&ast, options).map(|code| (code, "ll")),
+            _ => unreachable!(),
+        };
+        let (code, extension) = match result {
+            Ok(result) => result,
+            Err(error) => {
+                eprint!("{}", renderer.render(&Diagnostic::from(&error)));
+                exit(1);
+            }
+        };
+        let output = output_path(matches.value_of("output"), file, extension);
+        std::fs::write(&output, code).expect("Failed to write output file");
+        exit(0);
+    }
+
+    if mode == "cfg" {
+        print!("{}", snl_cfg::to_dot(&snl_cfg::build(&ast)));
+        exit(0);
+    }
+
+    if mode == "ir" || mode == "opt" {
+        match lower(&ast, options, optimize) {
+            Ok(module) if mode == "opt" => {
+                // the module before and after optimization
+                let mut optimized = module.clone();
+                snl_ir::optimize(&mut optimized);
+                println!("; before optimization");
+                print!("{}", module);
+                println!();
+                println!("; after optimization");
+                print!("{}", optimized);
+            }
+            Ok(module) => print!("{}", module),
+            Err(error) => {
+                eprint!("{}", renderer.render(&Diagnostic::from(&error)));
+                exit(1);
+            }
+        }
+        exit(0);
+    }
+
+    if mode == "bytecode" {
+        let bytecode = match snl_pcode::compile(&ast, options) {
+            Ok(bytecode) => bytecode,
+            Err(error) => {
+                eprint!("{}", renderer.render(&Diagnostic::from(&error)));
+                exit(1);
+            }
+        };
+        let output = output_path(matches.value_of("output"), file, "snlb");
+        std::fs::write(&output, bytecode.to_bytes()).expect("Failed to write output file");
+        exit(0);
+    }
+
+    assert_eq!(mode, "run");
+    let result = std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .stack_size(INTERPRETER_STACK_SIZE)
+            .spawn_scoped(scope, || {
+                let stdin = std::io::stdin();
+                let stdout = std::io::stdout();
+                Interpreter::new(&ast, stdin.lock(), stdout.lock()).run()
+            })
+            .expect("Failed to start interpreter")
+            .join()
+            .expect("Interpreter panicked")
+    });
+    if let Err(error) = result {
+        eprint!("{}", renderer.render(&Diagnostic::from(&error)));
+        exit(1);
+    }
+}
+
+/// Option setting the level of the lints given to it, e.g. `-D unused-variable`
+fn lint_arg<'a>(name: &'a str, short: &'a str, help: &'a str) -> Arg<'a, 'a> {
+    Arg::with_name(name)
+        .long(name)
+        .short(short)
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .value_name("LINT")
+        .possible_values(&[
+            "warnings",
+            "unreachable-statement",
+            "uninitialized-variable",
+            "unused-variable",
+            "unused-type",
+            "unused-procedure",
+            "unused-parameter",
+            "shadowed-identifier",
+        ])
+        .help(help)
+}
+
+/// Levels of lints from `--warn`, `--allow` and `--deny`, later options override earlier ones
+fn lints(matches: &ArgMatches) -> Lints {
+    let mut options = Vec::new();
+    for (name, level) in &[("warn", Level::Warn), ("allow", Level::Allow), ("deny", Level::Deny)] {
+        if let (Some(indices), Some(values)) = (matches.indices_of(name), matches.values_of(name)) {
+            options.extend(indices.zip(values).map(|(index, value)| (index, value, *level)));
         }
     }
+    options.sort_by_key(|(index, _, _)| *index);
+
+    let mut lints = Lints::default();
+    for (_, value, level) in options {
+        match value {
+            "warnings" => lints.set_all(level),
+            lint => lints.set(lint.parse::<Lint>().unwrap(), level),
+        }
+    }
+    lints
+}
+
+/// Print the sets of a grammar, returns the exit code: 1 if it can not be read or is not LL(1)
+fn check_grammar(file: Option<&str>) -> i32 {
+    let source = match file {
+        Some(file) => std::fs::read_to_string(file).expect("Failed to read grammar"),
+        None => snl_ll1::grammar::SNL_GRAMMAR.to_owned(),
+    };
+    let grammar = match snl_ll1::grammar::Grammar::parse(&source) {
+        Ok(grammar) => grammar,
+        Err(error) => {
+            eprintln!("Invalid grammar: {}", error);
+            return 1;
+        }
+    };
+    print!("{}", grammar.report());
+    let conflicts = grammar.conflicts().len();
+    if conflicts > 0 {
+        eprintln!("Grammar is not LL(1): {} conflicts", conflicts);
+        return 1;
+    }
+    0
+}
+
+/// Print the number of states of the LR tables of a grammar and their conflicts,
+/// returns the exit code: 1 if it can not be read or has conflicts
+fn check_lr_grammar(file: Option<&str>, method: &str) -> i32 {
+    let source = match file {
+        Some(file) => std::fs::read_to_string(file).expect("Failed to read grammar"),
+        None => snl_lr::grammar::SNL_GRAMMAR.to_owned(),
+    };
+    let grammar = match snl_lr::grammar::Grammar::parse(&source) {
+        Ok(grammar) => grammar,
+        Err(error) => {
+            eprintln!("Invalid grammar: {}", error);
+            return 1;
+        }
+    };
+    let method = if method == "slr" { snl_lr::Method::Slr } else { snl_lr::Method::Lalr };
+    let table = snl_lr::Table::new(&grammar, method);
+    print!("{}", table.report(&grammar));
+    let conflicts = table.conflicts().len();
+    if conflicts > 0 {
+        eprintln!("Grammar is not {}: {} conflicts", method, conflicts);
+        return 1;
+    }
+    0
+}
+
+/// Lower the program to three-address code, optimized if asked to
+fn lower(ast: &Positional<snl_utils::ast::Program>, options: CodegenOptions, optimize: bool) -> Result<snl_ir::Module, Positional<snl_ir::Error>> {
+    let mut module = snl_ir::lower(ast, options)?;
+    if optimize {
+        snl_ir::optimize(&mut module);
+    }
+    Ok(module)
+}
+
+/// Path of the output file, derived from the input file unless given explicitly
+fn output_path(output: Option<&str>, input: &str, extension: &str) -> PathBuf {
+    match output {
+        Some(output) => PathBuf::from(output),
+        None if input == "-" => PathBuf::from("out").with_extension(extension),
+        None => Path::new(input).with_extension(extension),
+    }
+}
+
+/// Whether diagnostics written to a stream are coloured, following `--color`
+fn color_enabled(choice: &str, terminal: bool) -> bool {
+    match choice {
+        "always" => true,
+        "never" => false,
+        _ => terminal && std::env::var_os("NO_COLOR").is_none(),
+    }
 }
@@ -0,0 +1,41 @@
+use clap::{App, Arg};
+use std::process::exit;
+use snl_pcode::{Bytecode, Vm};
+
+fn main() {
+    let matches = App::new("SNL Virtual Machine")
+        .arg(Arg::with_name("disassemble")
+            .long("disassemble")
+            .short("d")
+            .help("Print the instructions instead of running them")
+        )
+        .arg(Arg::with_name("filename")
+            .required(true)
+            .takes_value(true)
+            .empty_values(false)
+            .multiple(false)
+        )
+        .get_matches();
+
+    let file = matches.value_of("filename").unwrap();
+    let data = std::fs::read(file).expect("Failed to read file");
+    let bytecode = match Bytecode::from_bytes(&data) {
+        Ok(bytecode) => bytecode,
+        Err(error) => {
+            eprintln!("{}: {}", file, error);
+            exit(1);
+        }
+    };
+
+    if matches.is_present("disassemble") {
+        print!("{}", bytecode);
+        exit(0);
+    }
+
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    if let Err(error) = Vm::new(&bytecode, stdin.lock(), stdout.lock()).run() {
+        eprintln!("Runtime error at line {}, column {}:\t{}", error.line(), error.column(), error.inner());
+        exit(1);
+    }
+}